@@ -0,0 +1,16 @@
+CREATE TABLE IF NOT EXISTS import_profiles (
+    name TEXT PRIMARY KEY,
+    date_position INTEGER NOT NULL,
+    amount_position INTEGER NOT NULL,
+    description_position INTEGER NOT NULL,
+    date_format TEXT NOT NULL DEFAULT '%d/%m/%Y',
+    header_rows INTEGER NOT NULL DEFAULT 1,
+    delimiter TEXT NOT NULL DEFAULT ',',
+    invert_sign BOOLEAN NOT NULL DEFAULT FALSE,
+    created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    updated_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+INSERT INTO import_profiles (name, date_position, amount_position, description_position)
+VALUES ('Amex', 0, 4, 1)
+ON CONFLICT (name) DO NOTHING;
@@ -0,0 +1 @@
+ALTER TABLE payment_transactions ADD COLUMN IF NOT EXISTS external_id TEXT;
@@ -0,0 +1,17 @@
+ALTER TABLE import_profiles
+    ALTER COLUMN date_position DROP NOT NULL,
+    ALTER COLUMN amount_position DROP NOT NULL,
+    ALTER COLUMN description_position DROP NOT NULL,
+    ADD COLUMN IF NOT EXISTS date_headers TEXT[] NOT NULL DEFAULT '{}',
+    ADD COLUMN IF NOT EXISTS amount_headers TEXT[] NOT NULL DEFAULT '{}',
+    ADD COLUMN IF NOT EXISTS description_headers TEXT[] NOT NULL DEFAULT '{}',
+    ADD COLUMN IF NOT EXISTS has_header BOOLEAN NOT NULL DEFAULT TRUE;
+
+-- header_rows counted the header itself, skip_rows only counts the preamble before it
+ALTER TABLE import_profiles RENAME COLUMN header_rows TO skip_rows;
+ALTER TABLE import_profiles ALTER COLUMN skip_rows SET DEFAULT 0;
+UPDATE import_profiles SET has_header = skip_rows > 0, skip_rows = GREATEST(skip_rows - 1, 0);
+
+UPDATE import_profiles
+SET date_headers = '{Date}', amount_headers = '{Amount}', description_headers = '{Description}'
+WHERE name = 'Amex';
@@ -0,0 +1,14 @@
+CREATE TYPE amount_mode AS ENUM ('signed', 'debit_credit', 'indicator');
+
+ALTER TABLE import_profiles
+    ADD COLUMN amount_mode amount_mode NOT NULL DEFAULT 'signed',
+    ADD COLUMN debit_position INTEGER,
+    ADD COLUMN debit_headers TEXT[] NOT NULL DEFAULT '{}',
+    ADD COLUMN credit_position INTEGER,
+    ADD COLUMN credit_headers TEXT[] NOT NULL DEFAULT '{}',
+    ADD COLUMN indicator_position INTEGER,
+    ADD COLUMN indicator_headers TEXT[] NOT NULL DEFAULT '{}',
+    ADD COLUMN debit_indicators TEXT[] NOT NULL DEFAULT '{}';
+
+-- Amex reports purchases as positive amounts
+UPDATE import_profiles SET invert_sign = TRUE WHERE name = 'Amex';
@@ -0,0 +1,9 @@
+ALTER TABLE import_profiles
+    ADD COLUMN date_formats TEXT[] NOT NULL DEFAULT '{%d/%m/%Y}',
+    ADD COLUMN decimal_separator TEXT NOT NULL DEFAULT '.',
+    ADD COLUMN thousands_separator TEXT NOT NULL DEFAULT ',',
+    ADD COLUMN currency_symbols TEXT[] NOT NULL DEFAULT '{£,$,€}';
+
+UPDATE import_profiles SET date_formats = ARRAY[date_format];
+
+ALTER TABLE import_profiles DROP COLUMN date_format;
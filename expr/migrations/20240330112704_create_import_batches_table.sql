@@ -0,0 +1,17 @@
+CREATE TABLE IF NOT EXISTS import_batches (
+    id UUID DEFAULT gen_random_uuid() PRIMARY KEY,
+    filename TEXT,
+    profile TEXT,
+    format TEXT NOT NULL,
+    checksum TEXT NOT NULL,
+    imported_rows INTEGER NOT NULL DEFAULT 0,
+    skipped_rows INTEGER NOT NULL DEFAULT 0,
+    failed_rows INTEGER NOT NULL DEFAULT 0,
+    uploaded_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    rolled_back_at TIMESTAMP
+);
+
+ALTER TABLE payment_transactions
+    ADD COLUMN batch_id UUID REFERENCES import_batches (id);
+
+CREATE INDEX IF NOT EXISTS payment_transactions_batch_id_idx ON payment_transactions (batch_id);
@@ -0,0 +1,5 @@
+ALTER TABLE payment_transactions ADD COLUMN fingerprint TEXT;
+
+CREATE UNIQUE INDEX IF NOT EXISTS payment_transactions_fingerprint_idx ON payment_transactions (fingerprint);
+
+ALTER TABLE import_batches ADD COLUMN duplicate_rows INTEGER NOT NULL DEFAULT 0;
@@ -0,0 +1,36 @@
+CREATE TYPE import_job_state AS ENUM ('queued', 'running', 'done', 'failed');
+
+CREATE TABLE IF NOT EXISTS import_jobs (
+    id UUID DEFAULT gen_random_uuid() PRIMARY KEY,
+    state import_job_state NOT NULL DEFAULT 'queued',
+    imported_rows INTEGER NOT NULL DEFAULT 0,
+    skipped_rows INTEGER NOT NULL DEFAULT 0,
+    failed_rows INTEGER NOT NULL DEFAULT 0,
+    duplicate_rows INTEGER NOT NULL DEFAULT 0,
+    summary JSONB,
+    error TEXT,
+    created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    started_at TIMESTAMP,
+    finished_at TIMESTAMP
+);
+
+CREATE INDEX IF NOT EXISTS import_jobs_state_idx ON import_jobs (state, created_at);
+
+-- the uploaded files, kept until the job has run
+CREATE TABLE IF NOT EXISTS import_job_files (
+    job_id UUID NOT NULL REFERENCES import_jobs (id) ON DELETE CASCADE,
+    position INTEGER NOT NULL,
+    filename TEXT,
+    profile TEXT,
+    on_error TEXT NOT NULL,
+    PRIMARY KEY (job_id, position)
+);
+
+CREATE TABLE IF NOT EXISTS import_job_chunks (
+    job_id UUID NOT NULL,
+    position INTEGER NOT NULL,
+    sequence INTEGER NOT NULL,
+    data BYTEA NOT NULL,
+    PRIMARY KEY (job_id, position, sequence),
+    FOREIGN KEY (job_id, position) REFERENCES import_job_files (job_id, position) ON DELETE CASCADE
+);
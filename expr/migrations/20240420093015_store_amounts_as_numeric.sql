@@ -0,0 +1,7 @@
+-- amounts were floats, which drift by pennies when added up. they were read from
+-- the decimal amounts on statements, so casting them back recovers those digits
+ALTER TABLE payment_transactions
+    ALTER COLUMN amount TYPE NUMERIC USING amount::NUMERIC,
+    ADD COLUMN currency TEXT NOT NULL DEFAULT 'GBP';
+
+ALTER TABLE import_profiles ADD COLUMN currency TEXT NOT NULL DEFAULT 'GBP';
@@ -0,0 +1,9 @@
+-- one unit of base is worth rate units of quote on rate_date, e.g. 1 EUR = 0.85 GBP
+CREATE TABLE IF NOT EXISTS fx_rates (
+    rate_date DATE NOT NULL,
+    base TEXT NOT NULL,
+    quote TEXT NOT NULL,
+    rate NUMERIC NOT NULL CHECK (rate > 0),
+    imported_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    PRIMARY KEY (base, quote, rate_date)
+);
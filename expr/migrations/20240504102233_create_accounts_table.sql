@@ -0,0 +1,35 @@
+CREATE TYPE account_kind AS ENUM ('current', 'credit', 'savings', 'loan');
+
+CREATE TABLE IF NOT EXISTS accounts (
+    id UUID DEFAULT gen_random_uuid() PRIMARY KEY,
+    name TEXT NOT NULL UNIQUE,
+    institution TEXT,
+    kind account_kind NOT NULL DEFAULT 'current',
+    currency TEXT NOT NULL DEFAULT 'GBP',
+    opening_balance NUMERIC NOT NULL DEFAULT 0,
+    closed BOOLEAN NOT NULL DEFAULT FALSE,
+    created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    updated_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+-- transactions imported before accounts existed are put in an account named
+-- after the account their statement named, the same as imports without one
+INSERT INTO accounts (name, currency)
+SELECT account_type, MIN(currency) FROM payment_transactions GROUP BY account_type;
+
+ALTER TABLE payment_transactions ADD COLUMN account_id UUID REFERENCES accounts (id);
+
+UPDATE payment_transactions t SET account_id = a.id FROM accounts a WHERE a.name = t.account_type;
+
+ALTER TABLE payment_transactions ALTER COLUMN account_id SET NOT NULL;
+
+CREATE INDEX IF NOT EXISTS payment_transactions_account_id_idx ON payment_transactions (account_id);
+
+-- fingerprints are scoped to their account so the same rows on two accounts
+-- are not taken for duplicates, see service::parse::fingerprint::link
+UPDATE payment_transactions
+SET fingerprint = encode(sha256(convert_to(account_id::TEXT || '|' || fingerprint, 'UTF8')), 'hex')
+WHERE fingerprint IS NOT NULL;
+
+-- the account chosen for an uploaded file
+ALTER TABLE import_job_files ADD COLUMN account_id UUID REFERENCES accounts (id);
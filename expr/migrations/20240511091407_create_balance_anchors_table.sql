@@ -0,0 +1,12 @@
+-- balances known from statements, the balance at the end of the day. they
+-- override the balance worked out from the transactions before them
+CREATE TABLE IF NOT EXISTS balance_anchors (
+    account_id UUID NOT NULL REFERENCES accounts (id) ON DELETE CASCADE,
+    balance_date DATE NOT NULL,
+    balance NUMERIC NOT NULL,
+    note TEXT,
+    created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    PRIMARY KEY (account_id, balance_date)
+);
+
+CREATE INDEX IF NOT EXISTS payment_transactions_account_date_idx ON payment_transactions (account_id, payment_date);
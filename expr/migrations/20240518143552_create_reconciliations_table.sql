@@ -0,0 +1,33 @@
+CREATE TYPE reconciliation_state AS ENUM ('open', 'finished');
+
+-- a statement checked against the account's transactions
+CREATE TABLE IF NOT EXISTS reconciliations (
+    id UUID DEFAULT gen_random_uuid() PRIMARY KEY,
+    account_id UUID NOT NULL REFERENCES accounts (id),
+    statement_date DATE NOT NULL,
+    closing_balance NUMERIC NOT NULL,
+    state reconciliation_state NOT NULL DEFAULT 'open',
+    created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    finished_at TIMESTAMP
+);
+
+-- an account is reconciled one statement at a time
+CREATE UNIQUE INDEX IF NOT EXISTS reconciliations_open_idx ON reconciliations (account_id) WHERE state = 'open';
+
+CREATE TYPE transaction_status AS ENUM ('uncleared', 'cleared', 'reconciled');
+
+ALTER TABLE payment_transactions
+    ADD COLUMN status transaction_status NOT NULL DEFAULT 'uncleared',
+    ADD COLUMN reconciliation_id UUID REFERENCES reconciliations (id);
+
+-- reconciled transactions match a statement and are never changed again
+CREATE OR REPLACE FUNCTION lock_reconciled_transactions() RETURNS TRIGGER AS $$
+BEGIN
+    RAISE EXCEPTION 'transaction % is reconciled and cannot be changed', OLD.id;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER payment_transactions_reconciled_lock
+    BEFORE UPDATE OR DELETE ON payment_transactions
+    FOR EACH ROW WHEN (OLD.status = 'reconciled')
+    EXECUTE FUNCTION lock_reconciled_transactions();
@@ -0,0 +1,2 @@
+ALTER TABLE import_profiles
+    ADD COLUMN skip_descriptions TEXT[] NOT NULL DEFAULT '{}';
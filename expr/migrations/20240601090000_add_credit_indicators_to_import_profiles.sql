@@ -0,0 +1,4 @@
+-- Indicator profiles saved before this have no credit indicators, their rows
+-- are reported as errors until the values marking money coming in are added
+ALTER TABLE import_profiles
+    ADD COLUMN credit_indicators TEXT[] NOT NULL DEFAULT '{}';
@@ -1,4 +1,6 @@
 use chrono::NaiveDateTime;
+use import::service::parse_service::Money;
+use rust_decimal::Decimal;
 
 use crate::{
     database::textfile::TextFile, models::transaction::CreateTransaction,
@@ -19,11 +21,16 @@ impl Cli {
 
     pub fn start(self) {
         let _new_transaction = CreateTransaction {
-            account_type: format!("Amex"),
+            account_id: None,
+            account_type: "Amex".to_string(),
             payment_date: NaiveDateTime::parse_from_str("2021-01-01 00:00:00", "%Y-%m-%d %H:%M:%S")
                 .unwrap(),
-            amount: 12.34,
-            description: format!("TEST"),
+            amount: Money::new(Decimal::new(1234, 2), "GBP"),
+            description: "TEST".to_string(),
+            external_id: None,
+            category: None,
+            value_date: None,
+            fingerprint: None,
         };
     }
 }
@@ -1,4 +1,7 @@
 pub mod base;
+#[cfg(test)]
+pub mod memory;
 pub mod postgres;
+#[allow(dead_code)]
 pub mod redis;
 pub mod textfile;
@@ -1,13 +1,18 @@
 use std::fmt::{self, Display};
 
+#[allow(clippy::enum_variant_names)]
 pub enum DatabaseError {
     ConnectionError(String),
+    #[allow(dead_code)]
     ClientError(String),
     SaveError(String),
     GetError(String),
     DeleteError(String),
+    #[allow(dead_code)]
     JsonError(String),
+    #[allow(dead_code)]
     StringConversionError(String),
+    #[allow(dead_code)]
     UnknownValueError(String),
 }
 
@@ -34,9 +39,11 @@ impl Display for DatabaseError {
 
 pub trait DatabaseInit {
     async fn connect(&mut self) -> Result<(), DatabaseError>;
+    #[allow(dead_code)]
     async fn disconnect(&mut self) -> Result<(), DatabaseError>;
 }
 
+#[allow(dead_code)]
 pub trait GetId {
     fn get_id(&self) -> String;
 }
@@ -0,0 +1,919 @@
+use std::sync::{Arc, Mutex, MutexGuard};
+
+use chrono::{NaiveDate, NaiveDateTime, Utc};
+use import::service::parse_service::Money;
+use rust_decimal::Decimal;
+use uuid::Uuid;
+
+use crate::{
+    models::{
+        account::{Account, AccountKind, CreateAccount},
+        balance::{BalanceAnchor, CreateBalanceAnchor},
+        batch::{CreateImportBatch, ImportBatch},
+        fx::FxRate,
+        job::{ImportJob, ImportJobFile, JobState},
+        reconciliation::{CreateReconciliation, Reconciliation, ReconciliationState},
+        transaction::{CreateTransaction, Transaction, TransactionStatus},
+    },
+    service::{
+        account::{AccountRead, AccountWrite},
+        balance::{BalanceRead, BalanceWrite},
+        batch::{BatchRead, BatchWrite, ImportSession},
+        fx::{FxRead, FxWrite},
+        job::{JobRead, JobUpload, JobWrite},
+        parse::report::Counts,
+        reconciliation::{ReconciliationRead, ReconciliationWrite},
+        transaction::{TransactionRead, TransactionWrite},
+    },
+};
+
+use super::base::{DatabaseError, DatabaseInit};
+
+// the tables the services' tests work on, left the way the postgres queries
+// would leave them. clones share the tables, so a test can keep one to look
+// at what a service did with another
+#[derive(Clone, Default)]
+pub struct Memory {
+    tables: Arc<Mutex<Tables>>,
+}
+
+#[derive(Clone, Default)]
+pub struct Tables {
+    pub accounts: Vec<Account>,
+    pub transactions: Vec<Transaction>,
+    pub batches: Vec<ImportBatch>,
+    pub anchors: Vec<BalanceAnchor>,
+    pub reconciliations: Vec<Reconciliation>,
+    pub rates: Vec<FxRate>,
+    pub jobs: Vec<ImportJob>,
+    // the files of each job by its id, with their chunks by position and
+    // sequence
+    pub job_files: Vec<(String, ImportJobFile)>,
+    pub job_chunks: Vec<(String, i32, i32, Vec<u8>)>,
+}
+
+impl Memory {
+    pub fn tables(&self) -> MutexGuard<'_, Tables> {
+        self.tables.lock().unwrap()
+    }
+
+    pub fn add_account(&self, name: &str, currency: &str, opening_balance: Decimal) -> Account {
+        let account = Account {
+            id: Uuid::new_v4(),
+            name: name.to_string(),
+            institution: None,
+            kind: AccountKind::Current,
+            currency: currency.to_string(),
+            opening_balance,
+            closed: false,
+            created_at: NaiveDateTime::default(),
+            updated_at: NaiveDateTime::default(),
+        };
+        self.tables().accounts.push(account.clone());
+
+        account
+    }
+
+    // paid at noon so it falls on the day whatever the query's cut off
+    pub fn add_transaction(&self, account_id: Uuid, date: NaiveDate, amount: Money) -> Uuid {
+        let id = Uuid::new_v4();
+        self.tables().transactions.push(Transaction {
+            id: id.to_string(),
+            account_id,
+            account_type: String::new(),
+            payment_date: date.and_hms_opt(12, 0, 0).unwrap(),
+            amount,
+            description: String::new(),
+            external_id: None,
+            category: None,
+            value_date: None,
+            fingerprint: None,
+            created_at: NaiveDateTime::default(),
+            updated_at: NaiveDateTime::default(),
+            batch_id: None,
+            status: TransactionStatus::Uncleared,
+            reconciliation_id: None,
+        });
+
+        id
+    }
+
+    // the sum of the account's transactions in the currency on each day they
+    // were paid, oldest first
+    pub fn daily_totals(&self, account_id: Uuid, currency: &str) -> Vec<(NaiveDate, Decimal)> {
+        let tables = self.tables();
+        let mut transactions: Vec<&Transaction> = tables
+            .transactions
+            .iter()
+            .filter(|t| t.account_id == account_id && t.amount.currency == currency)
+            .collect();
+        transactions.sort_by_key(|t| t.payment_date);
+
+        let mut days: Vec<(NaiveDate, Decimal)> = Vec::new();
+        for t in transactions {
+            let day = t.payment_date.date();
+            match days.last_mut() {
+                Some((d, total)) if *d == day => *total += t.amount.amount,
+                _ => days.push((day, t.amount.amount)),
+            }
+        }
+
+        days
+    }
+}
+
+impl DatabaseInit for Memory {
+    async fn connect(&mut self) -> Result<(), DatabaseError> {
+        Ok(())
+    }
+
+    async fn disconnect(&mut self) -> Result<(), DatabaseError> {
+        Ok(())
+    }
+}
+
+impl TransactionWrite for Memory {
+    async fn delete_transaction(&self, id: &str) -> Result<(), DatabaseError> {
+        self.tables().transactions.retain(|t| t.id != id);
+        Ok(())
+    }
+}
+
+impl TransactionRead for Memory {
+    async fn get_transaction(&self, id: &str) -> Result<Option<Transaction>, DatabaseError> {
+        Ok(self
+            .tables()
+            .transactions
+            .iter()
+            .find(|t| t.id == id)
+            .cloned())
+    }
+
+    async fn get_transactions(&self) -> Result<Vec<Transaction>, DatabaseError> {
+        Ok(self.tables().transactions.clone())
+    }
+
+    async fn get_known_fingerprints(
+        &self,
+        fingerprints: &[String],
+    ) -> Result<Vec<String>, DatabaseError> {
+        Ok(self.tables().known_fingerprints(fingerprints))
+    }
+}
+
+// an upload's changes are made to a copy of the tables, which replaces them
+// when it is committed
+pub struct MemoryImport {
+    db: Memory,
+    tables: Tables,
+}
+
+impl BatchWrite for Memory {
+    type Session = MemoryImport;
+
+    async fn begin_import(&self) -> Result<MemoryImport, DatabaseError> {
+        Ok(MemoryImport {
+            db: self.clone(),
+            tables: self.tables().clone(),
+        })
+    }
+
+    async fn rollback_batch(&self, id: &str) -> Result<Option<u64>, DatabaseError> {
+        let mut tables = self.tables();
+        let Some(batch) = tables.batches.iter_mut().find(|b| b.id == id) else {
+            return Ok(None);
+        };
+        batch.rolled_back_at = batch
+            .rolled_back_at
+            .or_else(|| Some(Utc::now().naive_utc()));
+
+        let before = tables.transactions.len();
+        let batch_id = Uuid::parse_str(id).ok();
+        tables.transactions.retain(|t| t.batch_id != batch_id);
+
+        Ok(Some((before - tables.transactions.len()) as u64))
+    }
+}
+
+impl ImportSession for MemoryImport {
+    async fn create_batch(&mut self, batch: &CreateImportBatch) -> Result<Uuid, DatabaseError> {
+        let id = Uuid::new_v4();
+        self.tables.batches.push(ImportBatch {
+            id: id.to_string(),
+            filename: batch.filename.clone(),
+            profile: batch.profile.clone(),
+            format: batch.format.clone(),
+            checksum: batch.checksum.clone(),
+            imported_rows: batch.imported_rows,
+            skipped_rows: batch.skipped_rows,
+            failed_rows: batch.failed_rows,
+            duplicate_rows: batch.duplicate_rows,
+            uploaded_at: Utc::now().naive_utc(),
+            rolled_back_at: None,
+        });
+
+        Ok(id)
+    }
+
+    async fn update_batch(
+        &mut self,
+        id: Uuid,
+        batch: &CreateImportBatch,
+    ) -> Result<(), DatabaseError> {
+        let id = id.to_string();
+        if let Some(saved) = self.tables.batches.iter_mut().find(|b| b.id == id) {
+            saved.checksum = batch.checksum.clone();
+            saved.imported_rows = batch.imported_rows;
+            saved.skipped_rows = batch.skipped_rows;
+            saved.failed_rows = batch.failed_rows;
+            saved.duplicate_rows = batch.duplicate_rows;
+        }
+
+        Ok(())
+    }
+
+    async fn create_transactions(
+        &mut self,
+        batch_id: Uuid,
+        transactions: &[CreateTransaction],
+    ) -> Result<(), DatabaseError> {
+        for t in transactions {
+            let account_id = t.account_id.ok_or_else(|| {
+                DatabaseError::SaveError(format!("{} has no account", t.account_type))
+            })?;
+
+            self.tables.transactions.push(Transaction {
+                id: Uuid::new_v4().to_string(),
+                account_id,
+                account_type: t.account_type.clone(),
+                payment_date: t.payment_date,
+                amount: t.amount.clone(),
+                description: t.description.clone(),
+                external_id: t.external_id.clone(),
+                category: t.category.clone(),
+                value_date: t.value_date,
+                fingerprint: t.fingerprint.clone(),
+                created_at: NaiveDateTime::default(),
+                updated_at: NaiveDateTime::default(),
+                batch_id: Some(batch_id),
+                status: TransactionStatus::Uncleared,
+                reconciliation_id: None,
+            });
+        }
+
+        Ok(())
+    }
+
+    async fn delete_transactions(&mut self, batch_id: Uuid) -> Result<(), DatabaseError> {
+        self.tables
+            .transactions
+            .retain(|t| t.batch_id != Some(batch_id));
+        Ok(())
+    }
+
+    async fn get_known_fingerprints(
+        &mut self,
+        fingerprints: &[String],
+    ) -> Result<Vec<String>, DatabaseError> {
+        Ok(self.tables.known_fingerprints(fingerprints))
+    }
+
+    async fn get_statement_accounts(
+        &mut self,
+        names: &[String],
+        currencies: &[String],
+    ) -> Result<Vec<Account>, DatabaseError> {
+        for (name, currency) in names.iter().zip(currencies) {
+            if !self.tables.accounts.iter().any(|a| &a.name == name) {
+                self.tables.accounts.push(Account {
+                    id: Uuid::new_v4(),
+                    name: name.clone(),
+                    institution: None,
+                    kind: AccountKind::Current,
+                    currency: currency.clone(),
+                    opening_balance: Decimal::ZERO,
+                    closed: false,
+                    created_at: NaiveDateTime::default(),
+                    updated_at: NaiveDateTime::default(),
+                });
+            }
+        }
+
+        Ok(self.tables.accounts_named(names))
+    }
+
+    async fn commit(self) -> Result<(), DatabaseError> {
+        *self.db.tables() = self.tables;
+        Ok(())
+    }
+}
+
+impl BatchRead for Memory {
+    async fn get_batch(&self, id: &str) -> Result<Option<ImportBatch>, DatabaseError> {
+        Ok(self.tables().batches.iter().find(|b| b.id == id).cloned())
+    }
+
+    async fn get_batches(&self) -> Result<Vec<ImportBatch>, DatabaseError> {
+        Ok(self.tables().batches.clone())
+    }
+
+    async fn get_batch_transactions(&self, id: &str) -> Result<Vec<Transaction>, DatabaseError> {
+        let batch_id = Uuid::parse_str(id).ok();
+
+        Ok(self
+            .tables()
+            .transactions
+            .iter()
+            .filter(|t| batch_id.is_some() && t.batch_id == batch_id)
+            .cloned()
+            .collect())
+    }
+
+    async fn has_reconciled_transactions(&self, id: &str) -> Result<bool, DatabaseError> {
+        let batch_id = Uuid::parse_str(id).ok();
+
+        Ok(self.tables().transactions.iter().any(|t| {
+            batch_id.is_some()
+                && t.batch_id == batch_id
+                && t.status == TransactionStatus::Reconciled
+        }))
+    }
+}
+
+impl AccountWrite for Memory {
+    async fn create_account(&self, account: &CreateAccount) -> Result<Account, DatabaseError> {
+        let created = Account {
+            id: Uuid::new_v4(),
+            name: account.name.clone(),
+            institution: account.institution.clone(),
+            kind: account.kind,
+            currency: account.currency.clone(),
+            opening_balance: account.opening_balance,
+            closed: account.closed,
+            created_at: NaiveDateTime::default(),
+            updated_at: NaiveDateTime::default(),
+        };
+        self.tables().accounts.push(created.clone());
+
+        Ok(created)
+    }
+
+    async fn update_account(
+        &self,
+        id: &str,
+        account: &CreateAccount,
+    ) -> Result<Option<Account>, DatabaseError> {
+        let mut tables = self.tables();
+        let Some(current) = tables.accounts.iter_mut().find(|a| a.id.to_string() == id) else {
+            return Ok(None);
+        };
+
+        current.name = account.name.clone();
+        current.institution = account.institution.clone();
+        current.kind = account.kind;
+        current.currency = account.currency.clone();
+        current.opening_balance = account.opening_balance;
+        current.closed = account.closed;
+
+        Ok(Some(current.clone()))
+    }
+
+    async fn delete_account(&self, id: &str) -> Result<bool, DatabaseError> {
+        let accounts = &mut self.tables().accounts;
+        let before = accounts.len();
+        accounts.retain(|a| a.id.to_string() != id);
+
+        Ok(accounts.len() < before)
+    }
+}
+
+impl AccountRead for Memory {
+    async fn get_account(&self, id: &str) -> Result<Option<Account>, DatabaseError> {
+        Ok(self
+            .tables()
+            .accounts
+            .iter()
+            .find(|a| a.id.to_string() == id)
+            .cloned())
+    }
+
+    async fn get_accounts(&self) -> Result<Vec<Account>, DatabaseError> {
+        let mut accounts = self.tables().accounts.clone();
+        accounts.sort_by(|a, b| a.name.cmp(&b.name));
+
+        Ok(accounts)
+    }
+
+    async fn get_accounts_named(&self, names: &[String]) -> Result<Vec<Account>, DatabaseError> {
+        Ok(self.tables().accounts_named(names))
+    }
+
+    async fn has_transactions(&self, id: &str) -> Result<bool, DatabaseError> {
+        Ok(self
+            .tables()
+            .transactions
+            .iter()
+            .any(|t| t.account_id.to_string() == id))
+    }
+
+    async fn has_finished_reconciliations(&self, id: &str) -> Result<bool, DatabaseError> {
+        Ok(self
+            .tables()
+            .reconciliations
+            .iter()
+            .any(|r| r.account_id.to_string() == id && r.state == ReconciliationState::Finished))
+    }
+}
+
+impl BalanceWrite for Memory {
+    async fn save_anchor(
+        &self,
+        account_id: Uuid,
+        anchor: &CreateBalanceAnchor,
+    ) -> Result<BalanceAnchor, DatabaseError> {
+        let saved = BalanceAnchor {
+            account_id,
+            date: anchor.date,
+            balance: anchor.balance,
+            note: anchor.note.clone(),
+            created_at: NaiveDateTime::default(),
+        };
+        self.tables().save_anchor(saved.clone());
+
+        Ok(saved)
+    }
+
+    async fn delete_anchor(
+        &self,
+        account_id: Uuid,
+        date: NaiveDate,
+    ) -> Result<bool, DatabaseError> {
+        let anchors = &mut self.tables().anchors;
+        let before = anchors.len();
+        anchors.retain(|a| a.account_id != account_id || a.date != date);
+
+        Ok(anchors.len() < before)
+    }
+}
+
+impl BalanceRead for Memory {
+    async fn get_anchors(&self, account_id: Uuid) -> Result<Vec<BalanceAnchor>, DatabaseError> {
+        let mut anchors: Vec<BalanceAnchor> = self
+            .tables()
+            .anchors
+            .iter()
+            .filter(|a| a.account_id == account_id)
+            .cloned()
+            .collect();
+        anchors.sort_by_key(|a| a.date);
+
+        Ok(anchors)
+    }
+
+    async fn get_daily_totals(
+        &self,
+        account_id: Uuid,
+        currency: &str,
+    ) -> Result<Vec<(NaiveDate, Decimal)>, DatabaseError> {
+        Ok(self.daily_totals(account_id, currency))
+    }
+
+    async fn get_account_transactions(
+        &self,
+        account_id: Uuid,
+        from: Option<NaiveDate>,
+        to: Option<NaiveDate>,
+    ) -> Result<Vec<Transaction>, DatabaseError> {
+        let mut transactions: Vec<Transaction> = self
+            .tables()
+            .transactions
+            .iter()
+            .filter(|t| t.account_id == account_id)
+            .filter(|t| from.is_none_or(|f| t.payment_date.date() >= f))
+            .filter(|t| to.is_none_or(|l| t.payment_date.date() <= l))
+            .cloned()
+            .collect();
+        transactions.sort_by_key(|t| t.payment_date);
+
+        Ok(transactions)
+    }
+}
+
+impl ReconciliationWrite for Memory {
+    async fn create_reconciliation(
+        &self,
+        account_id: Uuid,
+        reconciliation: &CreateReconciliation,
+    ) -> Result<Reconciliation, DatabaseError> {
+        let created = Reconciliation {
+            id: Uuid::new_v4(),
+            account_id,
+            statement_date: reconciliation.statement_date,
+            closing_balance: reconciliation.closing_balance,
+            state: ReconciliationState::Open,
+            created_at: NaiveDateTime::default(),
+            finished_at: None,
+        };
+        self.tables().reconciliations.push(created.clone());
+
+        Ok(created)
+    }
+
+    async fn set_cleared(
+        &self,
+        account_id: Uuid,
+        transactions: &[Uuid],
+        cleared: bool,
+    ) -> Result<(), DatabaseError> {
+        let status = match cleared {
+            true => TransactionStatus::Cleared,
+            false => TransactionStatus::Uncleared,
+        };
+
+        for t in self.tables().transactions.iter_mut() {
+            if t.account_id == account_id
+                && transactions.iter().any(|id| id.to_string() == t.id)
+                && t.status != TransactionStatus::Reconciled
+            {
+                t.status = status;
+            }
+        }
+
+        Ok(())
+    }
+
+    async fn finish_reconciliation(
+        &self,
+        reconciliation: &Reconciliation,
+    ) -> Result<u64, DatabaseError> {
+        let mut tables = self.tables();
+
+        let mut reconciled = 0;
+        for t in tables.transactions.iter_mut() {
+            if t.account_id == reconciliation.account_id
+                && t.status == TransactionStatus::Cleared
+                && t.payment_date.date() <= reconciliation.statement_date
+            {
+                t.status = TransactionStatus::Reconciled;
+                t.reconciliation_id = Some(reconciliation.id);
+                reconciled += 1;
+            }
+        }
+
+        for r in tables.reconciliations.iter_mut() {
+            if r.id == reconciliation.id {
+                r.state = ReconciliationState::Finished;
+                r.finished_at = Some(Utc::now().naive_utc());
+            }
+        }
+
+        tables.save_anchor(BalanceAnchor {
+            account_id: reconciliation.account_id,
+            date: reconciliation.statement_date,
+            balance: reconciliation.closing_balance,
+            note: Some("Reconciled".to_string()),
+            created_at: NaiveDateTime::default(),
+        });
+
+        Ok(reconciled)
+    }
+
+    async fn delete_reconciliation(&self, id: Uuid) -> Result<bool, DatabaseError> {
+        let reconciliations = &mut self.tables().reconciliations;
+        let before = reconciliations.len();
+        reconciliations.retain(|r| r.id != id || r.state != ReconciliationState::Open);
+
+        Ok(reconciliations.len() < before)
+    }
+}
+
+impl ReconciliationRead for Memory {
+    async fn get_reconciliation(&self, id: &str) -> Result<Option<Reconciliation>, DatabaseError> {
+        Ok(self
+            .tables()
+            .reconciliations
+            .iter()
+            .find(|r| r.id.to_string() == id)
+            .cloned())
+    }
+
+    async fn get_reconciliations(
+        &self,
+        account_id: Uuid,
+    ) -> Result<Vec<Reconciliation>, DatabaseError> {
+        let mut reconciliations: Vec<Reconciliation> = self
+            .tables()
+            .reconciliations
+            .iter()
+            .filter(|r| r.account_id == account_id)
+            .cloned()
+            .collect();
+        reconciliations.sort_by_key(|r| r.statement_date);
+
+        Ok(reconciliations)
+    }
+
+    async fn get_cleared_total(
+        &self,
+        account_id: Uuid,
+        currency: &str,
+        to: NaiveDate,
+    ) -> Result<Decimal, DatabaseError> {
+        Ok(self
+            .tables()
+            .transactions
+            .iter()
+            .filter(|t| {
+                t.account_id == account_id
+                    && t.amount.currency == currency
+                    && t.status != TransactionStatus::Uncleared
+                    && t.payment_date.date() <= to
+            })
+            .map(|t| t.amount.amount)
+            .sum())
+    }
+
+    async fn get_unreconciled_transactions(
+        &self,
+        reconciliation: &Reconciliation,
+    ) -> Result<Vec<Transaction>, DatabaseError> {
+        let mut transactions: Vec<Transaction> = self
+            .tables()
+            .transactions
+            .iter()
+            .filter(|t| {
+                t.account_id == reconciliation.account_id
+                    && t.status != TransactionStatus::Reconciled
+                    && t.payment_date.date() <= reconciliation.statement_date
+            })
+            .cloned()
+            .collect();
+        transactions.sort_by_key(|t| t.payment_date);
+
+        Ok(transactions)
+    }
+
+    async fn get_unreconciled_ids(
+        &self,
+        account_id: Uuid,
+        transactions: &[Uuid],
+        to: NaiveDate,
+    ) -> Result<Vec<Uuid>, DatabaseError> {
+        Ok(self
+            .tables()
+            .transactions
+            .iter()
+            .filter(|t| {
+                t.account_id == account_id
+                    && t.status != TransactionStatus::Reconciled
+                    && t.payment_date.date() <= to
+            })
+            .filter_map(|t| Uuid::parse_str(&t.id).ok())
+            .filter(|id| transactions.contains(id))
+            .collect())
+    }
+}
+
+// a job's files are saved to a copy of the tables, which replaces them when
+// the upload is committed
+pub struct MemoryJobUpload {
+    db: Memory,
+    id: String,
+    tables: Tables,
+}
+
+impl FxWrite for Memory {
+    async fn save_rates(&self, rates: &[FxRate]) -> Result<u64, DatabaseError> {
+        let saved = &mut self.tables().rates;
+        for rate in rates {
+            saved.retain(|r| (&r.base, &r.quote, r.date) != (&rate.base, &rate.quote, rate.date));
+            saved.push(rate.clone());
+        }
+
+        Ok(rates.len() as u64)
+    }
+}
+
+impl FxRead for Memory {
+    async fn get_rates(&self) -> Result<Vec<FxRate>, DatabaseError> {
+        let mut rates = self.tables().rates.clone();
+        rates.sort_by(|a, b| (&a.base, &a.quote, a.date).cmp(&(&b.base, &b.quote, b.date)));
+
+        Ok(rates)
+    }
+
+    async fn get_rates_between(
+        &self,
+        currency: &str,
+        others: &[String],
+    ) -> Result<Vec<FxRate>, DatabaseError> {
+        Ok(self
+            .tables()
+            .rates
+            .iter()
+            .filter(|r| {
+                (r.quote == currency && others.contains(&r.base))
+                    || (r.base == currency && others.contains(&r.quote))
+            })
+            .cloned()
+            .collect())
+    }
+}
+
+impl JobWrite for Memory {
+    type Upload = MemoryJobUpload;
+
+    async fn begin_job(&self) -> Result<MemoryJobUpload, DatabaseError> {
+        let id = Uuid::new_v4().to_string();
+        let mut tables = self.tables().clone();
+        tables.jobs.push(ImportJob {
+            id: id.clone(),
+            state: JobState::Queued,
+            imported_rows: 0,
+            skipped_rows: 0,
+            failed_rows: 0,
+            duplicate_rows: 0,
+            summary: None,
+            error: None,
+            created_at: Utc::now().naive_utc(),
+            started_at: None,
+            finished_at: None,
+        });
+
+        Ok(MemoryJobUpload {
+            db: self.clone(),
+            id,
+            tables,
+        })
+    }
+
+    async fn claim_job(&self) -> Result<Option<ImportJob>, DatabaseError> {
+        let mut tables = self.tables();
+        let oldest = tables
+            .jobs
+            .iter_mut()
+            .filter(|j| j.state == JobState::Queued)
+            .min_by_key(|j| j.created_at);
+
+        Ok(oldest.map(|job| {
+            job.state = JobState::Running;
+            job.started_at = Some(Utc::now().naive_utc());
+            job.clone()
+        }))
+    }
+
+    async fn update_job_progress(&self, id: &str, counts: &Counts) -> Result<(), DatabaseError> {
+        if let Some(job) = self
+            .tables()
+            .jobs
+            .iter_mut()
+            .find(|j| j.id == id && j.state == JobState::Running)
+        {
+            set_counts(job, counts);
+        }
+
+        Ok(())
+    }
+
+    async fn finish_job(
+        &self,
+        id: &str,
+        counts: &Counts,
+        summary: serde_json::Value,
+    ) -> Result<(), DatabaseError> {
+        let mut tables = self.tables();
+        if let Some(job) = tables.jobs.iter_mut().find(|j| j.id == id) {
+            job.state = JobState::Done;
+            set_counts(job, counts);
+            job.summary = Some(summary);
+            job.finished_at = Some(Utc::now().naive_utc());
+        }
+        tables.delete_job_files(id);
+
+        Ok(())
+    }
+
+    async fn fail_job(&self, id: &str, error: &str) -> Result<(), DatabaseError> {
+        let mut tables = self.tables();
+        if let Some(job) = tables.jobs.iter_mut().find(|j| j.id == id) {
+            job.state = JobState::Failed;
+            set_counts(job, &Counts::default());
+            job.error = Some(error.to_string());
+            job.finished_at = Some(Utc::now().naive_utc());
+        }
+        tables.delete_job_files(id);
+
+        Ok(())
+    }
+
+    async fn requeue_jobs(&self) -> Result<u64, DatabaseError> {
+        let mut requeued = 0;
+        for job in self.tables().jobs.iter_mut() {
+            if job.state == JobState::Running {
+                job.state = JobState::Queued;
+                set_counts(job, &Counts::default());
+                job.started_at = None;
+                requeued += 1;
+            }
+        }
+
+        Ok(requeued)
+    }
+}
+
+impl JobUpload for MemoryJobUpload {
+    async fn add_file(&mut self, file: &ImportJobFile) -> Result<(), DatabaseError> {
+        self.tables.job_files.push((self.id.clone(), file.clone()));
+        Ok(())
+    }
+
+    async fn add_chunk(
+        &mut self,
+        position: i32,
+        sequence: i32,
+        data: &[u8],
+    ) -> Result<(), DatabaseError> {
+        self.tables
+            .job_chunks
+            .push((self.id.clone(), position, sequence, data.to_vec()));
+        Ok(())
+    }
+
+    async fn commit(self) -> Result<ImportJob, DatabaseError> {
+        let job = self.tables.jobs.iter().find(|j| j.id == self.id).cloned();
+        *self.db.tables() = self.tables;
+
+        job.ok_or_else(|| DatabaseError::GetError("Job was not saved".to_string()))
+    }
+}
+
+impl JobRead for Memory {
+    async fn get_job(&self, id: &str) -> Result<Option<ImportJob>, DatabaseError> {
+        Ok(self.tables().jobs.iter().find(|j| j.id == id).cloned())
+    }
+
+    async fn get_job_files(&self, id: &str) -> Result<Vec<ImportJobFile>, DatabaseError> {
+        let mut files: Vec<ImportJobFile> = self
+            .tables()
+            .job_files
+            .iter()
+            .filter(|(job_id, _)| job_id == id)
+            .map(|(_, file)| file.clone())
+            .collect();
+        files.sort_by_key(|f| f.position);
+
+        Ok(files)
+    }
+
+    async fn get_job_chunk(
+        &self,
+        id: &str,
+        position: i32,
+        sequence: i32,
+    ) -> Result<Option<Vec<u8>>, DatabaseError> {
+        Ok(self
+            .tables()
+            .job_chunks
+            .iter()
+            .find(|(job_id, p, s, _)| job_id == id && *p == position && *s == sequence)
+            .map(|(_, _, _, data)| data.clone()))
+    }
+}
+
+fn set_counts(job: &mut ImportJob, counts: &Counts) {
+    job.imported_rows = counts.imported as i32;
+    job.skipped_rows = counts.skipped as i32;
+    job.failed_rows = counts.failed as i32;
+    job.duplicate_rows = counts.duplicates as i32;
+}
+
+impl Tables {
+    fn known_fingerprints(&self, fingerprints: &[String]) -> Vec<String> {
+        self.transactions
+            .iter()
+            .filter_map(|t| t.fingerprint.clone())
+            .filter(|f| fingerprints.contains(f))
+            .collect()
+    }
+
+    fn accounts_named(&self, names: &[String]) -> Vec<Account> {
+        self.accounts
+            .iter()
+            .filter(|a| names.contains(&a.name))
+            .cloned()
+            .collect()
+    }
+
+    // replaces the account's anchor of the same day
+    fn save_anchor(&mut self, anchor: BalanceAnchor) {
+        self.anchors
+            .retain(|a| a.account_id != anchor.account_id || a.date != anchor.date);
+        self.anchors.push(anchor);
+    }
+
+    // the files of a job are deleted once it has finished or failed
+    fn delete_job_files(&mut self, id: &str) {
+        self.job_files.retain(|(job_id, _)| job_id != id);
+        self.job_chunks.retain(|(job_id, ..)| job_id != id);
+    }
+}
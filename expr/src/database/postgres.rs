@@ -1,16 +1,38 @@
 use std::sync::Arc;
 
+use chrono::{NaiveDate, NaiveDateTime};
+use import::service::parse_service::Money;
+use rust_decimal::Decimal;
 use sqlx::PgPool;
 use tracing::{error, info};
 use uuid::Uuid;
 
 use crate::{
-    models::transaction::{CreateTransaction, Transaction},
-    service::transaction::{TransactionRead, TransactionWrite},
+    models::{
+        account::{Account, AccountKind, CreateAccount},
+        balance::{BalanceAnchor, CreateBalanceAnchor},
+        batch::{CreateImportBatch, ImportBatch},
+        fx::FxRate,
+        job::{ImportJob, ImportJobFile, JobState},
+        reconciliation::{CreateReconciliation, Reconciliation, ReconciliationState},
+        transaction::{CreateTransaction, Transaction, TransactionStatus},
+    },
+    service::{
+        account::{AccountRead, AccountWrite},
+        balance::{BalanceRead, BalanceWrite},
+        batch::{BatchRead, BatchWrite, ImportSession},
+        fx::{FxRead, FxWrite},
+        job::{JobRead, JobUpload, JobWrite},
+        parse::{report::Counts, AmountMode, Config},
+        profile::{ProfileRead, ProfileWrite},
+        reconciliation::{ReconciliationRead, ReconciliationWrite},
+        transaction::{TransactionRead, TransactionWrite},
+    },
 };
 
 use super::base::{DatabaseError, DatabaseInit};
 
+#[derive(Clone)]
 pub struct Postgres {
     connection_string: Arc<str>,
     pool: Option<PgPool>,
@@ -23,6 +45,15 @@ impl Postgres {
             connection_string: connection_string.into(),
         }
     }
+
+    // connects to the database the crate was built against, the url holds the
+    // credentials so it is never logged
+    pub async fn connect_default() -> Result<Self, DatabaseError> {
+        let mut postgres = Self::new(env!("DATABASE_URL"));
+        postgres.connect().await?;
+
+        Ok(postgres)
+    }
 }
 
 impl DatabaseInit for Postgres {
@@ -47,45 +78,18 @@ impl DatabaseInit for Postgres {
         if let Some(pool) = &self.pool {
             pool.close().await;
             self.pool = None;
-            return Ok(());
         }
 
-        return Ok(());
+        Ok(())
     }
 }
 
 impl TransactionWrite for Postgres {
-    async fn create_transaction(
-        &self,
-        create_transaction: CreateTransaction,
-    ) -> Result<Uuid, DatabaseError> {
-        if let Some(pool) = &self.pool {
-            let res = sqlx::query!(
-                r#"
-            INSERT INTO payment_transactions (account_type, payment_date, description, amount)
-            VALUES ($1, $2, $3, $4)
-            RETURNING id
-            "#,
-                create_transaction.account_type,
-                create_transaction.payment_date,
-                create_transaction.description,
-                create_transaction.amount
-            )
-            .fetch_one(pool)
-            .await
-            .map_err(|e| DatabaseError::SaveError(e.to_string()))?;
-
-            return Ok(res.id);
-        }
-
-        return Err(DatabaseError::ConnectionError("No connection".to_string()));
-    }
-
     async fn delete_transaction(&self, id: &str) -> Result<(), DatabaseError> {
         let id = Uuid::parse_str(id).map_err(|e| DatabaseError::GetError(e.to_string()))?;
 
         if let Some(pool) = &self.pool {
-            let _ = sqlx::query!(
+            sqlx::query!(
                 r#"
         DELETE FROM payment_transactions WHERE id = $1
             "#,
@@ -93,7 +97,7 @@ impl TransactionWrite for Postgres {
             )
             .execute(pool)
             .await
-            .map_err(|e| DatabaseError::DeleteError(e.to_string()));
+            .map_err(|e| DatabaseError::DeleteError(e.to_string()))?;
 
             return Ok(());
         }
@@ -104,15 +108,60 @@ impl TransactionWrite for Postgres {
     }
 }
 
+// a payment_transactions row, its amount and currency make up the
+// transaction's money
+struct TransactionRecord {
+    id: Uuid,
+    account_id: Uuid,
+    account_type: String,
+    payment_date: NaiveDateTime,
+    amount: Decimal,
+    currency: String,
+    description: String,
+    external_id: Option<String>,
+    category: Option<String>,
+    value_date: Option<NaiveDateTime>,
+    fingerprint: Option<String>,
+    created_at: NaiveDateTime,
+    updated_at: NaiveDateTime,
+    batch_id: Option<Uuid>,
+    status: TransactionStatus,
+    reconciliation_id: Option<Uuid>,
+}
+
+impl From<TransactionRecord> for Transaction {
+    fn from(r: TransactionRecord) -> Self {
+        Self {
+            id: r.id.to_string(),
+            account_id: r.account_id,
+            account_type: r.account_type,
+            payment_date: r.payment_date,
+            amount: Money::new(r.amount, &r.currency),
+            description: r.description,
+            external_id: r.external_id,
+            category: r.category,
+            value_date: r.value_date,
+            fingerprint: r.fingerprint,
+            created_at: r.created_at,
+            updated_at: r.updated_at,
+            batch_id: r.batch_id,
+            status: r.status,
+            reconciliation_id: r.reconciliation_id,
+        }
+    }
+}
+
 impl TransactionRead for Postgres {
     async fn get_transaction(&self, id: &str) -> Result<Option<Transaction>, DatabaseError> {
         let id = Uuid::parse_str(id).map_err(|e| DatabaseError::GetError(e.to_string()))?;
 
         if let Some(pool) = &self.pool {
             let record = sqlx::query_as!(
-                Transaction,
+                TransactionRecord,
                 r#"
-            SELECT * FROM payment_transactions WHERE id = $1
+            SELECT id, account_id, account_type, payment_date, amount, currency, description, external_id, category,
+                value_date, fingerprint, created_at, updated_at, batch_id, status as "status: TransactionStatus", reconciliation_id
+            FROM payment_transactions WHERE id = $1
             "#,
                 id
             )
@@ -120,7 +169,7 @@ impl TransactionRead for Postgres {
             .await
             .map_err(|e| DatabaseError::GetError(e.to_string()))?;
 
-            return Ok(Some(record));
+            return Ok(Some(record.into()));
         }
 
         Err(DatabaseError::GetError("No connection".to_string()))
@@ -129,20 +178,1541 @@ impl TransactionRead for Postgres {
     async fn get_transactions(&self) -> Result<Vec<Transaction>, DatabaseError> {
         if let Some(pool) = &self.pool {
             let records = sqlx::query_as!(
-                Transaction,
+                TransactionRecord,
                 r#"
-            SELECT * FROM payment_transactions
+            SELECT id, account_id, account_type, payment_date, amount, currency, description, external_id, category,
+                value_date, fingerprint, created_at, updated_at, batch_id, status as "status: TransactionStatus", reconciliation_id
+            FROM payment_transactions
                 "#
             )
             .fetch_all(pool)
             .await
             .map_err(|e| DatabaseError::GetError(e.to_string()))?;
 
-            return Ok(records);
+            return Ok(records.into_iter().map(Transaction::from).collect());
         }
 
         Err(DatabaseError::GetError(
             "No database connection".to_string(),
         ))
     }
+
+    async fn get_known_fingerprints(
+        &self,
+        fingerprints: &[String],
+    ) -> Result<Vec<String>, DatabaseError> {
+        if let Some(pool) = &self.pool {
+            let records = sqlx::query!(
+                r#"
+            SELECT fingerprint as "fingerprint!" FROM payment_transactions WHERE fingerprint = ANY($1)
+            "#,
+                fingerprints
+            )
+            .fetch_all(pool)
+            .await
+            .map_err(|e| DatabaseError::GetError(e.to_string()))?;
+
+            return Ok(records.into_iter().map(|r| r.fingerprint).collect());
+        }
+
+        Err(DatabaseError::GetError("No connection".to_string()))
+    }
+}
+
+impl ProfileWrite for Postgres {
+    async fn create_profile(&self, profile: &Config) -> Result<(), DatabaseError> {
+        if let Some(pool) = &self.pool {
+            sqlx::query!(
+                r#"
+            INSERT INTO import_profiles (name, date_position, amount_position, description_position, date_headers, amount_headers, description_headers, amount_mode, debit_position, debit_headers, credit_position, credit_headers, indicator_position, indicator_headers, debit_indicators, credit_indicators, date_formats, decimal_separator, thousands_separator, currency_symbols, skip_rows, has_header, skip_descriptions, delimiter, invert_sign, currency)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14, $15, $16, $17, $18, $19, $20, $21, $22, $23, $24, $25, $26)
+            "#,
+                profile.name,
+                profile.date_position,
+                profile.amount_position,
+                profile.description_position,
+                &profile.date_headers[..],
+                &profile.amount_headers[..],
+                &profile.description_headers[..],
+                profile.amount_mode as AmountMode,
+                profile.debit_position,
+                &profile.debit_headers[..],
+                profile.credit_position,
+                &profile.credit_headers[..],
+                profile.indicator_position,
+                &profile.indicator_headers[..],
+                &profile.debit_indicators[..],
+                &profile.credit_indicators[..],
+                &profile.date_formats[..],
+                profile.decimal_separator,
+                profile.thousands_separator,
+                &profile.currency_symbols[..],
+                profile.skip_rows,
+                profile.has_header,
+                &profile.skip_descriptions[..],
+                profile.delimiter,
+                profile.invert_sign,
+                profile.currency
+            )
+            .execute(pool)
+            .await
+            .map_err(|e| DatabaseError::SaveError(e.to_string()))?;
+
+            return Ok(());
+        }
+
+        Err(DatabaseError::ConnectionError("No connection".to_string()))
+    }
+
+    async fn update_profile(&self, name: &str, profile: &Config) -> Result<bool, DatabaseError> {
+        if let Some(pool) = &self.pool {
+            let res = sqlx::query!(
+                r#"
+            UPDATE import_profiles
+            SET name = $2, date_position = $3, amount_position = $4, description_position = $5,
+                date_headers = $6, amount_headers = $7, description_headers = $8, amount_mode = $9,
+                debit_position = $10, debit_headers = $11, credit_position = $12,
+                credit_headers = $13, indicator_position = $14, indicator_headers = $15,
+                debit_indicators = $16, credit_indicators = $17, date_formats = $18,
+                decimal_separator = $19, thousands_separator = $20, currency_symbols = $21,
+                skip_rows = $22, has_header = $23, skip_descriptions = $24, delimiter = $25,
+                invert_sign = $26, currency = $27,
+                updated_at = CURRENT_TIMESTAMP
+            WHERE name = $1
+            "#,
+                name,
+                profile.name,
+                profile.date_position,
+                profile.amount_position,
+                profile.description_position,
+                &profile.date_headers[..],
+                &profile.amount_headers[..],
+                &profile.description_headers[..],
+                profile.amount_mode as AmountMode,
+                profile.debit_position,
+                &profile.debit_headers[..],
+                profile.credit_position,
+                &profile.credit_headers[..],
+                profile.indicator_position,
+                &profile.indicator_headers[..],
+                &profile.debit_indicators[..],
+                &profile.credit_indicators[..],
+                &profile.date_formats[..],
+                profile.decimal_separator,
+                profile.thousands_separator,
+                &profile.currency_symbols[..],
+                profile.skip_rows,
+                profile.has_header,
+                &profile.skip_descriptions[..],
+                profile.delimiter,
+                profile.invert_sign,
+                profile.currency
+            )
+            .execute(pool)
+            .await
+            .map_err(|e| DatabaseError::SaveError(e.to_string()))?;
+
+            return Ok(res.rows_affected() > 0);
+        }
+
+        Err(DatabaseError::ConnectionError("No connection".to_string()))
+    }
+
+    async fn delete_profile(&self, name: &str) -> Result<bool, DatabaseError> {
+        if let Some(pool) = &self.pool {
+            let res = sqlx::query!(
+                r#"
+            DELETE FROM import_profiles WHERE name = $1
+            "#,
+                name
+            )
+            .execute(pool)
+            .await
+            .map_err(|e| DatabaseError::DeleteError(e.to_string()))?;
+
+            return Ok(res.rows_affected() > 0);
+        }
+
+        Err(DatabaseError::ConnectionError("No connection".to_string()))
+    }
+}
+
+impl ProfileRead for Postgres {
+    async fn get_profile(&self, name: &str) -> Result<Option<Config>, DatabaseError> {
+        if let Some(pool) = &self.pool {
+            let record = sqlx::query_as!(
+                Config,
+                r#"
+            SELECT name, date_position, amount_position, description_position, date_headers, amount_headers, description_headers, amount_mode as "amount_mode: AmountMode", debit_position, debit_headers, credit_position, credit_headers, indicator_position, indicator_headers, debit_indicators, credit_indicators, date_formats, decimal_separator, thousands_separator, currency_symbols, skip_rows, has_header, skip_descriptions, delimiter, invert_sign, currency
+            FROM import_profiles WHERE name = $1
+            "#,
+                name
+            )
+            .fetch_optional(pool)
+            .await
+            .map_err(|e| DatabaseError::GetError(e.to_string()))?;
+
+            return Ok(record);
+        }
+
+        Err(DatabaseError::GetError("No connection".to_string()))
+    }
+
+    async fn get_profiles(&self) -> Result<Vec<Config>, DatabaseError> {
+        if let Some(pool) = &self.pool {
+            let records = sqlx::query_as!(
+                Config,
+                r#"
+            SELECT name, date_position, amount_position, description_position, date_headers, amount_headers, description_headers, amount_mode as "amount_mode: AmountMode", debit_position, debit_headers, credit_position, credit_headers, indicator_position, indicator_headers, debit_indicators, credit_indicators, date_formats, decimal_separator, thousands_separator, currency_symbols, skip_rows, has_header, skip_descriptions, delimiter, invert_sign, currency
+            FROM import_profiles ORDER BY name
+            "#
+            )
+            .fetch_all(pool)
+            .await
+            .map_err(|e| DatabaseError::GetError(e.to_string()))?;
+
+            return Ok(records);
+        }
+
+        Err(DatabaseError::GetError("No connection".to_string()))
+    }
+}
+
+// an upload's open database transaction
+pub struct PostgresImport {
+    tx: sqlx::Transaction<'static, sqlx::Postgres>,
+}
+
+impl BatchWrite for Postgres {
+    type Session = PostgresImport;
+
+    async fn begin_import(&self) -> Result<PostgresImport, DatabaseError> {
+        if let Some(pool) = &self.pool {
+            let tx = pool
+                .begin()
+                .await
+                .map_err(|e| DatabaseError::SaveError(e.to_string()))?;
+
+            return Ok(PostgresImport { tx });
+        }
+
+        Err(DatabaseError::ConnectionError("No connection".to_string()))
+    }
+
+    async fn rollback_batch(&self, id: &str) -> Result<Option<u64>, DatabaseError> {
+        let id = Uuid::parse_str(id).map_err(|e| DatabaseError::GetError(e.to_string()))?;
+
+        if let Some(pool) = &self.pool {
+            let mut tx = pool
+                .begin()
+                .await
+                .map_err(|e| DatabaseError::DeleteError(e.to_string()))?;
+
+            let batch = sqlx::query!(
+                r#"
+            UPDATE import_batches SET rolled_back_at = COALESCE(rolled_back_at, CURRENT_TIMESTAMP)
+            WHERE id = $1
+            "#,
+                id
+            )
+            .execute(&mut *tx)
+            .await
+            .map_err(|e| DatabaseError::DeleteError(e.to_string()))?;
+
+            if batch.rows_affected() == 0 {
+                return Ok(None);
+            }
+
+            let deleted = sqlx::query!(
+                r#"
+            DELETE FROM payment_transactions WHERE batch_id = $1
+            "#,
+                id
+            )
+            .execute(&mut *tx)
+            .await
+            .map_err(|e| DatabaseError::DeleteError(e.to_string()))?;
+
+            tx.commit()
+                .await
+                .map_err(|e| DatabaseError::DeleteError(e.to_string()))?;
+
+            return Ok(Some(deleted.rows_affected()));
+        }
+
+        Err(DatabaseError::ConnectionError("No connection".to_string()))
+    }
+}
+
+impl ImportSession for PostgresImport {
+    async fn create_batch(&mut self, batch: &CreateImportBatch) -> Result<Uuid, DatabaseError> {
+        let res = sqlx::query!(
+            r#"
+            INSERT INTO import_batches (filename, profile, format, checksum, imported_rows, skipped_rows, failed_rows, duplicate_rows)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
+            RETURNING id
+            "#,
+            batch.filename,
+            batch.profile,
+            batch.format,
+            batch.checksum,
+            batch.imported_rows,
+            batch.skipped_rows,
+            batch.failed_rows,
+            batch.duplicate_rows
+        )
+        .fetch_one(&mut *self.tx)
+        .await
+        .map_err(|e| DatabaseError::SaveError(e.to_string()))?;
+
+        Ok(res.id)
+    }
+
+    async fn update_batch(
+        &mut self,
+        id: Uuid,
+        batch: &CreateImportBatch,
+    ) -> Result<(), DatabaseError> {
+        sqlx::query!(
+            r#"
+            UPDATE import_batches
+            SET checksum = $2, imported_rows = $3, skipped_rows = $4, failed_rows = $5, duplicate_rows = $6
+            WHERE id = $1
+            "#,
+            id,
+            batch.checksum,
+            batch.imported_rows,
+            batch.skipped_rows,
+            batch.failed_rows,
+            batch.duplicate_rows
+        )
+        .execute(&mut *self.tx)
+        .await
+        .map_err(|e| DatabaseError::SaveError(e.to_string()))?;
+
+        Ok(())
+    }
+
+    // one statement per chunk, the columns are sent as arrays and unnested
+    async fn create_transactions(
+        &mut self,
+        batch_id: Uuid,
+        transactions: &[CreateTransaction],
+    ) -> Result<(), DatabaseError> {
+        if transactions.is_empty() {
+            return Ok(());
+        }
+
+        let mut account_ids = Vec::with_capacity(transactions.len());
+        let mut account_types = Vec::with_capacity(transactions.len());
+        let mut payment_dates = Vec::with_capacity(transactions.len());
+        let mut descriptions = Vec::with_capacity(transactions.len());
+        let mut amounts = Vec::with_capacity(transactions.len());
+        let mut currencies = Vec::with_capacity(transactions.len());
+        let mut external_ids = Vec::with_capacity(transactions.len());
+        let mut categories = Vec::with_capacity(transactions.len());
+        let mut value_dates = Vec::with_capacity(transactions.len());
+        let mut fingerprints = Vec::with_capacity(transactions.len());
+        for t in transactions {
+            account_ids.push(t.account_id);
+            account_types.push(t.account_type.clone());
+            payment_dates.push(t.payment_date);
+            descriptions.push(t.description.clone());
+            amounts.push(t.amount.amount);
+            currencies.push(t.amount.currency.clone());
+            external_ids.push(t.external_id.clone());
+            categories.push(t.category.clone());
+            value_dates.push(t.value_date);
+            fingerprints.push(t.fingerprint.clone());
+        }
+
+        sqlx::query!(
+            r#"
+            INSERT INTO payment_transactions (account_type, payment_date, description, amount, currency, external_id, category, value_date, fingerprint, account_id, batch_id)
+            SELECT account_type, payment_date, description, amount, currency, external_id, category, value_date, fingerprint, account_id, $11
+            FROM UNNEST($1::TEXT[], $2::TIMESTAMP[], $3::TEXT[], $4::NUMERIC[], $5::TEXT[], $6::TEXT[], $7::TEXT[], $8::TIMESTAMP[], $9::TEXT[], $10::UUID[])
+                AS t (account_type, payment_date, description, amount, currency, external_id, category, value_date, fingerprint, account_id)
+            "#,
+            &account_types[..],
+            &payment_dates[..],
+            &descriptions[..],
+            &amounts[..],
+            &currencies[..],
+            &external_ids[..] as &[Option<String>],
+            &categories[..] as &[Option<String>],
+            &value_dates[..] as &[Option<NaiveDateTime>],
+            &fingerprints[..] as &[Option<String>],
+            &account_ids[..] as &[Option<Uuid>],
+            batch_id
+        )
+        .execute(&mut *self.tx)
+        .await
+        .map_err(|e| DatabaseError::SaveError(e.to_string()))?;
+
+        Ok(())
+    }
+
+    async fn delete_transactions(&mut self, batch_id: Uuid) -> Result<(), DatabaseError> {
+        sqlx::query!(
+            r#"
+            DELETE FROM payment_transactions WHERE batch_id = $1
+            "#,
+            batch_id
+        )
+        .execute(&mut *self.tx)
+        .await
+        .map_err(|e| DatabaseError::DeleteError(e.to_string()))?;
+
+        Ok(())
+    }
+
+    async fn get_known_fingerprints(
+        &mut self,
+        fingerprints: &[String],
+    ) -> Result<Vec<String>, DatabaseError> {
+        let records = sqlx::query!(
+            r#"
+            SELECT fingerprint as "fingerprint!" FROM payment_transactions WHERE fingerprint = ANY($1)
+            "#,
+            fingerprints
+        )
+        .fetch_all(&mut *self.tx)
+        .await
+        .map_err(|e| DatabaseError::GetError(e.to_string()))?;
+
+        Ok(records.into_iter().map(|r| r.fingerprint).collect())
+    }
+
+    async fn get_statement_accounts(
+        &mut self,
+        names: &[String],
+        currencies: &[String],
+    ) -> Result<Vec<Account>, DatabaseError> {
+        sqlx::query!(
+            r#"
+            INSERT INTO accounts (name, currency)
+            SELECT name, currency FROM UNNEST($1::TEXT[], $2::TEXT[]) AS a (name, currency)
+            ON CONFLICT (name) DO NOTHING
+            "#,
+            names,
+            currencies
+        )
+        .execute(&mut *self.tx)
+        .await
+        .map_err(|e| DatabaseError::SaveError(e.to_string()))?;
+
+        let records = sqlx::query_as!(
+            Account,
+            r#"
+            SELECT id, name, institution, kind as "kind: AccountKind", currency, opening_balance, closed, created_at, updated_at
+            FROM accounts WHERE name = ANY($1)
+            "#,
+            names
+        )
+        .fetch_all(&mut *self.tx)
+        .await
+        .map_err(|e| DatabaseError::GetError(e.to_string()))?;
+
+        Ok(records)
+    }
+
+    async fn commit(self) -> Result<(), DatabaseError> {
+        self.tx
+            .commit()
+            .await
+            .map_err(|e| DatabaseError::SaveError(e.to_string()))
+    }
+}
+
+impl BatchRead for Postgres {
+    async fn has_reconciled_transactions(&self, id: &str) -> Result<bool, DatabaseError> {
+        let id = Uuid::parse_str(id).map_err(|e| DatabaseError::GetError(e.to_string()))?;
+
+        if let Some(pool) = &self.pool {
+            let record = sqlx::query!(
+                r#"
+            SELECT EXISTS (
+                SELECT 1 FROM payment_transactions WHERE batch_id = $1 AND status = 'reconciled'
+            ) as "reconciled!"
+            "#,
+                id
+            )
+            .fetch_one(pool)
+            .await
+            .map_err(|e| DatabaseError::GetError(e.to_string()))?;
+
+            return Ok(record.reconciled);
+        }
+
+        Err(DatabaseError::GetError("No connection".to_string()))
+    }
+
+    async fn get_batch(&self, id: &str) -> Result<Option<ImportBatch>, DatabaseError> {
+        let id = Uuid::parse_str(id).map_err(|e| DatabaseError::GetError(e.to_string()))?;
+
+        if let Some(pool) = &self.pool {
+            let record = sqlx::query_as!(
+                ImportBatch,
+                r#"
+            SELECT * FROM import_batches WHERE id = $1
+            "#,
+                id
+            )
+            .fetch_optional(pool)
+            .await
+            .map_err(|e| DatabaseError::GetError(e.to_string()))?;
+
+            return Ok(record);
+        }
+
+        Err(DatabaseError::GetError("No connection".to_string()))
+    }
+
+    async fn get_batches(&self) -> Result<Vec<ImportBatch>, DatabaseError> {
+        if let Some(pool) = &self.pool {
+            let records = sqlx::query_as!(
+                ImportBatch,
+                r#"
+            SELECT * FROM import_batches ORDER BY uploaded_at DESC
+            "#
+            )
+            .fetch_all(pool)
+            .await
+            .map_err(|e| DatabaseError::GetError(e.to_string()))?;
+
+            return Ok(records);
+        }
+
+        Err(DatabaseError::GetError("No connection".to_string()))
+    }
+
+    async fn get_batch_transactions(&self, id: &str) -> Result<Vec<Transaction>, DatabaseError> {
+        let id = Uuid::parse_str(id).map_err(|e| DatabaseError::GetError(e.to_string()))?;
+
+        if let Some(pool) = &self.pool {
+            let records = sqlx::query_as!(
+                TransactionRecord,
+                r#"
+            SELECT id, account_id, account_type, payment_date, amount, currency, description, external_id, category,
+                value_date, fingerprint, created_at, updated_at, batch_id, status as "status: TransactionStatus", reconciliation_id
+            FROM payment_transactions WHERE batch_id = $1 ORDER BY payment_date
+            "#,
+                id
+            )
+            .fetch_all(pool)
+            .await
+            .map_err(|e| DatabaseError::GetError(e.to_string()))?;
+
+            return Ok(records.into_iter().map(Transaction::from).collect());
+        }
+
+        Err(DatabaseError::GetError("No connection".to_string()))
+    }
+}
+
+pub struct PostgresJobUpload {
+    id: Uuid,
+    tx: sqlx::Transaction<'static, sqlx::Postgres>,
+}
+
+impl JobWrite for Postgres {
+    type Upload = PostgresJobUpload;
+
+    async fn begin_job(&self) -> Result<PostgresJobUpload, DatabaseError> {
+        if let Some(pool) = &self.pool {
+            let mut tx = pool
+                .begin()
+                .await
+                .map_err(|e| DatabaseError::SaveError(e.to_string()))?;
+
+            let res = sqlx::query!(
+                r#"
+            INSERT INTO import_jobs DEFAULT VALUES RETURNING id
+            "#
+            )
+            .fetch_one(&mut *tx)
+            .await
+            .map_err(|e| DatabaseError::SaveError(e.to_string()))?;
+
+            return Ok(PostgresJobUpload { id: res.id, tx });
+        }
+
+        Err(DatabaseError::ConnectionError("No connection".to_string()))
+    }
+
+    async fn claim_job(&self) -> Result<Option<ImportJob>, DatabaseError> {
+        if let Some(pool) = &self.pool {
+            let record = sqlx::query_as!(
+                ImportJob,
+                r#"
+            UPDATE import_jobs SET state = 'running', started_at = CURRENT_TIMESTAMP
+            WHERE id = (
+                SELECT id FROM import_jobs WHERE state = 'queued'
+                ORDER BY created_at LIMIT 1 FOR UPDATE SKIP LOCKED
+            )
+            RETURNING id, state as "state: JobState", imported_rows, skipped_rows, failed_rows,
+                duplicate_rows, summary, error, created_at, started_at, finished_at
+            "#
+            )
+            .fetch_optional(pool)
+            .await
+            .map_err(|e| DatabaseError::SaveError(e.to_string()))?;
+
+            return Ok(record);
+        }
+
+        Err(DatabaseError::ConnectionError("No connection".to_string()))
+    }
+
+    async fn update_job_progress(&self, id: &str, counts: &Counts) -> Result<(), DatabaseError> {
+        let id = Uuid::parse_str(id).map_err(|e| DatabaseError::SaveError(e.to_string()))?;
+
+        if let Some(pool) = &self.pool {
+            sqlx::query!(
+                r#"
+            UPDATE import_jobs
+            SET imported_rows = $2, skipped_rows = $3, failed_rows = $4, duplicate_rows = $5
+            WHERE id = $1 AND state = 'running'
+            "#,
+                id,
+                counts.imported as i32,
+                counts.skipped as i32,
+                counts.failed as i32,
+                counts.duplicates as i32
+            )
+            .execute(pool)
+            .await
+            .map_err(|e| DatabaseError::SaveError(e.to_string()))?;
+
+            return Ok(());
+        }
+
+        Err(DatabaseError::ConnectionError("No connection".to_string()))
+    }
+
+    async fn finish_job(
+        &self,
+        id: &str,
+        counts: &Counts,
+        summary: serde_json::Value,
+    ) -> Result<(), DatabaseError> {
+        let id = Uuid::parse_str(id).map_err(|e| DatabaseError::SaveError(e.to_string()))?;
+
+        if let Some(pool) = &self.pool {
+            let mut tx = pool
+                .begin()
+                .await
+                .map_err(|e| DatabaseError::SaveError(e.to_string()))?;
+
+            sqlx::query!(
+                r#"
+            UPDATE import_jobs
+            SET state = 'done', imported_rows = $2, skipped_rows = $3, failed_rows = $4,
+                duplicate_rows = $5, summary = $6, finished_at = CURRENT_TIMESTAMP
+            WHERE id = $1
+            "#,
+                id,
+                counts.imported as i32,
+                counts.skipped as i32,
+                counts.failed as i32,
+                counts.duplicates as i32,
+                summary
+            )
+            .execute(&mut *tx)
+            .await
+            .map_err(|e| DatabaseError::SaveError(e.to_string()))?;
+
+            sqlx::query!("DELETE FROM import_job_files WHERE job_id = $1", id)
+                .execute(&mut *tx)
+                .await
+                .map_err(|e| DatabaseError::DeleteError(e.to_string()))?;
+
+            tx.commit()
+                .await
+                .map_err(|e| DatabaseError::SaveError(e.to_string()))?;
+
+            return Ok(());
+        }
+
+        Err(DatabaseError::ConnectionError("No connection".to_string()))
+    }
+
+    async fn fail_job(&self, id: &str, error: &str) -> Result<(), DatabaseError> {
+        let id = Uuid::parse_str(id).map_err(|e| DatabaseError::SaveError(e.to_string()))?;
+
+        if let Some(pool) = &self.pool {
+            let mut tx = pool
+                .begin()
+                .await
+                .map_err(|e| DatabaseError::SaveError(e.to_string()))?;
+
+            // nothing of a failed job is imported, so its counts are cleared
+            sqlx::query!(
+                r#"
+            UPDATE import_jobs
+            SET state = 'failed', imported_rows = 0, skipped_rows = 0, failed_rows = 0,
+                duplicate_rows = 0, error = $2, finished_at = CURRENT_TIMESTAMP
+            WHERE id = $1
+            "#,
+                id,
+                error
+            )
+            .execute(&mut *tx)
+            .await
+            .map_err(|e| DatabaseError::SaveError(e.to_string()))?;
+
+            sqlx::query!("DELETE FROM import_job_files WHERE job_id = $1", id)
+                .execute(&mut *tx)
+                .await
+                .map_err(|e| DatabaseError::DeleteError(e.to_string()))?;
+
+            tx.commit()
+                .await
+                .map_err(|e| DatabaseError::SaveError(e.to_string()))?;
+
+            return Ok(());
+        }
+
+        Err(DatabaseError::ConnectionError("No connection".to_string()))
+    }
+
+    async fn requeue_jobs(&self) -> Result<u64, DatabaseError> {
+        if let Some(pool) = &self.pool {
+            let res = sqlx::query!(
+                r#"
+            UPDATE import_jobs
+            SET state = 'queued', imported_rows = 0, skipped_rows = 0, failed_rows = 0,
+                duplicate_rows = 0, started_at = NULL
+            WHERE state = 'running'
+            "#
+            )
+            .execute(pool)
+            .await
+            .map_err(|e| DatabaseError::SaveError(e.to_string()))?;
+
+            return Ok(res.rows_affected());
+        }
+
+        Err(DatabaseError::ConnectionError("No connection".to_string()))
+    }
+}
+
+impl JobUpload for PostgresJobUpload {
+    async fn add_file(&mut self, file: &ImportJobFile) -> Result<(), DatabaseError> {
+        sqlx::query!(
+            r#"
+            INSERT INTO import_job_files (job_id, position, filename, profile, on_error, account_id)
+            VALUES ($1, $2, $3, $4, $5, $6)
+            "#,
+            self.id,
+            file.position,
+            file.filename,
+            file.profile,
+            file.on_error,
+            file.account_id
+        )
+        .execute(&mut *self.tx)
+        .await
+        .map_err(|e| DatabaseError::SaveError(e.to_string()))?;
+
+        Ok(())
+    }
+
+    async fn add_chunk(
+        &mut self,
+        position: i32,
+        sequence: i32,
+        data: &[u8],
+    ) -> Result<(), DatabaseError> {
+        sqlx::query!(
+            r#"
+            INSERT INTO import_job_chunks (job_id, position, sequence, data)
+            VALUES ($1, $2, $3, $4)
+            "#,
+            self.id,
+            position,
+            sequence,
+            data
+        )
+        .execute(&mut *self.tx)
+        .await
+        .map_err(|e| DatabaseError::SaveError(e.to_string()))?;
+
+        Ok(())
+    }
+
+    async fn commit(mut self) -> Result<ImportJob, DatabaseError> {
+        let record = sqlx::query_as!(
+            ImportJob,
+            r#"
+            SELECT id, state as "state: JobState", imported_rows, skipped_rows, failed_rows,
+                duplicate_rows, summary, error, created_at, started_at, finished_at
+            FROM import_jobs WHERE id = $1
+            "#,
+            self.id
+        )
+        .fetch_one(&mut *self.tx)
+        .await
+        .map_err(|e| DatabaseError::GetError(e.to_string()))?;
+
+        self.tx
+            .commit()
+            .await
+            .map_err(|e| DatabaseError::SaveError(e.to_string()))?;
+
+        Ok(record)
+    }
+}
+
+impl JobRead for Postgres {
+    async fn get_job(&self, id: &str) -> Result<Option<ImportJob>, DatabaseError> {
+        let id = Uuid::parse_str(id).map_err(|e| DatabaseError::GetError(e.to_string()))?;
+
+        if let Some(pool) = &self.pool {
+            let record = sqlx::query_as!(
+                ImportJob,
+                r#"
+            SELECT id, state as "state: JobState", imported_rows, skipped_rows, failed_rows,
+                duplicate_rows, summary, error, created_at, started_at, finished_at
+            FROM import_jobs WHERE id = $1
+            "#,
+                id
+            )
+            .fetch_optional(pool)
+            .await
+            .map_err(|e| DatabaseError::GetError(e.to_string()))?;
+
+            return Ok(record);
+        }
+
+        Err(DatabaseError::GetError("No connection".to_string()))
+    }
+
+    async fn get_job_files(&self, id: &str) -> Result<Vec<ImportJobFile>, DatabaseError> {
+        let id = Uuid::parse_str(id).map_err(|e| DatabaseError::GetError(e.to_string()))?;
+
+        if let Some(pool) = &self.pool {
+            let records = sqlx::query_as!(
+                ImportJobFile,
+                r#"
+            SELECT position, filename, profile, on_error, account_id FROM import_job_files
+            WHERE job_id = $1 ORDER BY position
+            "#,
+                id
+            )
+            .fetch_all(pool)
+            .await
+            .map_err(|e| DatabaseError::GetError(e.to_string()))?;
+
+            return Ok(records);
+        }
+
+        Err(DatabaseError::GetError("No connection".to_string()))
+    }
+
+    async fn get_job_chunk(
+        &self,
+        id: &str,
+        position: i32,
+        sequence: i32,
+    ) -> Result<Option<Vec<u8>>, DatabaseError> {
+        let id = Uuid::parse_str(id).map_err(|e| DatabaseError::GetError(e.to_string()))?;
+
+        if let Some(pool) = &self.pool {
+            let record = sqlx::query!(
+                r#"
+            SELECT data FROM import_job_chunks
+            WHERE job_id = $1 AND position = $2 AND sequence = $3
+            "#,
+                id,
+                position,
+                sequence
+            )
+            .fetch_optional(pool)
+            .await
+            .map_err(|e| DatabaseError::GetError(e.to_string()))?;
+
+            return Ok(record.map(|r| r.data));
+        }
+
+        Err(DatabaseError::GetError("No connection".to_string()))
+    }
+}
+
+impl FxWrite for Postgres {
+    async fn save_rates(&self, rates: &[FxRate]) -> Result<u64, DatabaseError> {
+        let dates: Vec<NaiveDate> = rates.iter().map(|r| r.date).collect();
+        let bases: Vec<String> = rates.iter().map(|r| r.base.clone()).collect();
+        let quotes: Vec<String> = rates.iter().map(|r| r.quote.clone()).collect();
+        let values: Vec<Decimal> = rates.iter().map(|r| r.rate).collect();
+
+        if let Some(pool) = &self.pool {
+            // a file naming the same pair and day twice keeps its last rate
+            let res = sqlx::query!(
+                r#"
+            INSERT INTO fx_rates (rate_date, base, quote, rate)
+            SELECT DISTINCT ON (base, quote, rate_date) rate_date, base, quote, rate
+            FROM UNNEST($1::DATE[], $2::TEXT[], $3::TEXT[], $4::NUMERIC[])
+                WITH ORDINALITY AS r (rate_date, base, quote, rate, position)
+            ORDER BY base, quote, rate_date, position DESC
+            ON CONFLICT (base, quote, rate_date)
+            DO UPDATE SET rate = EXCLUDED.rate, imported_at = CURRENT_TIMESTAMP
+            "#,
+                &dates[..],
+                &bases[..],
+                &quotes[..],
+                &values[..]
+            )
+            .execute(pool)
+            .await
+            .map_err(|e| DatabaseError::SaveError(e.to_string()))?;
+
+            return Ok(res.rows_affected());
+        }
+
+        Err(DatabaseError::ConnectionError("No connection".to_string()))
+    }
+}
+
+impl FxRead for Postgres {
+    async fn get_rates(&self) -> Result<Vec<FxRate>, DatabaseError> {
+        if let Some(pool) = &self.pool {
+            let records = sqlx::query_as!(
+                FxRate,
+                r#"
+            SELECT rate_date AS date, base, quote, rate FROM fx_rates ORDER BY base, quote, rate_date
+            "#
+            )
+            .fetch_all(pool)
+            .await
+            .map_err(|e| DatabaseError::GetError(e.to_string()))?;
+
+            return Ok(records);
+        }
+
+        Err(DatabaseError::GetError("No connection".to_string()))
+    }
+
+    async fn get_rates_between(
+        &self,
+        currency: &str,
+        others: &[String],
+    ) -> Result<Vec<FxRate>, DatabaseError> {
+        if let Some(pool) = &self.pool {
+            let records = sqlx::query_as!(
+                FxRate,
+                r#"
+            SELECT rate_date AS date, base, quote, rate FROM fx_rates
+            WHERE (quote = $1 AND base = ANY($2)) OR (base = $1 AND quote = ANY($2))
+            "#,
+                currency,
+                others
+            )
+            .fetch_all(pool)
+            .await
+            .map_err(|e| DatabaseError::GetError(e.to_string()))?;
+
+            return Ok(records);
+        }
+
+        Err(DatabaseError::GetError("No connection".to_string()))
+    }
+}
+
+impl AccountWrite for Postgres {
+    async fn create_account(&self, account: &CreateAccount) -> Result<Account, DatabaseError> {
+        if let Some(pool) = &self.pool {
+            let record = sqlx::query_as!(
+                Account,
+                r#"
+            INSERT INTO accounts (name, institution, kind, currency, opening_balance, closed)
+            VALUES ($1, $2, $3, $4, $5, $6)
+            RETURNING id, name, institution, kind as "kind: AccountKind", currency, opening_balance, closed, created_at, updated_at
+            "#,
+                account.name,
+                account.institution,
+                account.kind as AccountKind,
+                account.currency,
+                account.opening_balance,
+                account.closed
+            )
+            .fetch_one(pool)
+            .await
+            .map_err(|e| DatabaseError::SaveError(e.to_string()))?;
+
+            return Ok(record);
+        }
+
+        Err(DatabaseError::ConnectionError("No connection".to_string()))
+    }
+
+    async fn update_account(
+        &self,
+        id: &str,
+        account: &CreateAccount,
+    ) -> Result<Option<Account>, DatabaseError> {
+        // no account has an id that is not a uuid
+        let Ok(id) = Uuid::parse_str(id) else {
+            return Ok(None);
+        };
+
+        if let Some(pool) = &self.pool {
+            let record = sqlx::query_as!(
+                Account,
+                r#"
+            UPDATE accounts
+            SET name = $2, institution = $3, kind = $4, currency = $5, opening_balance = $6, closed = $7, updated_at = CURRENT_TIMESTAMP
+            WHERE id = $1
+            RETURNING id, name, institution, kind as "kind: AccountKind", currency, opening_balance, closed, created_at, updated_at
+            "#,
+                id,
+                account.name,
+                account.institution,
+                account.kind as AccountKind,
+                account.currency,
+                account.opening_balance,
+                account.closed
+            )
+            .fetch_optional(pool)
+            .await
+            .map_err(|e| DatabaseError::SaveError(e.to_string()))?;
+
+            return Ok(record);
+        }
+
+        Err(DatabaseError::ConnectionError("No connection".to_string()))
+    }
+
+    async fn delete_account(&self, id: &str) -> Result<bool, DatabaseError> {
+        let Ok(id) = Uuid::parse_str(id) else {
+            return Ok(false);
+        };
+
+        if let Some(pool) = &self.pool {
+            let res = sqlx::query!(
+                r#"
+            DELETE FROM accounts WHERE id = $1
+            "#,
+                id
+            )
+            .execute(pool)
+            .await
+            .map_err(|e| DatabaseError::DeleteError(e.to_string()))?;
+
+            return Ok(res.rows_affected() > 0);
+        }
+
+        Err(DatabaseError::ConnectionError("No connection".to_string()))
+    }
+}
+
+impl AccountRead for Postgres {
+    async fn get_account(&self, id: &str) -> Result<Option<Account>, DatabaseError> {
+        let Ok(id) = Uuid::parse_str(id) else {
+            return Ok(None);
+        };
+
+        if let Some(pool) = &self.pool {
+            let record = sqlx::query_as!(
+                Account,
+                r#"
+            SELECT id, name, institution, kind as "kind: AccountKind", currency, opening_balance, closed, created_at, updated_at
+            FROM accounts WHERE id = $1
+            "#,
+                id
+            )
+            .fetch_optional(pool)
+            .await
+            .map_err(|e| DatabaseError::GetError(e.to_string()))?;
+
+            return Ok(record);
+        }
+
+        Err(DatabaseError::GetError("No connection".to_string()))
+    }
+
+    async fn get_accounts(&self) -> Result<Vec<Account>, DatabaseError> {
+        if let Some(pool) = &self.pool {
+            let records = sqlx::query_as!(
+                Account,
+                r#"
+            SELECT id, name, institution, kind as "kind: AccountKind", currency, opening_balance, closed, created_at, updated_at
+            FROM accounts ORDER BY name
+            "#
+            )
+            .fetch_all(pool)
+            .await
+            .map_err(|e| DatabaseError::GetError(e.to_string()))?;
+
+            return Ok(records);
+        }
+
+        Err(DatabaseError::GetError("No connection".to_string()))
+    }
+
+    async fn get_accounts_named(&self, names: &[String]) -> Result<Vec<Account>, DatabaseError> {
+        if let Some(pool) = &self.pool {
+            let records = sqlx::query_as!(
+                Account,
+                r#"
+            SELECT id, name, institution, kind as "kind: AccountKind", currency, opening_balance, closed, created_at, updated_at
+            FROM accounts WHERE name = ANY($1)
+            "#,
+                names
+            )
+            .fetch_all(pool)
+            .await
+            .map_err(|e| DatabaseError::GetError(e.to_string()))?;
+
+            return Ok(records);
+        }
+
+        Err(DatabaseError::GetError("No connection".to_string()))
+    }
+
+    async fn has_transactions(&self, id: &str) -> Result<bool, DatabaseError> {
+        let Ok(id) = Uuid::parse_str(id) else {
+            return Ok(false);
+        };
+
+        if let Some(pool) = &self.pool {
+            let record = sqlx::query!(
+                r#"
+            SELECT EXISTS (SELECT 1 FROM payment_transactions WHERE account_id = $1) as "in_use!"
+            "#,
+                id
+            )
+            .fetch_one(pool)
+            .await
+            .map_err(|e| DatabaseError::GetError(e.to_string()))?;
+
+            return Ok(record.in_use);
+        }
+
+        Err(DatabaseError::GetError("No connection".to_string()))
+    }
+
+    async fn has_finished_reconciliations(&self, id: &str) -> Result<bool, DatabaseError> {
+        let Ok(id) = Uuid::parse_str(id) else {
+            return Ok(false);
+        };
+
+        if let Some(pool) = &self.pool {
+            let record = sqlx::query!(
+                r#"
+            SELECT EXISTS (
+                SELECT 1 FROM reconciliations WHERE account_id = $1 AND state = 'finished'
+            ) as "reconciled!"
+            "#,
+                id
+            )
+            .fetch_one(pool)
+            .await
+            .map_err(|e| DatabaseError::GetError(e.to_string()))?;
+
+            return Ok(record.reconciled);
+        }
+
+        Err(DatabaseError::GetError("No connection".to_string()))
+    }
+}
+
+impl BalanceWrite for Postgres {
+    async fn save_anchor(
+        &self,
+        account_id: Uuid,
+        anchor: &CreateBalanceAnchor,
+    ) -> Result<BalanceAnchor, DatabaseError> {
+        if let Some(pool) = &self.pool {
+            let record = sqlx::query_as!(
+                BalanceAnchor,
+                r#"
+            INSERT INTO balance_anchors (account_id, balance_date, balance, note)
+            VALUES ($1, $2, $3, $4)
+            ON CONFLICT (account_id, balance_date)
+            DO UPDATE SET balance = EXCLUDED.balance, note = EXCLUDED.note, created_at = CURRENT_TIMESTAMP
+            RETURNING account_id, balance_date AS date, balance, note, created_at
+            "#,
+                account_id,
+                anchor.date,
+                anchor.balance,
+                anchor.note
+            )
+            .fetch_one(pool)
+            .await
+            .map_err(|e| DatabaseError::SaveError(e.to_string()))?;
+
+            return Ok(record);
+        }
+
+        Err(DatabaseError::ConnectionError("No connection".to_string()))
+    }
+
+    async fn delete_anchor(
+        &self,
+        account_id: Uuid,
+        date: NaiveDate,
+    ) -> Result<bool, DatabaseError> {
+        if let Some(pool) = &self.pool {
+            let res = sqlx::query!(
+                r#"
+            DELETE FROM balance_anchors WHERE account_id = $1 AND balance_date = $2
+            "#,
+                account_id,
+                date
+            )
+            .execute(pool)
+            .await
+            .map_err(|e| DatabaseError::DeleteError(e.to_string()))?;
+
+            return Ok(res.rows_affected() > 0);
+        }
+
+        Err(DatabaseError::ConnectionError("No connection".to_string()))
+    }
+}
+
+impl BalanceRead for Postgres {
+    async fn get_anchors(&self, account_id: Uuid) -> Result<Vec<BalanceAnchor>, DatabaseError> {
+        if let Some(pool) = &self.pool {
+            let records = sqlx::query_as!(
+                BalanceAnchor,
+                r#"
+            SELECT account_id, balance_date AS date, balance, note, created_at
+            FROM balance_anchors WHERE account_id = $1 ORDER BY balance_date
+            "#,
+                account_id
+            )
+            .fetch_all(pool)
+            .await
+            .map_err(|e| DatabaseError::GetError(e.to_string()))?;
+
+            return Ok(records);
+        }
+
+        Err(DatabaseError::GetError("No connection".to_string()))
+    }
+
+    async fn get_daily_totals(
+        &self,
+        account_id: Uuid,
+        currency: &str,
+    ) -> Result<Vec<(NaiveDate, Decimal)>, DatabaseError> {
+        if let Some(pool) = &self.pool {
+            let records = sqlx::query!(
+                r#"
+            SELECT payment_date::DATE as "day!", SUM(amount) as "total!"
+            FROM payment_transactions WHERE account_id = $1 AND currency = $2
+            GROUP BY 1 ORDER BY 1
+            "#,
+                account_id,
+                currency
+            )
+            .fetch_all(pool)
+            .await
+            .map_err(|e| DatabaseError::GetError(e.to_string()))?;
+
+            return Ok(records.into_iter().map(|r| (r.day, r.total)).collect());
+        }
+
+        Err(DatabaseError::GetError("No connection".to_string()))
+    }
+
+    async fn get_account_transactions(
+        &self,
+        account_id: Uuid,
+        from: Option<NaiveDate>,
+        to: Option<NaiveDate>,
+    ) -> Result<Vec<Transaction>, DatabaseError> {
+        if let Some(pool) = &self.pool {
+            let records = sqlx::query_as!(
+                TransactionRecord,
+                r#"
+            SELECT id, account_id, account_type, payment_date, amount, currency, description, external_id, category,
+                value_date, fingerprint, created_at, updated_at, batch_id, status as "status: TransactionStatus", reconciliation_id
+            FROM payment_transactions
+            WHERE account_id = $1
+                AND ($2::DATE IS NULL OR payment_date::DATE >= $2)
+                AND ($3::DATE IS NULL OR payment_date::DATE <= $3)
+            ORDER BY payment_date, created_at, id
+            "#,
+                account_id,
+                from,
+                to
+            )
+            .fetch_all(pool)
+            .await
+            .map_err(|e| DatabaseError::GetError(e.to_string()))?;
+
+            return Ok(records.into_iter().map(Transaction::from).collect());
+        }
+
+        Err(DatabaseError::GetError("No connection".to_string()))
+    }
+}
+
+impl ReconciliationWrite for Postgres {
+    async fn create_reconciliation(
+        &self,
+        account_id: Uuid,
+        reconciliation: &CreateReconciliation,
+    ) -> Result<Reconciliation, DatabaseError> {
+        if let Some(pool) = &self.pool {
+            let record = sqlx::query_as!(
+                Reconciliation,
+                r#"
+            INSERT INTO reconciliations (account_id, statement_date, closing_balance)
+            VALUES ($1, $2, $3)
+            RETURNING id, account_id, statement_date, closing_balance, state as "state: ReconciliationState", created_at, finished_at
+            "#,
+                account_id,
+                reconciliation.statement_date,
+                reconciliation.closing_balance
+            )
+            .fetch_one(pool)
+            .await
+            .map_err(|e| DatabaseError::SaveError(e.to_string()))?;
+
+            return Ok(record);
+        }
+
+        Err(DatabaseError::ConnectionError("No connection".to_string()))
+    }
+
+    async fn set_cleared(
+        &self,
+        account_id: Uuid,
+        transactions: &[Uuid],
+        cleared: bool,
+    ) -> Result<(), DatabaseError> {
+        let status = match cleared {
+            true => TransactionStatus::Cleared,
+            false => TransactionStatus::Uncleared,
+        };
+
+        if let Some(pool) = &self.pool {
+            sqlx::query!(
+                r#"
+            UPDATE payment_transactions SET status = $3, updated_at = CURRENT_TIMESTAMP
+            WHERE account_id = $1 AND id = ANY($2) AND status <> 'reconciled'
+            "#,
+                account_id,
+                transactions,
+                status as TransactionStatus
+            )
+            .execute(pool)
+            .await
+            .map_err(|e| DatabaseError::SaveError(e.to_string()))?;
+
+            return Ok(());
+        }
+
+        Err(DatabaseError::ConnectionError("No connection".to_string()))
+    }
+
+    async fn finish_reconciliation(
+        &self,
+        reconciliation: &Reconciliation,
+    ) -> Result<u64, DatabaseError> {
+        if let Some(pool) = &self.pool {
+            let mut tx = pool
+                .begin()
+                .await
+                .map_err(|e| DatabaseError::SaveError(e.to_string()))?;
+
+            let reconciled = sqlx::query!(
+                r#"
+            UPDATE payment_transactions
+            SET status = 'reconciled', reconciliation_id = $2, updated_at = CURRENT_TIMESTAMP
+            WHERE account_id = $1 AND status = 'cleared' AND payment_date::DATE <= $3
+            "#,
+                reconciliation.account_id,
+                reconciliation.id,
+                reconciliation.statement_date
+            )
+            .execute(&mut *tx)
+            .await
+            .map_err(|e| DatabaseError::SaveError(e.to_string()))?;
+
+            sqlx::query!(
+                r#"
+            UPDATE reconciliations SET state = 'finished', finished_at = CURRENT_TIMESTAMP
+            WHERE id = $1
+            "#,
+                reconciliation.id
+            )
+            .execute(&mut *tx)
+            .await
+            .map_err(|e| DatabaseError::SaveError(e.to_string()))?;
+
+            sqlx::query!(
+                r#"
+            INSERT INTO balance_anchors (account_id, balance_date, balance, note)
+            VALUES ($1, $2, $3, 'Reconciled')
+            ON CONFLICT (account_id, balance_date)
+            DO UPDATE SET balance = EXCLUDED.balance, note = EXCLUDED.note, created_at = CURRENT_TIMESTAMP
+            "#,
+                reconciliation.account_id,
+                reconciliation.statement_date,
+                reconciliation.closing_balance
+            )
+            .execute(&mut *tx)
+            .await
+            .map_err(|e| DatabaseError::SaveError(e.to_string()))?;
+
+            tx.commit()
+                .await
+                .map_err(|e| DatabaseError::SaveError(e.to_string()))?;
+
+            return Ok(reconciled.rows_affected());
+        }
+
+        Err(DatabaseError::ConnectionError("No connection".to_string()))
+    }
+
+    async fn delete_reconciliation(&self, id: Uuid) -> Result<bool, DatabaseError> {
+        if let Some(pool) = &self.pool {
+            let res = sqlx::query!(
+                r#"
+            DELETE FROM reconciliations WHERE id = $1 AND state = 'open'
+            "#,
+                id
+            )
+            .execute(pool)
+            .await
+            .map_err(|e| DatabaseError::DeleteError(e.to_string()))?;
+
+            return Ok(res.rows_affected() > 0);
+        }
+
+        Err(DatabaseError::ConnectionError("No connection".to_string()))
+    }
+}
+
+impl ReconciliationRead for Postgres {
+    async fn get_reconciliation(&self, id: &str) -> Result<Option<Reconciliation>, DatabaseError> {
+        let Ok(id) = Uuid::parse_str(id) else {
+            return Ok(None);
+        };
+
+        if let Some(pool) = &self.pool {
+            let record = sqlx::query_as!(
+                Reconciliation,
+                r#"
+            SELECT id, account_id, statement_date, closing_balance, state as "state: ReconciliationState", created_at, finished_at
+            FROM reconciliations WHERE id = $1
+            "#,
+                id
+            )
+            .fetch_optional(pool)
+            .await
+            .map_err(|e| DatabaseError::GetError(e.to_string()))?;
+
+            return Ok(record);
+        }
+
+        Err(DatabaseError::GetError("No connection".to_string()))
+    }
+
+    async fn get_reconciliations(
+        &self,
+        account_id: Uuid,
+    ) -> Result<Vec<Reconciliation>, DatabaseError> {
+        if let Some(pool) = &self.pool {
+            let records = sqlx::query_as!(
+                Reconciliation,
+                r#"
+            SELECT id, account_id, statement_date, closing_balance, state as "state: ReconciliationState", created_at, finished_at
+            FROM reconciliations WHERE account_id = $1 ORDER BY statement_date
+            "#,
+                account_id
+            )
+            .fetch_all(pool)
+            .await
+            .map_err(|e| DatabaseError::GetError(e.to_string()))?;
+
+            return Ok(records);
+        }
+
+        Err(DatabaseError::GetError("No connection".to_string()))
+    }
+
+    async fn get_cleared_total(
+        &self,
+        account_id: Uuid,
+        currency: &str,
+        to: NaiveDate,
+    ) -> Result<Decimal, DatabaseError> {
+        if let Some(pool) = &self.pool {
+            let record = sqlx::query!(
+                r#"
+            SELECT COALESCE(SUM(amount), 0) as "total!" FROM payment_transactions
+            WHERE account_id = $1 AND currency = $2 AND status <> 'uncleared' AND payment_date::DATE <= $3
+            "#,
+                account_id,
+                currency,
+                to
+            )
+            .fetch_one(pool)
+            .await
+            .map_err(|e| DatabaseError::GetError(e.to_string()))?;
+
+            return Ok(record.total);
+        }
+
+        Err(DatabaseError::GetError("No connection".to_string()))
+    }
+
+    async fn get_unreconciled_transactions(
+        &self,
+        reconciliation: &Reconciliation,
+    ) -> Result<Vec<Transaction>, DatabaseError> {
+        if let Some(pool) = &self.pool {
+            let records = sqlx::query_as!(
+                TransactionRecord,
+                r#"
+            SELECT id, account_id, account_type, payment_date, amount, currency, description, external_id, category,
+                value_date, fingerprint, created_at, updated_at, batch_id, status as "status: TransactionStatus", reconciliation_id
+            FROM payment_transactions
+            WHERE account_id = $1 AND status <> 'reconciled' AND payment_date::DATE <= $2
+            ORDER BY payment_date, created_at, id
+            "#,
+                reconciliation.account_id,
+                reconciliation.statement_date
+            )
+            .fetch_all(pool)
+            .await
+            .map_err(|e| DatabaseError::GetError(e.to_string()))?;
+
+            return Ok(records.into_iter().map(Transaction::from).collect());
+        }
+
+        Err(DatabaseError::GetError("No connection".to_string()))
+    }
+
+    async fn get_unreconciled_ids(
+        &self,
+        account_id: Uuid,
+        transactions: &[Uuid],
+        to: NaiveDate,
+    ) -> Result<Vec<Uuid>, DatabaseError> {
+        if let Some(pool) = &self.pool {
+            let records = sqlx::query!(
+                r#"
+            SELECT id FROM payment_transactions
+            WHERE account_id = $1 AND id = ANY($2) AND status <> 'reconciled' AND payment_date::DATE <= $3
+            "#,
+                account_id,
+                transactions,
+                to
+            )
+            .fetch_all(pool)
+            .await
+            .map_err(|e| DatabaseError::GetError(e.to_string()))?;
+
+            return Ok(records.into_iter().map(|r| r.id).collect());
+        }
+
+        Err(DatabaseError::GetError("No connection".to_string()))
+    }
 }
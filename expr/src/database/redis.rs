@@ -1,9 +1,8 @@
 use std::{error::Error, fmt::Display, sync::Arc};
 
 use tokio::sync::RwLock;
-use uuid::Uuid;
 
-use crate::{models::transaction::CreateTransaction, service::transaction::TransactionWrite};
+use crate::service::transaction::TransactionWrite;
 
 use super::base::{DatabaseError, DatabaseInit};
 
@@ -67,14 +66,7 @@ impl DatabaseInit for Redis {
 }
 
 impl TransactionWrite for Redis {
-    async fn create_transaction(
-        &self,
-        _create_transaction: CreateTransaction,
-    ) -> Result<Uuid, DatabaseError> {
-        todo!()
-    }
-
-    async fn delete_transaction(&self, id: &str) -> Result<(), DatabaseError> {
+    async fn delete_transaction(&self, _id: &str) -> Result<(), DatabaseError> {
         todo!()
     }
 }
@@ -1,9 +1,7 @@
 use std::fs::{remove_file, File};
 
-use uuid::Uuid;
-
 use crate::{
-    models::transaction::{CreateTransaction, Transaction},
+    models::transaction::Transaction,
     service::transaction::{TransactionRead, TransactionWrite},
 };
 
@@ -30,7 +28,7 @@ impl DatabaseInit for TextFile {
 
         self.file = Some(new_file);
 
-        return Ok(());
+        Ok(())
     }
 
     async fn disconnect(&mut self) -> Result<(), DatabaseError> {
@@ -41,14 +39,7 @@ impl DatabaseInit for TextFile {
 }
 
 impl TransactionWrite for TextFile {
-    async fn create_transaction(
-        &self,
-        _create_transaction: CreateTransaction,
-    ) -> Result<Uuid, DatabaseError> {
-        todo!()
-    }
-
-    async fn delete_transaction(&self, id: &str) -> Result<(), DatabaseError> {
+    async fn delete_transaction(&self, _id: &str) -> Result<(), DatabaseError> {
         todo!()
     }
 }
@@ -61,4 +52,14 @@ impl TransactionRead for TextFile {
     async fn get_transactions(&self) -> Result<Vec<Transaction>, DatabaseError> {
         todo!()
     }
+
+    // nothing is saved with a fingerprint, so duplicates cannot be found
+    async fn get_known_fingerprints(
+        &self,
+        _fingerprints: &[String],
+    ) -> Result<Vec<String>, DatabaseError> {
+        Err(DatabaseError::GetError(
+            "Text files do not store fingerprints".to_string(),
+        ))
+    }
 }
@@ -1,10 +1,15 @@
+use std::path::PathBuf;
+
 use clap::Parser;
+use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt, EnvFilter};
 
 mod cli;
 mod database;
 mod models;
 mod service;
+mod watch;
 mod web;
+mod worker;
 
 #[derive(Parser)]
 #[command(author, version, about)]
@@ -12,20 +17,47 @@ struct Args {
     // start a web server
     #[arg(short = 's', long, default_value = "false")]
     enable_server: bool,
+
+    // import the statement files dropped into a directory
+    #[arg(short = 'w', long, value_name = "DIR")]
+    watch: Option<PathBuf>,
 }
 
 #[tokio::main]
 async fn main() {
     let args = Args::parse();
     if args.enable_server {
-        let server = web::Server::new().await;
-        match server.start().await {
-            Ok(_) => (),
-            Err(e) => eprintln!("Error: {}", e),
+        logging();
+        let started = match web::Server::new().await {
+            Ok(server) => server.start().await,
+            Err(e) => Err(e),
+        };
+        if let Err(e) = started {
+            eprintln!("Error: {}", e);
+        }
+        return;
+    }
+
+    if let Some(directory) = args.watch {
+        logging();
+        let started = match watch::Watcher::new(directory).await {
+            Ok(watcher) => watcher.start().await,
+            Err(e) => Err(e),
         };
+        if let Err(e) = started {
+            eprintln!("Error: {}", e);
+        }
         return;
     }
 
     let cli = cli::Cli::new();
     cli.start();
 }
+
+// set up before connecting, so the server and watcher log their start up
+fn logging() {
+    tracing_subscriber::registry()
+        .with(EnvFilter::new("info"))
+        .with(tracing_subscriber::fmt::layer())
+        .init();
+}
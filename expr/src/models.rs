@@ -1 +1,7 @@
+pub mod account;
+pub mod balance;
+pub mod batch;
+pub mod fx;
+pub mod job;
+pub mod reconciliation;
 pub mod transaction;
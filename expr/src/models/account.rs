@@ -0,0 +1,55 @@
+use chrono::NaiveDateTime;
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use import::service::parse_service::DEFAULT_CURRENCY;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize, sqlx::Type)]
+#[serde(rename_all = "snake_case")]
+#[sqlx(type_name = "account_kind", rename_all = "snake_case")]
+pub enum AccountKind {
+    #[default]
+    Current,
+    Credit,
+    Savings,
+    Loan,
+}
+
+// an account transactions are imported into
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Account {
+    pub id: Uuid,
+    pub name: String,
+    // the bank or card issuer, e.g. Monzo
+    pub institution: Option<String>,
+    #[serde(rename = "type")]
+    pub kind: AccountKind,
+    pub currency: String,
+    // the balance before the first transaction
+    pub opening_balance: Decimal,
+    // closed accounts are kept with their transactions but nothing more can be
+    // imported into them
+    pub closed: bool,
+    pub created_at: NaiveDateTime,
+    pub updated_at: NaiveDateTime,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct CreateAccount {
+    pub name: String,
+    #[serde(default)]
+    pub institution: Option<String>,
+    #[serde(default, rename = "type")]
+    pub kind: AccountKind,
+    #[serde(default = "default_currency")]
+    pub currency: String,
+    #[serde(default)]
+    pub opening_balance: Decimal,
+    #[serde(default)]
+    pub closed: bool,
+}
+
+fn default_currency() -> String {
+    DEFAULT_CURRENCY.to_string()
+}
@@ -0,0 +1,57 @@
+use chrono::{NaiveDate, NaiveDateTime};
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use super::transaction::Transaction;
+
+// an account's balance at the end of a day as shown on a statement
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct BalanceAnchor {
+    pub account_id: Uuid,
+    pub date: NaiveDate,
+    pub balance: Decimal,
+    pub note: Option<String>,
+    pub created_at: NaiveDateTime,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct CreateBalanceAnchor {
+    pub date: NaiveDate,
+    pub balance: Decimal,
+    #[serde(default)]
+    pub note: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Interval {
+    #[default]
+    Day,
+    Week,
+    Month,
+}
+
+// the balance at the end of the day
+#[derive(Serialize, Debug)]
+pub struct BalancePoint {
+    pub date: NaiveDate,
+    pub balance: Decimal,
+}
+
+#[derive(Serialize, Debug)]
+pub struct BalanceSeries {
+    pub account_id: Uuid,
+    pub currency: String,
+    pub interval: Interval,
+    pub balances: Vec<BalancePoint>,
+}
+
+// a transaction with the account's balance once it was paid, transactions in
+// another currency than the account's do not change it and have none
+#[derive(Serialize, Debug)]
+pub struct TransactionBalance {
+    #[serde(flatten)]
+    pub transaction: Transaction,
+    pub balance: Option<Decimal>,
+}
@@ -0,0 +1,33 @@
+use chrono::NaiveDateTime;
+use serde::{Deserialize, Serialize};
+
+// one uploaded statement file and the row counts of its import
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ImportBatch {
+    pub id: String,
+    pub filename: Option<String>,
+    pub profile: Option<String>,
+    pub format: String,
+    // sha256 of the file's contents
+    pub checksum: String,
+    pub imported_rows: i32,
+    pub skipped_rows: i32,
+    pub failed_rows: i32,
+    // rows already imported by an earlier upload
+    pub duplicate_rows: i32,
+    pub uploaded_at: NaiveDateTime,
+    // set once the batch's transactions have been deleted
+    pub rolled_back_at: Option<NaiveDateTime>,
+}
+
+#[derive(Debug)]
+pub struct CreateImportBatch {
+    pub filename: Option<String>,
+    pub profile: Option<String>,
+    pub format: String,
+    pub checksum: String,
+    pub imported_rows: i32,
+    pub skipped_rows: i32,
+    pub failed_rows: i32,
+    pub duplicate_rows: i32,
+}
@@ -0,0 +1,65 @@
+use chrono::NaiveDate;
+use import::service::parse_service::Money;
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use super::{
+    balance::{BalancePoint, Interval},
+    transaction::Transaction,
+};
+
+// one unit of base is worth rate units of quote on the date, e.g. 1 EUR = 0.85 GBP
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct FxRate {
+    pub date: NaiveDate,
+    pub base: String,
+    pub quote: String,
+    pub rate: Decimal,
+}
+
+// an amount presented in another currency and the rate it was converted at
+#[derive(Serialize, Debug)]
+pub struct Conversion {
+    pub amount: Money,
+    pub rate: Decimal,
+    // the day of the rate, the latest one on or before the payment date
+    pub rate_date: NaiveDate,
+}
+
+// a transaction with its amount in a requested currency, there is no
+// conversion when no rate was known on or before the payment date
+#[derive(Serialize, Debug)]
+pub struct ConvertedTransaction {
+    #[serde(flatten)]
+    pub transaction: Transaction,
+    pub converted: Option<Conversion>,
+}
+
+// a balance with its amount in a requested currency
+#[derive(Serialize, Debug)]
+pub struct ConvertedBalance {
+    #[serde(flatten)]
+    pub balance: BalancePoint,
+    pub converted: Option<Conversion>,
+}
+
+// a balance series with each balance in a requested currency as well, currency
+// stays the account's
+#[derive(Serialize, Debug)]
+pub struct ConvertedBalanceSeries {
+    pub account_id: Uuid,
+    pub currency: String,
+    pub interval: Interval,
+    pub balances: Vec<ConvertedBalance>,
+}
+
+// a transaction with its amount and the balance after it in a requested
+// currency
+#[derive(Serialize, Debug)]
+pub struct ConvertedTransactionBalance {
+    #[serde(flatten)]
+    pub transaction: ConvertedTransaction,
+    pub balance: Option<Decimal>,
+    pub converted_balance: Option<Conversion>,
+}
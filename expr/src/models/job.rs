@@ -0,0 +1,45 @@
+use chrono::NaiveDateTime;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use uuid::Uuid;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize, sqlx::Type)]
+#[serde(rename_all = "snake_case")]
+#[sqlx(type_name = "import_job_state", rename_all = "snake_case")]
+pub enum JobState {
+    Queued,
+    Running,
+    Done,
+    Failed,
+}
+
+// an upload imported in the background. the row counts are updated as the
+// files are saved, the summary holds the report of each file once it is done
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ImportJob {
+    pub id: String,
+    pub state: JobState,
+    pub imported_rows: i32,
+    pub skipped_rows: i32,
+    pub failed_rows: i32,
+    pub duplicate_rows: i32,
+    pub summary: Option<Value>,
+    // why the job failed, none of its files are imported when it does
+    pub error: Option<String>,
+    pub created_at: NaiveDateTime,
+    pub started_at: Option<NaiveDateTime>,
+    pub finished_at: Option<NaiveDateTime>,
+}
+
+// one statement file of a job, the text fields sent before it decide how it
+// is read
+#[derive(Debug, Clone)]
+pub struct ImportJobFile {
+    pub position: i32,
+    pub filename: Option<String>,
+    pub profile: Option<String>,
+    pub on_error: String,
+    // the account the file is imported into, otherwise the account named after
+    // the statement's
+    pub account_id: Option<Uuid>,
+}
@@ -0,0 +1,61 @@
+use chrono::{NaiveDate, NaiveDateTime};
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use super::transaction::Transaction;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize, sqlx::Type)]
+#[serde(rename_all = "snake_case")]
+#[sqlx(type_name = "reconciliation_state", rename_all = "snake_case")]
+pub enum ReconciliationState {
+    Open,
+    Finished,
+}
+
+// a statement being checked against an account's transactions
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Reconciliation {
+    pub id: Uuid,
+    pub account_id: Uuid,
+    // the last day the statement covers
+    pub statement_date: NaiveDate,
+    pub closing_balance: Decimal,
+    pub state: ReconciliationState,
+    pub created_at: NaiveDateTime,
+    pub finished_at: Option<NaiveDateTime>,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct CreateReconciliation {
+    pub statement_date: NaiveDate,
+    pub closing_balance: Decimal,
+}
+
+// ticks transactions off a statement, or unticks them
+#[derive(Deserialize, Debug)]
+pub struct ClearTransactions {
+    pub transactions: Vec<Uuid>,
+    #[serde(default = "cleared")]
+    pub cleared: bool,
+}
+
+fn cleared() -> bool {
+    true
+}
+
+// where a reconciliation stands. the cleared balance is the opening balance
+// and every cleared or reconciled transaction in the account's currency paid
+// by the statement date, the statement matches once the difference to its
+// closing balance is zero
+#[derive(Serialize, Debug)]
+pub struct ReconciliationSummary {
+    #[serde(flatten)]
+    pub reconciliation: Reconciliation,
+    pub currency: String,
+    pub cleared_balance: Decimal,
+    pub difference: Decimal,
+    // the transactions still to be reconciled that were paid by the statement
+    // date
+    pub transactions: Vec<Transaction>,
+}
@@ -2,25 +2,76 @@ use core::fmt;
 use std::fmt::Display;
 
 use chrono::NaiveDateTime;
+use import::service::parse_service::{Money, ParsedTransaction};
 use serde::{Deserialize, Serialize};
+use uuid::Uuid;
 
-#[derive(Serialize, Deserialize, Debug)]
+// uncleared transactions have not been seen on a statement yet, cleared ones
+// have been ticked off one being reconciled and reconciled ones are locked
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize, sqlx::Type)]
+#[serde(rename_all = "snake_case")]
+#[sqlx(type_name = "transaction_status", rename_all = "snake_case")]
+pub enum TransactionStatus {
+    #[default]
+    Uncleared,
+    Cleared,
+    Reconciled,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct Transaction {
     pub id: String,
+    pub account_id: Uuid,
+    // what the statement called the account, e.g. the profile name or an IBAN
     pub account_type: String,
     pub payment_date: NaiveDateTime,
-    pub amount: f64,
+    pub amount: Money,
     pub description: String,
+    // identifier assigned by the bank, e.g. the OFX FITID
+    pub external_id: Option<String>,
+    pub category: Option<String>,
+    // date the money was actually credited or debited, when the bank reports it
+    pub value_date: Option<NaiveDateTime>,
+    pub fingerprint: Option<String>,
     pub created_at: NaiveDateTime,
     pub updated_at: NaiveDateTime,
+    // the import batch that created the transaction
+    pub batch_id: Option<Uuid>,
+    pub status: TransactionStatus,
+    // the reconciliation that locked the transaction
+    pub reconciliation_id: Option<Uuid>,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct CreateTransaction {
+    // set once the account the transaction is imported into is known
+    pub account_id: Option<Uuid>,
     pub account_type: String,
     pub payment_date: NaiveDateTime,
-    pub amount: f64,
+    // negative for money going out of the account, positive for money coming in
+    pub amount: Money,
     pub description: String,
+    pub external_id: Option<String>,
+    pub category: Option<String>,
+    pub value_date: Option<NaiveDateTime>,
+    // identifies the transaction across overlapping statements, set on import
+    pub fingerprint: Option<String>,
+}
+
+impl From<ParsedTransaction> for CreateTransaction {
+    fn from(t: ParsedTransaction) -> Self {
+        Self {
+            account_id: None,
+            account_type: t.account_type,
+            payment_date: t.payment_date,
+            amount: t.amount,
+            description: t.description,
+            external_id: t.external_id,
+            category: t.category,
+            value_date: t.value_date,
+            fingerprint: None,
+        }
+    }
 }
 
 impl Display for CreateTransaction {
@@ -1,2 +1,9 @@
+pub mod account;
+pub mod balance;
+pub mod batch;
+pub mod fx;
+pub mod job;
 pub mod parse;
+pub mod profile;
+pub mod reconciliation;
 pub mod transaction;
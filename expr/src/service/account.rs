@@ -0,0 +1,368 @@
+use core::fmt;
+use std::{fmt::Display, sync::Arc};
+use tokio::sync::RwLock;
+
+use import::service::parse_service::is_currency;
+use uuid::Uuid;
+
+use crate::{
+    database::base::{DatabaseError, DatabaseInit},
+    models::account::{Account, CreateAccount},
+};
+
+#[allow(clippy::enum_variant_names)]
+pub enum AccountError {
+    InvalidError(String),
+    SaveError(String),
+    FindError(String),
+    DeleteError(String),
+}
+
+impl Display for AccountError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AccountError::InvalidError(e) => write!(f, "AccountError -> InvalidError, {}", e),
+            AccountError::SaveError(e) => write!(f, "AccountError -> SaveError, {}", e),
+            AccountError::FindError(e) => write!(f, "AccountError -> FindError, {}", e),
+            AccountError::DeleteError(e) => write!(f, "AccountError -> DeleteError, {}", e),
+        }
+    }
+}
+
+pub trait AccountWrite {
+    async fn create_account(&self, account: &CreateAccount) -> Result<Account, DatabaseError>;
+
+    // returns None when no account has the given id
+    async fn update_account(
+        &self,
+        id: &str,
+        account: &CreateAccount,
+    ) -> Result<Option<Account>, DatabaseError>;
+
+    // returns false when no account has the given id
+    async fn delete_account(&self, id: &str) -> Result<bool, DatabaseError>;
+}
+
+pub trait AccountRead {
+    async fn get_account(&self, id: &str) -> Result<Option<Account>, DatabaseError>;
+    async fn get_accounts(&self) -> Result<Vec<Account>, DatabaseError>;
+    async fn get_accounts_named(&self, names: &[String]) -> Result<Vec<Account>, DatabaseError>;
+    async fn has_transactions(&self, id: &str) -> Result<bool, DatabaseError>;
+    async fn has_finished_reconciliations(&self, id: &str) -> Result<bool, DatabaseError>;
+}
+
+pub struct AccountService<T>
+where
+    T: DatabaseInit + AccountWrite + AccountRead,
+{
+    db: Arc<RwLock<T>>,
+}
+
+impl<T> AccountService<T>
+where
+    T: DatabaseInit + AccountWrite + AccountRead,
+{
+    pub fn new(db: T) -> AccountService<T> {
+        let db = Arc::new(RwLock::new(db));
+        Self { db }
+    }
+
+    pub async fn create_account(&self, account: CreateAccount) -> Result<Account, AccountError> {
+        let account = self.validate(None, account).await?;
+        let db_connection = self.db.write().await;
+
+        db_connection
+            .create_account(&account)
+            .await
+            .map_err(|e| AccountError::SaveError(e.to_string()))
+    }
+
+    // the opening balance and currency of a reconciled account are fixed, the
+    // finished reconciliations were worked out from them
+    pub async fn update_account(
+        &self,
+        id: &str,
+        account: CreateAccount,
+    ) -> Result<Option<Account>, AccountError> {
+        let account = self.validate(Some(id), account).await?;
+        let db_connection = self.db.write().await;
+
+        let current = db_connection
+            .get_account(id)
+            .await
+            .map_err(|e| AccountError::FindError(e.to_string()))?;
+        let Some(current) = current else {
+            return Ok(None);
+        };
+
+        if current.opening_balance != account.opening_balance
+            || current.currency != account.currency
+        {
+            let reconciled = db_connection
+                .has_finished_reconciliations(id)
+                .await
+                .map_err(|e| AccountError::FindError(e.to_string()))?;
+            if reconciled {
+                return Err(AccountError::InvalidError(format!(
+                    "Account {} has been reconciled, its opening balance and currency cannot change",
+                    current.name
+                )));
+            }
+        }
+
+        db_connection
+            .update_account(id, &account)
+            .await
+            .map_err(|e| AccountError::SaveError(e.to_string()))
+    }
+
+    pub async fn find_account(&self, id: &str) -> Result<Option<Account>, AccountError> {
+        let db_connection = self.db.read().await;
+
+        db_connection
+            .get_account(id)
+            .await
+            .map_err(|e| AccountError::FindError(e.to_string()))
+    }
+
+    pub async fn find_accounts(&self) -> Result<Vec<Account>, AccountError> {
+        let db_connection = self.db.read().await;
+
+        db_connection
+            .get_accounts()
+            .await
+            .map_err(|e| AccountError::FindError(e.to_string()))
+    }
+
+    pub async fn find_accounts_named(
+        &self,
+        names: &[String],
+    ) -> Result<Vec<Account>, AccountError> {
+        let db_connection = self.db.read().await;
+
+        db_connection
+            .get_accounts_named(names)
+            .await
+            .map_err(|e| AccountError::FindError(e.to_string()))
+    }
+
+    // an account with transactions is closed rather than deleted, so they are
+    // never left without one
+    pub async fn delete_account(&self, id: &str) -> Result<bool, AccountError> {
+        let db_connection = self.db.write().await;
+
+        let in_use = db_connection
+            .has_transactions(id)
+            .await
+            .map_err(|e| AccountError::FindError(e.to_string()))?;
+        if in_use {
+            return Err(AccountError::InvalidError(format!(
+                "Account {} has transactions, close it instead",
+                id
+            )));
+        }
+
+        db_connection
+            .delete_account(id)
+            .await
+            .map_err(|e| AccountError::DeleteError(e.to_string()))
+    }
+
+    // names are unique so an import can pick its account by name
+    async fn validate(
+        &self,
+        id: Option<&str>,
+        mut account: CreateAccount,
+    ) -> Result<CreateAccount, AccountError> {
+        account.name = account.name.trim().to_string();
+        account.currency = account.currency.trim().to_ascii_uppercase();
+
+        if account.name.is_empty() {
+            return Err(AccountError::InvalidError(
+                "An account needs a name".to_string(),
+            ));
+        }
+
+        if !is_currency(&account.currency) {
+            return Err(AccountError::InvalidError(format!(
+                "Invalid currency: {}",
+                account.currency
+            )));
+        }
+
+        let named = self.find_accounts_named(&[account.name.clone()]).await?;
+        let id = id.and_then(|id| Uuid::parse_str(id).ok());
+        if named.iter().any(|a| Some(a.id) != id) {
+            return Err(AccountError::InvalidError(format!(
+                "An account named {} already exists",
+                account.name
+            )));
+        }
+
+        Ok(account)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{NaiveDate, NaiveDateTime};
+    use import::service::parse_service::Money;
+    use rust_decimal::Decimal;
+
+    use super::*;
+    use crate::{
+        database::memory::Memory,
+        models::{
+            account::AccountKind,
+            reconciliation::{Reconciliation, ReconciliationState},
+        },
+    };
+
+    fn new_account(name: &str, currency: &str) -> CreateAccount {
+        CreateAccount {
+            name: name.to_string(),
+            institution: None,
+            kind: AccountKind::Current,
+            currency: currency.to_string(),
+            opening_balance: Decimal::ZERO,
+            closed: false,
+        }
+    }
+
+    async fn create(accs: &AccountService<Memory>, name: &str) -> Account {
+        accs.create_account(new_account(name, "GBP"))
+            .await
+            .unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    fn invalid<V: fmt::Debug>(result: Result<V, AccountError>) -> String {
+        match result {
+            Err(AccountError::InvalidError(e)) => e,
+            Err(e) => panic!("{}", e),
+            Ok(v) => panic!("{:?} was accepted", v),
+        }
+    }
+
+    #[tokio::test]
+    async fn accounts_are_created_trimmed_and_found() {
+        let accs = AccountService::new(Memory::default());
+
+        let account = accs
+            .create_account(new_account("  Monzo ", " eur"))
+            .await
+            .unwrap_or_else(|e| panic!("{}", e));
+
+        assert_eq!(account.name, "Monzo");
+        assert_eq!(account.currency, "EUR");
+        let found = accs.find_account(&account.id.to_string()).await;
+        assert_eq!(found.ok().flatten().map(|a| a.id), Some(account.id));
+        let named = accs.find_accounts_named(&["Monzo".to_string()]).await;
+        assert_eq!(named.map(|a| a.len()).ok(), Some(1));
+    }
+
+    #[tokio::test]
+    async fn invalid_accounts_are_refused() {
+        let accs = AccountService::new(Memory::default());
+
+        assert!(invalid(accs.create_account(new_account(" ", "GBP")).await).contains("name"));
+        assert!(
+            invalid(accs.create_account(new_account("Monzo", "GB")).await).contains("currency")
+        );
+        assert!(accs.find_accounts().await.is_ok_and(|a| a.is_empty()));
+    }
+
+    #[tokio::test]
+    async fn names_are_unique() {
+        let accs = AccountService::new(Memory::default());
+        let monzo = create(&accs, "Monzo").await;
+        let amex = create(&accs, "Amex").await;
+
+        let duplicate = accs.create_account(new_account("Monzo ", "GBP")).await;
+        assert!(invalid(duplicate).contains("already exists"));
+
+        let renamed = accs
+            .update_account(&amex.id.to_string(), new_account("Monzo", "GBP"))
+            .await;
+        assert!(invalid(renamed).contains("already exists"));
+
+        // an account keeps its own name when it is updated
+        let mut closed = new_account("Monzo", "GBP");
+        closed.closed = true;
+        let updated = accs
+            .update_account(&monzo.id.to_string(), closed)
+            .await
+            .unwrap_or_else(|e| panic!("{}", e));
+        assert!(updated.is_some_and(|a| a.closed));
+    }
+
+    #[tokio::test]
+    async fn updating_an_unknown_account_finds_none() {
+        let accs = AccountService::new(Memory::default());
+
+        let updated = accs
+            .update_account(&Uuid::new_v4().to_string(), new_account("Monzo", "GBP"))
+            .await;
+
+        assert!(updated.is_ok_and(|a| a.is_none()));
+    }
+
+    #[tokio::test]
+    async fn a_reconciled_account_keeps_its_opening_balance_and_currency() {
+        let memory = Memory::default();
+        let accs = AccountService::new(memory.clone());
+        let account = create(&accs, "Monzo").await;
+        let id = account.id.to_string();
+        memory.tables().reconciliations.push(Reconciliation {
+            id: Uuid::new_v4(),
+            account_id: account.id,
+            statement_date: NaiveDate::from_ymd_opt(2024, 3, 31).unwrap(),
+            closing_balance: Decimal::ZERO,
+            state: ReconciliationState::Finished,
+            created_at: NaiveDateTime::default(),
+            finished_at: Some(NaiveDateTime::default()),
+        });
+
+        let mut opening = new_account("Monzo", "GBP");
+        opening.opening_balance = Decimal::ONE_HUNDRED;
+        assert!(invalid(accs.update_account(&id, opening).await).contains("reconciled"));
+        assert!(
+            invalid(accs.update_account(&id, new_account("Monzo", "EUR")).await)
+                .contains("reconciled")
+        );
+
+        let renamed = accs
+            .update_account(&id, new_account("Monzo Joint", "GBP"))
+            .await;
+        assert!(renamed.is_ok_and(|a| a.is_some_and(|a| a.name == "Monzo Joint")));
+    }
+
+    #[tokio::test]
+    async fn an_account_with_transactions_is_not_deleted() {
+        let memory = Memory::default();
+        let accs = AccountService::new(memory.clone());
+        let used = create(&accs, "Monzo").await;
+        let unused = create(&accs, "Amex").await;
+        memory.add_transaction(
+            used.id,
+            NaiveDate::from_ymd_opt(2024, 3, 1).unwrap(),
+            Money::new(Decimal::ONE, "GBP"),
+        );
+
+        let refused = accs.delete_account(&used.id.to_string()).await;
+        assert!(invalid(refused).contains("close it instead"));
+        assert!(accs
+            .delete_account(&unused.id.to_string())
+            .await
+            .is_ok_and(|d| d));
+        assert!(accs
+            .delete_account(&unused.id.to_string())
+            .await
+            .is_ok_and(|d| !d));
+
+        let left = accs
+            .find_accounts()
+            .await
+            .unwrap_or_else(|e| panic!("{}", e));
+        assert_eq!(left.iter().map(|a| a.id).collect::<Vec<_>>(), [used.id]);
+    }
+}
@@ -0,0 +1,608 @@
+use core::fmt;
+use std::{fmt::Display, sync::Arc};
+use tokio::sync::RwLock;
+
+use chrono::{Days, Months, NaiveDate, Utc};
+use rust_decimal::Decimal;
+use uuid::Uuid;
+
+use crate::{
+    database::base::{DatabaseError, DatabaseInit},
+    models::{
+        account::Account,
+        balance::{
+            BalanceAnchor, BalancePoint, BalanceSeries, CreateBalanceAnchor, Interval,
+            TransactionBalance,
+        },
+        transaction::Transaction,
+    },
+};
+
+use super::account::AccountRead;
+
+// points in one balance series, longer ranges need a longer interval
+const MAX_POINTS: usize = 5000;
+
+#[allow(clippy::enum_variant_names)]
+pub enum BalanceError {
+    InvalidError(String),
+    SaveError(String),
+    FindError(String),
+    DeleteError(String),
+}
+
+impl Display for BalanceError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BalanceError::InvalidError(e) => write!(f, "BalanceError -> InvalidError, {}", e),
+            BalanceError::SaveError(e) => write!(f, "BalanceError -> SaveError, {}", e),
+            BalanceError::FindError(e) => write!(f, "BalanceError -> FindError, {}", e),
+            BalanceError::DeleteError(e) => write!(f, "BalanceError -> DeleteError, {}", e),
+        }
+    }
+}
+
+pub trait BalanceWrite {
+    // replaces the anchor of the same day
+    async fn save_anchor(
+        &self,
+        account_id: Uuid,
+        anchor: &CreateBalanceAnchor,
+    ) -> Result<BalanceAnchor, DatabaseError>;
+
+    // returns false when the account has no anchor on the day
+    async fn delete_anchor(&self, account_id: Uuid, date: NaiveDate)
+        -> Result<bool, DatabaseError>;
+}
+
+pub trait BalanceRead {
+    async fn get_anchors(&self, account_id: Uuid) -> Result<Vec<BalanceAnchor>, DatabaseError>;
+
+    // the sum of the account's transactions in the currency on each day they
+    // were paid, oldest first
+    async fn get_daily_totals(
+        &self,
+        account_id: Uuid,
+        currency: &str,
+    ) -> Result<Vec<(NaiveDate, Decimal)>, DatabaseError>;
+
+    // the account's transactions paid between the days, in the order they
+    // were paid
+    async fn get_account_transactions(
+        &self,
+        account_id: Uuid,
+        from: Option<NaiveDate>,
+        to: Option<NaiveDate>,
+    ) -> Result<Vec<Transaction>, DatabaseError>;
+}
+
+pub struct BalanceService<T>
+where
+    T: DatabaseInit + BalanceWrite + BalanceRead + AccountRead,
+{
+    db: Arc<RwLock<T>>,
+}
+
+impl<T> BalanceService<T>
+where
+    T: DatabaseInit + BalanceWrite + BalanceRead + AccountRead,
+{
+    pub fn new(db: T) -> BalanceService<T> {
+        let db = Arc::new(RwLock::new(db));
+        Self { db }
+    }
+
+    // the balance at the end of every interval from the first day to the last,
+    // by default from the account's first transaction or anchor until today.
+    // returns None when no account has the id
+    pub async fn find_balances(
+        &self,
+        id: &str,
+        from: Option<NaiveDate>,
+        to: Option<NaiveDate>,
+        interval: Interval,
+    ) -> Result<Option<BalanceSeries>, BalanceError> {
+        let to = to.unwrap_or_else(|| Utc::now().date_naive());
+        check_range(from, Some(to))?;
+        let Some(account) = self.find_account(id).await? else {
+            return Ok(None);
+        };
+        let ledger = self.ledger(&account).await?;
+
+        // an account whose history starts after the last day still has its
+        // balance on that day
+        let from = from.unwrap_or_else(|| ledger.first_day().unwrap_or(to).min(to));
+
+        let mut balances = Vec::new();
+        for step in 0.. {
+            let date = match interval {
+                Interval::Day => from.checked_add_days(Days::new(step)),
+                Interval::Week => from.checked_add_days(Days::new(step * 7)),
+                // stepped from the first day so the 31st stays the last of
+                // shorter months
+                Interval::Month => from.checked_add_months(Months::new(step as u32)),
+            };
+            let Some(date) = date.filter(|d| *d <= to) else {
+                break;
+            };
+
+            if balances.len() == MAX_POINTS {
+                return Err(BalanceError::InvalidError(format!(
+                    "More than {} balances were asked for, use a longer interval",
+                    MAX_POINTS
+                )));
+            }
+
+            balances.push(BalancePoint {
+                date,
+                balance: ledger.balance(date),
+            });
+        }
+
+        Ok(Some(BalanceSeries {
+            account_id: account.id,
+            currency: account.currency,
+            interval,
+            balances,
+        }))
+    }
+
+    // the account's transactions with the balance after each one. returns
+    // None when no account has the id
+    pub async fn find_transaction_balances(
+        &self,
+        id: &str,
+        from: Option<NaiveDate>,
+        to: Option<NaiveDate>,
+    ) -> Result<Option<Vec<TransactionBalance>>, BalanceError> {
+        check_range(from, to)?;
+        let Some(account) = self.find_account(id).await? else {
+            return Ok(None);
+        };
+        let ledger = self.ledger(&account).await?;
+
+        let transactions = {
+            let db_connection = self.db.read().await;
+
+            db_connection
+                .get_account_transactions(account.id, from, to)
+                .await
+                .map_err(|e| BalanceError::FindError(e.to_string()))?
+        };
+
+        // each day's transactions are counted from the balance it ends on, so
+        // the last one of a day with an anchor ends on the anchor
+        let mut day = None;
+        let mut balance = Decimal::ZERO;
+        let mut balances = Vec::with_capacity(transactions.len());
+        for transaction in transactions {
+            let date = transaction.payment_date.date();
+            if day != Some(date) {
+                day = Some(date);
+                balance = ledger.start_of(date);
+            }
+
+            let changed = transaction.amount.currency == account.currency;
+            if changed {
+                balance += transaction.amount.amount;
+            }
+
+            balances.push(TransactionBalance {
+                transaction,
+                balance: changed.then_some(balance),
+            });
+        }
+
+        Ok(Some(balances))
+    }
+
+    // returns None when no account has the id
+    pub async fn find_anchors(&self, id: &str) -> Result<Option<Vec<BalanceAnchor>>, BalanceError> {
+        let Some(account) = self.find_account(id).await? else {
+            return Ok(None);
+        };
+        let db_connection = self.db.read().await;
+
+        db_connection
+            .get_anchors(account.id)
+            .await
+            .map(Some)
+            .map_err(|e| BalanceError::FindError(e.to_string()))
+    }
+
+    // returns None when no account has the id
+    pub async fn save_anchor(
+        &self,
+        id: &str,
+        anchor: CreateBalanceAnchor,
+    ) -> Result<Option<BalanceAnchor>, BalanceError> {
+        let Some(account) = self.find_account(id).await? else {
+            return Ok(None);
+        };
+        let db_connection = self.db.write().await;
+
+        db_connection
+            .save_anchor(account.id, &anchor)
+            .await
+            .map(Some)
+            .map_err(|e| BalanceError::SaveError(e.to_string()))
+    }
+
+    // returns false when the account has no anchor on the day
+    pub async fn delete_anchor(&self, id: &str, date: NaiveDate) -> Result<bool, BalanceError> {
+        let Some(account) = self.find_account(id).await? else {
+            return Ok(false);
+        };
+        let db_connection = self.db.write().await;
+
+        db_connection
+            .delete_anchor(account.id, date)
+            .await
+            .map_err(|e| BalanceError::DeleteError(e.to_string()))
+    }
+
+    async fn find_account(&self, id: &str) -> Result<Option<Account>, BalanceError> {
+        let db_connection = self.db.read().await;
+
+        db_connection
+            .get_account(id)
+            .await
+            .map_err(|e| BalanceError::FindError(e.to_string()))
+    }
+
+    async fn ledger(&self, account: &Account) -> Result<Ledger, BalanceError> {
+        let db_connection = self.db.read().await;
+
+        let days = db_connection
+            .get_daily_totals(account.id, &account.currency)
+            .await
+            .map_err(|e| BalanceError::FindError(e.to_string()))?;
+        let anchors = db_connection
+            .get_anchors(account.id)
+            .await
+            .map_err(|e| BalanceError::FindError(e.to_string()))?;
+
+        Ok(Ledger::new(account.opening_balance, days, anchors))
+    }
+}
+
+fn check_range(from: Option<NaiveDate>, to: Option<NaiveDate>) -> Result<(), BalanceError> {
+    match (from, to) {
+        (Some(from), Some(to)) if from > to => Err(BalanceError::InvalidError(format!(
+            "The first day {} is after the last day {}",
+            from, to
+        ))),
+        _ => Ok(()),
+    }
+}
+
+// works out an account's balance on any day from its daily totals. the latest
+// anchor on or before the day is counted on from, without one the earliest
+// anchor after it is counted back from, and without any the opening balance.
+// only transactions in the account's currency are counted, the others cannot
+// be added up without a rate
+struct Ledger {
+    opening: Decimal,
+    days: Vec<NaiveDate>,
+    // the total of every day up to, not including, the day at the same index
+    totals: Vec<Decimal>,
+    anchors: Vec<(NaiveDate, Decimal)>,
+}
+
+impl Ledger {
+    fn new(
+        opening: Decimal,
+        daily: Vec<(NaiveDate, Decimal)>,
+        anchors: Vec<BalanceAnchor>,
+    ) -> Self {
+        let mut days = Vec::with_capacity(daily.len());
+        let mut totals = Vec::with_capacity(daily.len() + 1);
+        let mut total = Decimal::ZERO;
+        totals.push(total);
+        for (day, amount) in daily {
+            days.push(day);
+            total += amount;
+            totals.push(total);
+        }
+
+        let mut anchors: Vec<(NaiveDate, Decimal)> =
+            anchors.into_iter().map(|a| (a.date, a.balance)).collect();
+        anchors.sort_by_key(|a| a.0);
+
+        Self {
+            opening,
+            days,
+            totals,
+            anchors,
+        }
+    }
+
+    fn first_day(&self) -> Option<NaiveDate> {
+        let day = self.days.first().copied();
+        let anchor = self.anchors.first().map(|a| a.0);
+
+        match (day, anchor) {
+            (Some(d), Some(a)) => Some(d.min(a)),
+            _ => day.or(anchor),
+        }
+    }
+
+    // the total of the transactions paid up to the end of the day
+    fn total(&self, date: NaiveDate) -> Decimal {
+        self.totals[self.days.partition_point(|d| *d <= date)]
+    }
+
+    // the balance at the start of the day, counted back from its end
+    fn start_of(&self, date: NaiveDate) -> Decimal {
+        let before = date.pred_opt().map_or(Decimal::ZERO, |d| self.total(d));
+
+        self.balance(date) - (self.total(date) - before)
+    }
+
+    // the balance at the end of the day
+    fn balance(&self, date: NaiveDate) -> Decimal {
+        let after = self.anchors.partition_point(|a| a.0 <= date);
+
+        if let Some((day, balance)) = after.checked_sub(1).map(|i| self.anchors[i]) {
+            return balance + self.total(date) - self.total(day);
+        }
+
+        if let Some((day, balance)) = self.anchors.get(after) {
+            return *balance - (self.total(*day) - self.total(date));
+        }
+
+        self.opening + self.total(date)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::NaiveDateTime;
+    use import::service::parse_service::Money;
+
+    use super::*;
+    use crate::database::memory::Memory;
+
+    fn date(day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(2024, 3, day).unwrap()
+    }
+
+    fn dec(value: &str) -> Decimal {
+        value.parse().unwrap()
+    }
+
+    // an opening balance of 100, then 10 out on the 1st, 60 in and 10 out on
+    // the 3rd, 20 out on the 5th and 5 out on the 8th
+    const HISTORY: [(u32, &str, &str); 6] = [
+        (1, "-10", "GBP"),
+        (3, "60", "GBP"),
+        (3, "-10", "GBP"),
+        (5, "-20", "GBP"),
+        (5, "-99", "EUR"),
+        (8, "-5", "GBP"),
+    ];
+
+    // the account with its history, and a statement balance at the end of
+    // each of the given days
+    fn memory(anchors: &[(u32, &str)]) -> (Memory, String) {
+        let db = Memory::default();
+        let account = db.add_account("Monzo", "GBP", dec("100"));
+        for (day, amount, currency) in HISTORY {
+            db.add_transaction(account.id, date(day), Money::new(dec(amount), currency));
+        }
+        for (day, balance) in anchors {
+            db.tables().anchors.push(BalanceAnchor {
+                account_id: account.id,
+                date: date(*day),
+                balance: dec(balance),
+                note: None,
+                created_at: NaiveDateTime::default(),
+            });
+        }
+
+        (db, account.id.to_string())
+    }
+
+    fn ledger(anchors: &[(u32, &str)]) -> Ledger {
+        let (db, _) = memory(anchors);
+        let account = db.tables().accounts[0].clone();
+        let days = db.daily_totals(account.id, &account.currency);
+        let anchors = db.tables().anchors.clone();
+
+        Ledger::new(account.opening_balance, days, anchors)
+    }
+
+    fn balances(ledger: &Ledger, days: &[u32]) -> Vec<Decimal> {
+        days.iter().map(|d| ledger.balance(date(*d))).collect()
+    }
+
+    #[test]
+    fn without_anchors_the_opening_balance_is_counted_on() {
+        let ledger = ledger(&[]);
+
+        assert_eq!(ledger.first_day(), Some(date(1)));
+        assert_eq!(
+            balances(&ledger, &[1, 2, 3, 5, 8, 30]),
+            ["90", "90", "140", "120", "115", "115"].map(dec)
+        );
+        // before the first transaction, the eur one is never counted
+        assert_eq!(
+            ledger.balance(NaiveDate::from_ymd_opt(2024, 2, 29).unwrap()),
+            dec("100")
+        );
+    }
+
+    #[test]
+    fn an_anchor_in_the_middle_is_counted_back_and_on_from() {
+        // the statement says 500 at the end of the 3rd, the opening balance
+        // is ignored
+        let ledger = ledger(&[(3, "500")]);
+
+        assert_eq!(
+            balances(&ledger, &[1, 2, 3, 4, 5, 8]),
+            ["450", "450", "500", "500", "480", "475"].map(dec)
+        );
+        assert_eq!(
+            ledger.balance(NaiveDate::from_ymd_opt(2024, 2, 29).unwrap()),
+            dec("460")
+        );
+    }
+
+    #[test]
+    fn the_latest_anchor_wins_after_it() {
+        // the anchors disagree with the transactions between them
+        let ledger = ledger(&[(5, "1000"), (3, "500")]);
+
+        assert_eq!(
+            balances(&ledger, &[2, 3, 4, 5, 6, 8]),
+            ["450", "500", "500", "1000", "1000", "995"].map(dec)
+        );
+    }
+
+    #[test]
+    fn an_anchor_before_any_transaction_is_the_first_day() {
+        let ledger = ledger(&[(1, "0")]);
+        assert_eq!(ledger.first_day(), Some(date(1)));
+
+        let earlier = Ledger::new(
+            Decimal::ZERO,
+            Vec::new(),
+            vec![BalanceAnchor {
+                account_id: Uuid::nil(),
+                date: date(4),
+                balance: dec("7"),
+                note: None,
+                created_at: NaiveDateTime::default(),
+            }],
+        );
+        assert_eq!(earlier.first_day(), Some(date(4)));
+        assert_eq!(balances(&earlier, &[1, 4, 9]), ["7", "7", "7"].map(dec));
+    }
+
+    // the balance after each of the history's transactions
+    async fn transaction_balances(anchors: &[(u32, &str)]) -> Vec<Option<Decimal>> {
+        let (db, id) = memory(anchors);
+        let service = BalanceService::new(db);
+
+        service
+            .find_transaction_balances(&id, None, None)
+            .await
+            .unwrap_or_else(|e| panic!("{}", e))
+            .expect("account")
+            .into_iter()
+            .map(|t| t.balance)
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn transactions_on_an_anchor_day_end_on_the_anchor() {
+        assert_eq!(
+            transaction_balances(&[(3, "500")]).await,
+            [
+                Some(dec("450")),
+                Some(dec("510")),
+                Some(dec("500")),
+                Some(dec("480")),
+                None,
+                Some(dec("475")),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn transactions_end_on_each_anchor_when_anchors_disagree() {
+        // 20 went out on the 5th but the statement balance rose by 500
+        assert_eq!(
+            transaction_balances(&[(3, "500"), (5, "1000")]).await,
+            [
+                Some(dec("450")),
+                Some(dec("510")),
+                Some(dec("500")),
+                Some(dec("1000")),
+                None,
+                Some(dec("995")),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn a_first_day_after_the_last_is_refused() {
+        let (db, id) = memory(&[]);
+        let service = BalanceService::new(db);
+
+        let series = service
+            .find_balances(&id, Some(date(5)), Some(date(4)), Interval::Day)
+            .await;
+        assert!(matches!(series, Err(BalanceError::InvalidError(_))));
+
+        let transactions = service
+            .find_transaction_balances(&id, Some(date(5)), Some(date(4)))
+            .await;
+        assert!(matches!(transactions, Err(BalanceError::InvalidError(_))));
+
+        // a single day is fine
+        let day = service
+            .find_balances(&id, Some(date(5)), Some(date(5)), Interval::Day)
+            .await
+            .unwrap_or_else(|e| panic!("{}", e))
+            .expect("account");
+        assert_eq!(day.balances.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn a_series_ends_on_the_day_asked_for() {
+        let (db, id) = memory(&[(3, "500")]);
+        let service = BalanceService::new(db);
+
+        let series = service
+            .find_balances(&id, None, Some(date(8)), Interval::Week)
+            .await
+            .unwrap_or_else(|e| panic!("{}", e))
+            .expect("account");
+
+        let points: Vec<(NaiveDate, Decimal)> = series
+            .balances
+            .iter()
+            .map(|p| (p.date, p.balance))
+            .collect();
+        assert_eq!(points, [(date(1), dec("450")), (date(8), dec("475"))]);
+        assert!(service
+            .find_balances(&Uuid::new_v4().to_string(), None, None, Interval::Day)
+            .await
+            .unwrap_or_else(|e| panic!("{}", e))
+            .is_none());
+    }
+
+    #[tokio::test]
+    async fn a_series_is_capped_at_max_points() {
+        let (db, id) = memory(&[]);
+        let service = BalanceService::new(db);
+        let last = date(1)
+            .checked_add_days(Days::new(MAX_POINTS as u64 - 1))
+            .unwrap();
+
+        let series = service
+            .find_balances(&id, Some(date(1)), Some(last), Interval::Day)
+            .await
+            .unwrap_or_else(|e| panic!("{}", e))
+            .expect("account");
+        assert_eq!(series.balances.len(), MAX_POINTS);
+        assert_eq!(series.balances.last().map(|p| p.date), Some(last));
+
+        let past = last.succ_opt();
+        assert!(matches!(
+            service
+                .find_balances(&id, Some(date(1)), past, Interval::Day)
+                .await,
+            Err(BalanceError::InvalidError(_))
+        ));
+
+        // the same range by month is well under the cap
+        let monthly = service
+            .find_balances(&id, Some(date(1)), past, Interval::Month)
+            .await
+            .unwrap_or_else(|e| panic!("{}", e))
+            .expect("account");
+        assert!(monthly.balances.len() < MAX_POINTS);
+    }
+}
@@ -0,0 +1,249 @@
+use core::fmt;
+use std::{fmt::Display, sync::Arc};
+use tokio::sync::RwLock;
+use uuid::Uuid;
+
+use crate::{
+    database::base::{DatabaseError, DatabaseInit},
+    models::{
+        account::Account,
+        batch::{CreateImportBatch, ImportBatch},
+        transaction::{CreateTransaction, Transaction},
+    },
+};
+
+#[allow(clippy::enum_variant_names)]
+pub enum BatchError {
+    InvalidError(String),
+    SaveError(String),
+    FindError(String),
+    RollbackError(String),
+}
+
+impl Display for BatchError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BatchError::InvalidError(e) => write!(f, "BatchError -> InvalidError, {}", e),
+            BatchError::SaveError(e) => write!(f, "BatchError -> SaveError, {}", e),
+            BatchError::FindError(e) => write!(f, "BatchError -> FindError, {}", e),
+            BatchError::RollbackError(e) => write!(f, "BatchError -> RollbackError, {}", e),
+        }
+    }
+}
+
+pub trait BatchWrite {
+    type Session: ImportSession;
+
+    // starts the database transaction an upload's batches are saved in
+    async fn begin_import(&self) -> Result<Self::Session, DatabaseError>;
+
+    // deletes the batch's transactions and marks it rolled back, returns the
+    // number of transactions deleted or None when no batch has the given id
+    async fn rollback_batch(&self, id: &str) -> Result<Option<u64>, DatabaseError>;
+}
+
+// an upload's open database transaction, nothing is visible to other
+// connections until it is committed and dropping it saves nothing
+pub trait ImportSession {
+    async fn create_batch(&mut self, batch: &CreateImportBatch) -> Result<Uuid, DatabaseError>;
+
+    // replaces the batch's checksum and row counts
+    async fn update_batch(
+        &mut self,
+        id: Uuid,
+        batch: &CreateImportBatch,
+    ) -> Result<(), DatabaseError>;
+
+    async fn create_transactions(
+        &mut self,
+        batch_id: Uuid,
+        transactions: &[CreateTransaction],
+    ) -> Result<(), DatabaseError>;
+
+    async fn delete_transactions(&mut self, batch_id: Uuid) -> Result<(), DatabaseError>;
+
+    // the given fingerprints already saved, including earlier in this session
+    async fn get_known_fingerprints(
+        &mut self,
+        fingerprints: &[String],
+    ) -> Result<Vec<String>, DatabaseError>;
+
+    // the accounts with the given names, those missing are created in the
+    // currency at the same position
+    async fn get_statement_accounts(
+        &mut self,
+        names: &[String],
+        currencies: &[String],
+    ) -> Result<Vec<Account>, DatabaseError>;
+
+    async fn commit(self) -> Result<(), DatabaseError>;
+}
+
+pub trait BatchRead {
+    async fn get_batch(&self, id: &str) -> Result<Option<ImportBatch>, DatabaseError>;
+    async fn get_batches(&self) -> Result<Vec<ImportBatch>, DatabaseError>;
+    async fn get_batch_transactions(&self, id: &str) -> Result<Vec<Transaction>, DatabaseError>;
+    async fn has_reconciled_transactions(&self, id: &str) -> Result<bool, DatabaseError>;
+}
+
+pub struct BatchService<T>
+where
+    T: DatabaseInit + BatchWrite + BatchRead,
+{
+    db: Arc<RwLock<T>>,
+}
+
+impl<T> BatchService<T>
+where
+    T: DatabaseInit + BatchWrite + BatchRead,
+{
+    pub fn new(db: T) -> BatchService<T> {
+        let db = Arc::new(RwLock::new(db));
+        Self { db }
+    }
+
+    pub async fn begin_import(&self) -> Result<T::Session, BatchError> {
+        let db_connection = self.db.read().await;
+
+        db_connection
+            .begin_import()
+            .await
+            .map_err(|e| BatchError::SaveError(e.to_string()))
+    }
+
+    pub async fn find_batch(&self, id: &str) -> Result<Option<ImportBatch>, BatchError> {
+        let db_connection = self.db.read().await;
+
+        db_connection
+            .get_batch(id)
+            .await
+            .map_err(|e| BatchError::FindError(e.to_string()))
+    }
+
+    pub async fn find_batches(&self) -> Result<Vec<ImportBatch>, BatchError> {
+        let db_connection = self.db.read().await;
+
+        db_connection
+            .get_batches()
+            .await
+            .map_err(|e| BatchError::FindError(e.to_string()))
+    }
+
+    pub async fn find_batch_transactions(&self, id: &str) -> Result<Vec<Transaction>, BatchError> {
+        let db_connection = self.db.read().await;
+
+        db_connection
+            .get_batch_transactions(id)
+            .await
+            .map_err(|e| BatchError::FindError(e.to_string()))
+    }
+
+    // a batch with reconciled transactions cannot be rolled back, they are
+    // locked
+    pub async fn rollback_batch(&self, id: &str) -> Result<Option<u64>, BatchError> {
+        let db_connection = self.db.write().await;
+
+        let reconciled = db_connection
+            .has_reconciled_transactions(id)
+            .await
+            .map_err(|e| BatchError::FindError(e.to_string()))?;
+        if reconciled {
+            return Err(BatchError::InvalidError(format!(
+                "Import batch {} has reconciled transactions",
+                id
+            )));
+        }
+
+        db_connection
+            .rollback_batch(id)
+            .await
+            .map_err(|e| BatchError::RollbackError(e.to_string()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::NaiveDate;
+    use import::service::parse_service::Money;
+    use rust_decimal::Decimal;
+
+    use super::*;
+    use crate::{database::memory::Memory, models::transaction::TransactionStatus};
+
+    // two imported batches of two transactions each, returned by their ids
+    fn service() -> (BatchService<Memory>, Memory, Vec<String>) {
+        let db = Memory::default();
+        let account = db.add_account("Monzo", "GBP", Decimal::ZERO);
+        let day = NaiveDate::from_ymd_opt(2024, 2, 1).unwrap();
+
+        let mut ids = Vec::new();
+        for _ in 0..2 {
+            let batch_id = Uuid::new_v4();
+            for _ in 0..2 {
+                db.add_transaction(account.id, day, Money::new(Decimal::ONE, "GBP"));
+                db.tables().transactions.last_mut().unwrap().batch_id = Some(batch_id);
+            }
+            db.tables().batches.push(ImportBatch {
+                id: batch_id.to_string(),
+                filename: None,
+                profile: None,
+                format: "csv".to_string(),
+                checksum: String::new(),
+                imported_rows: 2,
+                skipped_rows: 0,
+                failed_rows: 0,
+                duplicate_rows: 0,
+                uploaded_at: Default::default(),
+                rolled_back_at: None,
+            });
+            ids.push(batch_id.to_string());
+        }
+
+        (BatchService::new(db.clone()), db, ids)
+    }
+
+    #[tokio::test]
+    async fn batches_with_reconciled_transactions_cannot_be_rolled_back() {
+        let (service, db, ids) = service();
+        db.tables().transactions[0].status = TransactionStatus::Reconciled;
+
+        let rolled_back = service.rollback_batch(&ids[0]).await;
+
+        assert!(matches!(rolled_back, Err(BatchError::InvalidError(_))));
+        assert_eq!(db.tables().transactions.len(), 4);
+        assert!(db.tables().batches[0].rolled_back_at.is_none());
+    }
+
+    #[tokio::test]
+    async fn rolling_back_deletes_only_the_batch_transactions() {
+        let (service, db, ids) = service();
+
+        let rolled_back = service
+            .rollback_batch(&ids[0])
+            .await
+            .unwrap_or_else(|e| panic!("{}", e));
+
+        assert_eq!(rolled_back, Some(2));
+        let tables = db.tables();
+        assert!(tables
+            .transactions
+            .iter()
+            .all(|t| t.batch_id.map(|b| b.to_string()).as_ref() == Some(&ids[1])));
+        // the batch is kept as history
+        assert!(tables.batches[0].rolled_back_at.is_some());
+        assert!(tables.batches[1].rolled_back_at.is_none());
+    }
+
+    #[tokio::test]
+    async fn unknown_batches_are_not_rolled_back() {
+        let (service, db, _) = service();
+
+        let rolled_back = service
+            .rollback_batch(&Uuid::new_v4().to_string())
+            .await
+            .unwrap_or_else(|e| panic!("{}", e));
+
+        assert_eq!(rolled_back, None);
+        assert_eq!(db.tables().transactions.len(), 4);
+    }
+}
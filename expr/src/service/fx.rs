@@ -0,0 +1,660 @@
+use core::fmt;
+use std::{collections::HashMap, fmt::Display, str::FromStr, sync::Arc};
+use tokio::sync::RwLock;
+
+use chrono::NaiveDate;
+use import::service::parse_service::{is_currency, Money};
+use rust_decimal::Decimal;
+use serde::Deserialize;
+use serde_json::Value;
+
+use crate::{
+    database::base::{DatabaseError, DatabaseInit},
+    models::{
+        balance::{BalanceSeries, TransactionBalance},
+        fx::{
+            Conversion, ConvertedBalance, ConvertedBalanceSeries, ConvertedTransaction,
+            ConvertedTransactionBalance, FxRate,
+        },
+        transaction::Transaction,
+    },
+};
+
+// decimal places kept of a rate worked out from the inverse of a saved one
+const INVERSE_RATE_DP: u32 = 10;
+
+// decimal places of converted amounts
+const CONVERTED_DP: u32 = 2;
+
+#[allow(clippy::enum_variant_names)]
+pub enum FxError {
+    InvalidError(String),
+    SaveError(String),
+    FindError(String),
+}
+
+impl Display for FxError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FxError::InvalidError(e) => write!(f, "FxError -> InvalidError, {}", e),
+            FxError::SaveError(e) => write!(f, "FxError -> SaveError, {}", e),
+            FxError::FindError(e) => write!(f, "FxError -> FindError, {}", e),
+        }
+    }
+}
+
+pub trait FxWrite {
+    // saves the rates, replacing any already saved for the same pair and day
+    async fn save_rates(&self, rates: &[FxRate]) -> Result<u64, DatabaseError>;
+}
+
+pub trait FxRead {
+    async fn get_rates(&self) -> Result<Vec<FxRate>, DatabaseError>;
+
+    // the rates between the currency and any of the others, either way round
+    async fn get_rates_between(
+        &self,
+        currency: &str,
+        others: &[String],
+    ) -> Result<Vec<FxRate>, DatabaseError>;
+}
+
+pub struct FxService<T>
+where
+    T: DatabaseInit + FxWrite + FxRead,
+{
+    db: Arc<RwLock<T>>,
+}
+
+impl<T> FxService<T>
+where
+    T: DatabaseInit + FxWrite + FxRead,
+{
+    pub fn new(db: T) -> FxService<T> {
+        let db = Arc::new(RwLock::new(db));
+        Self { db }
+    }
+
+    // saves the rates of a csv or json rate file, returns the number saved
+    pub async fn import_rates(&self, filename: Option<&str>, data: &str) -> Result<u64, FxError> {
+        let rates = parse_rates(filename, data)?;
+        let db_connection = self.db.write().await;
+
+        db_connection
+            .save_rates(&rates)
+            .await
+            .map_err(|e| FxError::SaveError(e.to_string()))
+    }
+
+    pub async fn find_rates(&self) -> Result<Vec<FxRate>, FxError> {
+        let db_connection = self.db.read().await;
+
+        db_connection
+            .get_rates()
+            .await
+            .map_err(|e| FxError::FindError(e.to_string()))
+    }
+
+    // presents each transaction's amount in the currency using the latest rate
+    // on or before its payment date
+    pub async fn convert(
+        &self,
+        transactions: Vec<Transaction>,
+        currency: &str,
+    ) -> Result<Vec<ConvertedTransaction>, FxError> {
+        let others = transactions.iter().map(|t| t.amount.currency.clone());
+        let rates = self.rates(currency, others).await?;
+
+        Ok(transactions
+            .into_iter()
+            .map(|transaction| rates.convert_transaction(transaction))
+            .collect())
+    }
+
+    // presents each balance of the series in the currency using the latest rate
+    // on or before its day
+    pub async fn convert_balances(
+        &self,
+        series: BalanceSeries,
+        currency: &str,
+    ) -> Result<ConvertedBalanceSeries, FxError> {
+        let rates = self.rates(currency, [series.currency.clone()]).await?;
+
+        let balances = series
+            .balances
+            .into_iter()
+            .map(|balance| {
+                let amount = Money::new(balance.balance, &series.currency);
+                ConvertedBalance {
+                    converted: rates.convert(&amount, balance.date),
+                    balance,
+                }
+            })
+            .collect();
+
+        Ok(ConvertedBalanceSeries {
+            account_id: series.account_id,
+            currency: series.currency,
+            interval: series.interval,
+            balances,
+        })
+    }
+
+    // presents the amount of each transaction and the balance after it in the
+    // currency. a balance is in the currency of its transaction's amount
+    pub async fn convert_transaction_balances(
+        &self,
+        transactions: Vec<TransactionBalance>,
+        currency: &str,
+    ) -> Result<Vec<ConvertedTransactionBalance>, FxError> {
+        let others = transactions
+            .iter()
+            .map(|t| t.transaction.amount.currency.clone());
+        let rates = self.rates(currency, others).await?;
+
+        Ok(transactions
+            .into_iter()
+            .map(
+                |TransactionBalance {
+                     transaction,
+                     balance,
+                 }| {
+                    let converted_balance = balance.and_then(|b| {
+                        let amount = Money::new(b, &transaction.amount.currency);
+                        rates.convert(&amount, transaction.payment_date.date())
+                    });
+                    ConvertedTransactionBalance {
+                        transaction: rates.convert_transaction(transaction),
+                        balance,
+                        converted_balance,
+                    }
+                },
+            )
+            .collect())
+    }
+
+    // the saved rates into the currency from any of the others
+    async fn rates(
+        &self,
+        currency: &str,
+        others: impl IntoIterator<Item = String>,
+    ) -> Result<Rates, FxError> {
+        let currency = currency.trim().to_ascii_uppercase();
+        if !is_currency(&currency) {
+            return Err(FxError::InvalidError(format!(
+                "Invalid currency: {}",
+                currency
+            )));
+        }
+
+        let mut others: Vec<String> = others.into_iter().filter(|c| *c != currency).collect();
+        others.sort();
+        others.dedup();
+
+        let saved = if others.is_empty() {
+            Vec::new()
+        } else {
+            let db_connection = self.db.read().await;
+
+            db_connection
+                .get_rates_between(&currency, &others)
+                .await
+                .map_err(|e| FxError::FindError(e.to_string()))?
+        };
+
+        Ok(Rates::new(&currency, saved))
+    }
+}
+
+// the rates into one currency from each of the others, oldest first
+struct Rates {
+    currency: String,
+    from: HashMap<String, Vec<(NaiveDate, Decimal)>>,
+}
+
+impl Rates {
+    fn new(currency: &str, saved: Vec<FxRate>) -> Self {
+        let mut from: HashMap<String, Vec<(NaiveDate, Decimal, bool)>> = HashMap::new();
+        for r in saved {
+            if r.quote == currency {
+                from.entry(r.base).or_default().push((r.date, r.rate, true));
+            } else if r.base == currency {
+                let inverse = (Decimal::ONE / r.rate)
+                    .round_dp(INVERSE_RATE_DP)
+                    .normalize();
+                from.entry(r.quote)
+                    .or_default()
+                    .push((r.date, inverse, false));
+            }
+        }
+
+        let from = from
+            .into_iter()
+            .map(|(other, mut rates)| {
+                // a rate saved the right way round wins over an inverse one of the same day
+                rates.sort_by(|a, b| a.0.cmp(&b.0).then(b.2.cmp(&a.2)));
+                rates.dedup_by_key(|r| r.0);
+                (other, rates.into_iter().map(|r| (r.0, r.1)).collect())
+            })
+            .collect();
+
+        Self {
+            currency: currency.to_string(),
+            from,
+        }
+    }
+
+    fn convert_transaction(&self, transaction: Transaction) -> ConvertedTransaction {
+        ConvertedTransaction {
+            converted: self.convert(&transaction.amount, transaction.payment_date.date()),
+            transaction,
+        }
+    }
+
+    fn convert(&self, amount: &Money, on: NaiveDate) -> Option<Conversion> {
+        let (rate_date, rate) = if amount.currency == self.currency {
+            (on, Decimal::ONE)
+        } else {
+            let rates = self.from.get(&amount.currency)?;
+            let known = rates.partition_point(|r| r.0 <= on);
+            *rates.get(known.checked_sub(1)?)?
+        };
+
+        Some(Conversion {
+            amount: Money::new(
+                (amount.amount * rate).round_dp(CONVERTED_DP),
+                &self.currency,
+            ),
+            rate,
+            rate_date,
+        })
+    }
+}
+
+// a rate as written in a rate file, from and to are accepted for base and quote.
+// json rates can be numbers or strings, csv ones are always text
+#[derive(Deserialize)]
+struct RateRecord<R = String> {
+    date: String,
+    #[serde(alias = "from")]
+    base: String,
+    #[serde(alias = "to")]
+    quote: String,
+    rate: R,
+}
+
+impl RateRecord<Value> {
+    // json numbers keep the digits written in the file, serde_json's
+    // arbitrary_precision stops them being read as floats first
+    fn into_text(self) -> Result<RateRecord, String> {
+        let rate = match self.rate {
+            Value::String(s) => s,
+            Value::Number(n) => n.to_string(),
+            other => return Err(format!("invalid rate: {}", other)),
+        };
+
+        Ok(RateRecord {
+            date: self.date,
+            base: self.base,
+            quote: self.quote,
+            rate,
+        })
+    }
+}
+
+// reads a json array of rates or a csv file with date, base, quote and rate
+// columns. files named .json or starting with [ are read as json
+pub fn parse_rates(filename: Option<&str>, data: &str) -> Result<Vec<FxRate>, FxError> {
+    let is_json = filename.is_some_and(|f| f.to_ascii_lowercase().ends_with(".json"))
+        || data.trim_start().starts_with('[');
+
+    let records: Vec<RateRecord> = if is_json {
+        let records: Vec<RateRecord<Value>> =
+            serde_json::from_str(data).map_err(|e| FxError::InvalidError(e.to_string()))?;
+
+        records
+            .into_iter()
+            .enumerate()
+            .map(|(i, r)| {
+                r.into_text()
+                    .map_err(|e| FxError::InvalidError(format!("row {}: {}", i + 1, e)))
+            })
+            .collect::<Result<_, _>>()?
+    } else {
+        let mut reader = csv::ReaderBuilder::new()
+            .trim(csv::Trim::All)
+            .from_reader(data.as_bytes());
+        let headers = reader
+            .headers()
+            .map_err(|e| FxError::InvalidError(e.to_string()))?
+            .iter()
+            .map(|h| h.to_ascii_lowercase())
+            .collect::<csv::StringRecord>();
+        reader.set_headers(headers);
+
+        reader
+            .deserialize()
+            .enumerate()
+            .map(|(i, r)| r.map_err(|e| FxError::InvalidError(format!("row {}: {}", i + 1, e))))
+            .collect::<Result<_, _>>()?
+    };
+
+    if records.is_empty() {
+        return Err(FxError::InvalidError(
+            "The rate file has no rates".to_string(),
+        ));
+    }
+
+    records
+        .into_iter()
+        .enumerate()
+        .map(|(i, r)| rate(r).map_err(|e| FxError::InvalidError(format!("row {}: {}", i + 1, e))))
+        .collect()
+}
+
+fn rate(record: RateRecord) -> Result<FxRate, String> {
+    let date = NaiveDate::parse_from_str(record.date.trim(), "%Y-%m-%d")
+        .map_err(|e| format!("invalid date {:?}: {}", record.date, e))?;
+
+    let base = record.base.trim().to_ascii_uppercase();
+    let quote = record.quote.trim().to_ascii_uppercase();
+    for code in [&base, &quote] {
+        if !is_currency(code) {
+            return Err(format!("invalid currency {:?}", code));
+        }
+    }
+    if base == quote {
+        return Err(format!("{} cannot have a rate to itself", base));
+    }
+
+    let rate = Decimal::from_str(record.rate.trim())
+        .map_err(|e| format!("invalid rate {:?}: {}", record.rate, e))?;
+    if rate <= Decimal::ZERO {
+        return Err(format!("rate must be above zero, not {}", rate));
+    }
+
+    Ok(FxRate {
+        date,
+        base,
+        quote,
+        rate: rate.normalize(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        database::memory::Memory,
+        models::balance::{BalancePoint, Interval},
+    };
+
+    fn dec(value: &str) -> Decimal {
+        Decimal::from_str(value).unwrap()
+    }
+
+    fn day(d: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(2024, 3, d).unwrap()
+    }
+
+    fn fx(d: u32, base: &str, quote: &str, rate: &str) -> FxRate {
+        FxRate {
+            date: day(d),
+            base: base.to_string(),
+            quote: quote.to_string(),
+            rate: dec(rate),
+        }
+    }
+
+    fn gbp(amount: &str) -> Money {
+        Money::new(dec(amount), "GBP")
+    }
+
+    fn parsed(filename: Option<&str>, data: &str) -> Vec<FxRate> {
+        parse_rates(filename, data).unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    #[test]
+    fn csv_and_json_rate_files_read_the_same() {
+        let csv = "Date,From,To,Rate\n2024-03-01, eur ,GBP,0.8500\n2024-03-04,USD,GBP,0.79\n";
+        let json = r#"[
+            {"date": "2024-03-01", "base": "EUR", "quote": "gbp", "rate": 0.85},
+            {"date": "2024-03-04", "from": "USD", "to": "GBP", "rate": "0.79"}
+        ]"#;
+
+        let expected = vec![fx(1, "EUR", "GBP", "0.85"), fx(4, "USD", "GBP", "0.79")];
+        assert_eq!(parsed(None, csv), expected);
+        assert_eq!(parsed(Some("rates.csv"), csv), expected);
+        assert_eq!(parsed(None, json), expected);
+        assert_eq!(parsed(Some("RATES.JSON"), &format!("\n{}", json)), expected);
+    }
+
+    #[test]
+    fn json_number_rates_are_read_exactly() {
+        // neither is exact as a float, the second has more digits than one holds
+        let json = r#"[
+            {"date": "2024-03-01", "base": "EUR", "quote": "GBP", "rate": 1.1},
+            {"date": "2024-03-01", "base": "USD", "quote": "GBP", "rate": 0.790123456789012345678}
+        ]"#;
+        let csv = "date,base,quote,rate\n2024-03-01,EUR,GBP,1.1\n\
+                   2024-03-01,USD,GBP,0.790123456789012345678\n";
+
+        let expected = vec![
+            fx(1, "EUR", "GBP", "1.1"),
+            fx(1, "USD", "GBP", "0.790123456789012345678"),
+        ];
+        assert_eq!(parsed(None, json), expected);
+        assert_eq!(parsed(None, csv), expected);
+        assert_eq!(parsed(None, json)[0].rate.to_string(), "1.1");
+    }
+
+    #[test]
+    fn invalid_rates_are_refused_with_their_row() {
+        for (data, message) in [
+            ("date,base,quote,rate\n", "no rates"),
+            (
+                "date,base,quote,rate\n2024-03-01,EUR,GBP,0.85\n01/03/2024,EUR,GBP,0.85\n",
+                "row 2: invalid date",
+            ),
+            (
+                "date,base,quote,rate\n2024-03-01,EUR,EUR,1\n",
+                "row 1: EUR cannot have a rate to itself",
+            ),
+            (
+                "date,base,quote,rate\n2024-03-01,EURO,GBP,0.85\n",
+                "row 1: invalid currency",
+            ),
+            (
+                "date,base,quote,rate\n2024-03-01,EUR,GBP,0\n",
+                "row 1: rate must be above zero",
+            ),
+            ("[]", "no rates"),
+            (
+                r#"[{"date": "2024-03-01", "base": "EUR", "quote": "GBP", "rate": true}]"#,
+                "row 1: invalid rate",
+            ),
+        ] {
+            match parse_rates(None, data) {
+                Err(FxError::InvalidError(e)) => assert!(e.contains(message), "{}: {}", message, e),
+                Err(e) => panic!("{}", e),
+                Ok(rates) => panic!("{:?} was read from {:?}", rates, data),
+            }
+        }
+    }
+
+    #[test]
+    fn the_latest_rate_on_or_before_the_day_is_used() {
+        let rates = Rates::new(
+            "GBP",
+            vec![fx(4, "EUR", "GBP", "0.86"), fx(1, "EUR", "GBP", "0.85")],
+        );
+        let amount = Money::new(dec("10.00"), "EUR");
+
+        let on_the_day = rates.convert(&amount, day(4)).expect("converted");
+        assert_eq!(on_the_day.rate, dec("0.86"));
+        assert_eq!(on_the_day.rate_date, day(4));
+
+        let between = rates.convert(&amount, day(3)).expect("converted");
+        assert_eq!(between.rate, dec("0.85"));
+        assert_eq!(between.rate_date, day(1));
+        assert_eq!(between.amount, gbp("8.50"));
+    }
+
+    #[test]
+    fn converted_amounts_are_rounded_to_pence() {
+        let rates = Rates::new("GBP", vec![fx(1, "EUR", "GBP", "0.8567")]);
+
+        let converted = rates
+            .convert(&Money::new(dec("12.34"), "EUR"), day(1))
+            .expect("converted");
+        // 12.34 * 0.8567 = 10.571678
+        assert_eq!(converted.amount, gbp("10.57"));
+        assert_eq!(converted.rate, dec("0.8567"));
+    }
+
+    #[test]
+    fn an_inverse_rate_is_rounded_to_ten_places() {
+        let rates = Rates::new("EUR", vec![fx(1, "EUR", "GBP", "0.85")]);
+
+        let converted = rates.convert(&gbp("100"), day(2)).expect("converted");
+        // 1 / 0.85 = 1.176470588235...
+        assert_eq!(converted.rate, dec("1.1764705882"));
+        assert_eq!(converted.amount, Money::new(dec("117.65"), "EUR"));
+    }
+
+    #[test]
+    fn a_rate_saved_the_right_way_round_wins_over_an_inverse() {
+        let rates = Rates::new(
+            "EUR",
+            vec![fx(1, "EUR", "GBP", "0.85"), fx(1, "GBP", "EUR", "1.18")],
+        );
+
+        let converted = rates.convert(&gbp("100"), day(1)).expect("converted");
+        assert_eq!(converted.rate, dec("1.18"));
+    }
+
+    #[test]
+    fn there_is_no_conversion_without_a_rate() {
+        let rates = Rates::new("GBP", vec![fx(2, "EUR", "GBP", "0.85")]);
+
+        // before the first rate, and a currency with no rates at all
+        assert!(rates
+            .convert(&Money::new(dec("10"), "EUR"), day(1))
+            .is_none());
+        assert!(rates
+            .convert(&Money::new(dec("10"), "USD"), day(2))
+            .is_none());
+    }
+
+    #[test]
+    fn the_same_currency_converts_at_one() {
+        let rates = Rates::new("GBP", Vec::new());
+
+        let converted = rates.convert(&gbp("-12.345"), day(5)).expect("converted");
+        assert_eq!(converted.rate, Decimal::ONE);
+        assert_eq!(converted.rate_date, day(5));
+        // halves round to even
+        assert_eq!(converted.amount, gbp("-12.34"));
+    }
+
+    // a service with euro to pound rates saved on the 1st and 4th
+    async fn service() -> (FxService<Memory>, Memory) {
+        let memory = Memory::default();
+        let fs = FxService::new(memory.clone());
+        fs.import_rates(
+            None,
+            "date,base,quote,rate\n2024-03-01,EUR,GBP,0.85\n2024-03-04,EUR,GBP,0.86\n",
+        )
+        .await
+        .unwrap_or_else(|e| panic!("{}", e));
+
+        (fs, memory)
+    }
+
+    #[tokio::test]
+    async fn balances_are_converted_at_the_rate_of_their_day() {
+        let (fs, _) = service().await;
+        let series = BalanceSeries {
+            account_id: uuid::Uuid::new_v4(),
+            currency: "EUR".to_string(),
+            interval: Interval::Day,
+            balances: [(2, "100"), (4, "200")]
+                .into_iter()
+                .map(|(d, balance)| BalancePoint {
+                    date: day(d),
+                    balance: dec(balance),
+                })
+                .collect(),
+        };
+
+        let converted = fs
+            .convert_balances(series, "gbp")
+            .await
+            .unwrap_or_else(|e| panic!("{}", e));
+
+        assert_eq!(converted.currency, "EUR");
+        let amounts: Vec<_> = converted
+            .balances
+            .iter()
+            .map(|b| {
+                (
+                    b.balance.balance,
+                    b.converted.as_ref().map(|c| c.amount.clone()),
+                )
+            })
+            .collect();
+        assert_eq!(
+            amounts,
+            [
+                (dec("100"), Some(gbp("85.00"))),
+                (dec("200"), Some(gbp("172.00")))
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn transaction_balances_are_converted_with_their_amounts() {
+        let (fs, memory) = service().await;
+        let account = memory.add_account("Euro card", "EUR", Decimal::ZERO);
+        memory.add_transaction(account.id, day(2), Money::new(dec("-10"), "EUR"));
+        memory.add_transaction(account.id, day(3), gbp("-5"));
+        let transactions = memory.tables().transactions.clone();
+        let balances = transactions
+            .into_iter()
+            .zip([Some(dec("-10")), None])
+            .map(|(transaction, balance)| TransactionBalance {
+                transaction,
+                balance,
+            })
+            .collect();
+
+        let converted = fs
+            .convert_transaction_balances(balances, "GBP")
+            .await
+            .unwrap_or_else(|e| panic!("{}", e));
+
+        let amount = |c: &Option<Conversion>| c.as_ref().map(|c| c.amount.clone());
+        assert_eq!(
+            amount(&converted[0].transaction.converted),
+            Some(gbp("-8.50"))
+        );
+        assert_eq!(amount(&converted[0].converted_balance), Some(gbp("-8.50")));
+        // a transaction in another currency than the account's has no balance
+        assert_eq!(
+            amount(&converted[1].transaction.converted),
+            Some(gbp("-5.00"))
+        );
+        assert!(converted[1].converted_balance.is_none());
+    }
+
+    #[tokio::test]
+    async fn an_invalid_currency_is_refused() {
+        let (fs, _) = service().await;
+
+        match fs.convert(Vec::new(), "pounds").await {
+            Err(FxError::InvalidError(_)) => (),
+            Err(e) => panic!("{}", e),
+            Ok(_) => panic!("pounds was accepted"),
+        }
+    }
+}
@@ -0,0 +1,189 @@
+use core::fmt;
+use std::{fmt::Display, sync::Arc};
+use tokio::sync::RwLock;
+
+use crate::{
+    database::base::{DatabaseError, DatabaseInit},
+    models::job::{ImportJob, ImportJobFile},
+    service::parse::report::Counts,
+};
+
+#[allow(clippy::enum_variant_names)]
+pub enum JobError {
+    SaveError(String),
+    FindError(String),
+    UpdateError(String),
+}
+
+impl Display for JobError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            JobError::SaveError(e) => write!(f, "JobError -> SaveError, {}", e),
+            JobError::FindError(e) => write!(f, "JobError -> FindError, {}", e),
+            JobError::UpdateError(e) => write!(f, "JobError -> UpdateError, {}", e),
+        }
+    }
+}
+
+pub trait JobWrite {
+    type Upload: JobUpload;
+
+    // starts saving the files of a new job, it is queued once they are all saved
+    async fn begin_job(&self) -> Result<Self::Upload, DatabaseError>;
+
+    // marks the oldest queued job running and returns it, None when no job is
+    // waiting. a job is only ever claimed by one worker
+    async fn claim_job(&self) -> Result<Option<ImportJob>, DatabaseError>;
+
+    async fn update_job_progress(&self, id: &str, counts: &Counts) -> Result<(), DatabaseError>;
+
+    // the job's files are deleted once it has finished or failed
+    async fn finish_job(
+        &self,
+        id: &str,
+        counts: &Counts,
+        summary: serde_json::Value,
+    ) -> Result<(), DatabaseError>;
+
+    async fn fail_job(&self, id: &str, error: &str) -> Result<(), DatabaseError>;
+
+    // queues the jobs left running when the server stopped, nothing they
+    // imported was committed so they start again from the beginning
+    async fn requeue_jobs(&self) -> Result<u64, DatabaseError>;
+}
+
+// the uploaded files of a job being saved, the job is not visible until the
+// upload is committed
+pub trait JobUpload {
+    async fn add_file(&mut self, file: &ImportJobFile) -> Result<(), DatabaseError>;
+
+    async fn add_chunk(
+        &mut self,
+        position: i32,
+        sequence: i32,
+        data: &[u8],
+    ) -> Result<(), DatabaseError>;
+
+    async fn commit(self) -> Result<ImportJob, DatabaseError>;
+}
+
+pub trait JobRead {
+    async fn get_job(&self, id: &str) -> Result<Option<ImportJob>, DatabaseError>;
+    async fn get_job_files(&self, id: &str) -> Result<Vec<ImportJobFile>, DatabaseError>;
+
+    // None once the file has no more chunks
+    async fn get_job_chunk(
+        &self,
+        id: &str,
+        position: i32,
+        sequence: i32,
+    ) -> Result<Option<Vec<u8>>, DatabaseError>;
+}
+
+pub struct JobService<T>
+where
+    T: DatabaseInit + JobWrite + JobRead,
+{
+    db: Arc<RwLock<T>>,
+}
+
+impl<T> JobService<T>
+where
+    T: DatabaseInit + JobWrite + JobRead,
+{
+    pub fn new(db: T) -> JobService<T> {
+        let db = Arc::new(RwLock::new(db));
+        Self { db }
+    }
+
+    pub async fn begin_job(&self) -> Result<T::Upload, JobError> {
+        let db_connection = self.db.read().await;
+
+        db_connection
+            .begin_job()
+            .await
+            .map_err(|e| JobError::SaveError(e.to_string()))
+    }
+
+    pub async fn claim_job(&self) -> Result<Option<ImportJob>, JobError> {
+        let db_connection = self.db.read().await;
+
+        db_connection
+            .claim_job()
+            .await
+            .map_err(|e| JobError::UpdateError(e.to_string()))
+    }
+
+    pub async fn update_job_progress(&self, id: &str, counts: &Counts) -> Result<(), JobError> {
+        let db_connection = self.db.read().await;
+
+        db_connection
+            .update_job_progress(id, counts)
+            .await
+            .map_err(|e| JobError::UpdateError(e.to_string()))
+    }
+
+    pub async fn finish_job(
+        &self,
+        id: &str,
+        counts: &Counts,
+        summary: serde_json::Value,
+    ) -> Result<(), JobError> {
+        let db_connection = self.db.read().await;
+
+        db_connection
+            .finish_job(id, counts, summary)
+            .await
+            .map_err(|e| JobError::UpdateError(e.to_string()))
+    }
+
+    pub async fn fail_job(&self, id: &str, error: &str) -> Result<(), JobError> {
+        let db_connection = self.db.read().await;
+
+        db_connection
+            .fail_job(id, error)
+            .await
+            .map_err(|e| JobError::UpdateError(e.to_string()))
+    }
+
+    pub async fn requeue_jobs(&self) -> Result<u64, JobError> {
+        let db_connection = self.db.read().await;
+
+        db_connection
+            .requeue_jobs()
+            .await
+            .map_err(|e| JobError::UpdateError(e.to_string()))
+    }
+
+    pub async fn find_job(&self, id: &str) -> Result<Option<ImportJob>, JobError> {
+        let db_connection = self.db.read().await;
+
+        db_connection
+            .get_job(id)
+            .await
+            .map_err(|e| JobError::FindError(e.to_string()))
+    }
+
+    pub async fn find_job_files(&self, id: &str) -> Result<Vec<ImportJobFile>, JobError> {
+        let db_connection = self.db.read().await;
+
+        db_connection
+            .get_job_files(id)
+            .await
+            .map_err(|e| JobError::FindError(e.to_string()))
+    }
+
+    pub async fn find_job_chunk(
+        &self,
+        id: &str,
+        position: i32,
+        sequence: i32,
+    ) -> Result<Option<Vec<u8>>, JobError> {
+        let db_connection = self.db.read().await;
+
+        db_connection
+            .get_job_chunk(id, position, sequence)
+            .await
+            .map_err(|e| JobError::FindError(e.to_string()))
+    }
+}
@@ -1,32 +1,84 @@
 use std::{
+    collections::{HashMap, HashSet},
     fmt::{self, Display},
     sync::Arc,
 };
 
-use chrono::{NaiveDate, NaiveDateTime};
-use tokio::sync::RwLock;
+use tokio::sync::{mpsc, RwLock};
 
-use crate::{database::postgres::Postgres, models::transaction::CreateTransaction};
+use chrono::{Duration, NaiveDateTime, Utc};
+use sha2::{Digest, Sha256};
+use uuid::Uuid;
 
-use super::transaction::TransactionService;
+use crate::{
+    database::{
+        base::DatabaseError,
+        postgres::{Postgres, PostgresImport},
+    },
+    models::{batch::CreateImportBatch, transaction::CreateTransaction},
+    service::account::AccountService,
+};
+
+use self::{
+    fingerprint::{link, Fingerprints},
+    report::{Counts, Event, Policy, Preview, Report, Summary},
+};
+
+use super::{
+    batch::{BatchService, ImportSession},
+    transaction::TransactionService,
+};
+
+mod fingerprint;
+pub mod report;
+mod stream;
+
+pub use import::service::parse_service::{detect, AmountMode, Config, Format};
+
+// how long a preview can be committed for
+const PREVIEW_MINUTES: i64 = 30;
+
+// previews held at once, and transactions across all of them. the oldest are
+// dropped to make room for new ones
+const MAX_PREVIEWS: usize = 64;
+const MAX_PREVIEW_TRANSACTIONS: usize = 200_000;
+
+// csv rows parsed and saved at a time when streaming an upload
+const CHUNK_ROWS: usize = 1000;
+
+// parsed chunks waiting to be saved before the parser waits for the database
+const PARSED_CHUNKS: usize = 4;
+
+// how much of a streamed upload is read to detect its format and profile
+const SNIFF_BYTES: usize = 64 * 1024;
 
 pub struct Service {
+    account_service: Arc<RwLock<AccountService<Postgres>>>,
+    batch_service: Arc<RwLock<BatchService<Postgres>>>,
     transaction_service: Arc<RwLock<TransactionService<Postgres>>>,
+    // held in memory by the server that made them, so they are lost when it
+    // restarts and have to be made again
+    previews: RwLock<HashMap<String, (NaiveDateTime, Vec<Import>)>>,
+    parser: import::service::parse_service::Service,
 }
 
-// column position of the given columns
-pub struct Config {
-    pub name: String,
-    pub description_position: usize,
-    pub date_position: usize,
-    pub amount_position: usize,
+// a parsed statement file waiting to be saved
+#[derive(Clone)]
+pub struct Import {
+    pub report: Report,
+    pub transactions: Vec<CreateTransaction>,
 }
 
+// the parsing errors of the import crate, and saving the transactions read
+#[allow(clippy::enum_variant_names)]
 pub enum ParseError {
     RecordError(String),
     AmountConversionError(String),
     DateConversionError(String),
     SaveError(String),
+    ConfigError(String),
+    DetectionError(String),
+    LimitError(String),
 }
 
 impl Display for ParseError {
@@ -36,68 +88,742 @@ impl Display for ParseError {
             ParseError::AmountConversionError(e) => write!(f, "AmountConversionError: {}", e),
             ParseError::SaveError(e) => write!(f, "SaveError: {}", e),
             ParseError::DateConversionError(e) => write!(f, "DateConversionError: {}", e),
+            ParseError::ConfigError(e) => write!(f, "ConfigError: {}", e),
+            ParseError::DetectionError(e) => write!(f, "DetectionError: {}", e),
+            ParseError::LimitError(e) => write!(f, "LimitError: {}", e),
+        }
+    }
+}
+
+impl From<import::service::parse_service::ParseError> for ParseError {
+    fn from(e: import::service::parse_service::ParseError) -> Self {
+        use import::service::parse_service::ParseError as Import;
+
+        match e {
+            Import::RecordError(e) => ParseError::RecordError(e),
+            Import::AmountConversionError(e) => ParseError::AmountConversionError(e),
+            Import::DateConversionError(e) => ParseError::DateConversionError(e),
+            Import::ConfigError(e) => ParseError::ConfigError(e),
+            Import::DetectionError(e) => ParseError::DetectionError(e),
         }
     }
 }
 
 impl Service {
-    pub fn new(transaction_service: Arc<RwLock<TransactionService<Postgres>>>) -> Self {
+    pub fn new(
+        account_service: Arc<RwLock<AccountService<Postgres>>>,
+        batch_service: Arc<RwLock<BatchService<Postgres>>>,
+        transaction_service: Arc<RwLock<TransactionService<Postgres>>>,
+    ) -> Self {
         Self {
+            account_service,
+            batch_service,
             transaction_service,
+            previews: RwLock::new(HashMap::new()),
+            parser: import::service::parse_service::Service::new(),
         }
     }
 
-    pub async fn parse_data(
+    // csv files need a profile to find their columns, other formats only use
+    // the profile name as the account the transactions belong to. rows that
+    // cannot be read are reported, the policy decides whether the rest of the
+    // file is still imported. without an account the transactions go into the
+    // account named after the statement's when they are saved
+    pub fn parse_data(
         &self,
-        extraction_config: Config,
+        format: Format,
+        extraction_config: Option<&Config>,
         data: String,
-    ) -> Result<(), ParseError> {
-        let mut csv_reader = csv::Reader::from_reader(data.as_bytes());
-        let transaction_service = self.transaction_service.write().await;
+        policy: Policy,
+        account: Option<Uuid>,
+    ) -> Result<Import, ParseError> {
+        let rows = self.parser.parse_data(format, extraction_config, &data)?;
 
-        for (index, record) in csv_reader.records().enumerate() {
-            if index == 0 {
-                continue;
+        let mut report = Report {
+            format: format.to_string(),
+            checksum: format!("{:x}", Sha256::digest(data.as_bytes())),
+            profile: extraction_config.map(|c| c.name.clone()),
+            ..Report::default()
+        };
+
+        let mut transactions = Vec::new();
+        for row in rows {
+            match row {
+                Ok(transaction) => transactions.push(transaction.into()),
+                Err(e) => report.add_error(e),
             }
+        }
 
-            let r = record.map_err(|e| ParseError::RecordError(e.to_string()))?;
+        if policy == Policy::RejectFile && report.failed > 0 {
+            report.skipped = transactions.len();
+            transactions.clear();
+        }
 
-            let date = string_or_empty(r.get(extraction_config.date_position));
-            let amount = string_or_empty(r.get(extraction_config.amount_position));
-            let description = string_or_empty(r.get(extraction_config.description_position));
+        Fingerprints::default().assign(&mut transactions);
+        if let Some(account) = account {
+            transactions.iter_mut().for_each(|t| link(t, account));
+        }
 
-            let amount = amount
-                .parse::<f64>()
-                .map_err(|e| ParseError::AmountConversionError(e.to_string()))?;
+        let mut warnings = Warnings::default();
+        warnings.add(&transactions);
+        report.warnings = warnings.messages();
 
-            let pd = NaiveDate::parse_from_str(date, "%d/%m/%Y")
-                .map_err(|e| ParseError::DateConversionError(e.to_string()))?;
+        Ok(Import {
+            report,
+            transactions,
+        })
+    }
+
+    // starts an upload, every file of it is saved in the returned session as
+    // an import batch and nothing is kept unless the session is committed
+    pub async fn begin_import(&self) -> Result<PostgresImport, ParseError> {
+        self.batch_service
+            .read()
+            .await
+            .begin_import()
+            .await
+            .map_err(|e| ParseError::SaveError(e.to_string()))
+    }
+
+    pub async fn save(&self, imports: Vec<Import>) -> Result<Summary, ParseError> {
+        save(self.begin_import().await?, imports).await
+    }
+
+    // imports a file as it is uploaded. csv files are parsed a chunk of rows at
+    // a time and each chunk is saved before the next is read, so an export of
+    // any size is never held in memory. the other formats are small enough to
+    // be read whole before they are parsed. the format and, without a profile,
+    // the profile are detected from the start of the file. the events of each
+    // chunk are sent as it is saved
+    #[allow(clippy::too_many_arguments)]
+    pub async fn import_stream(
+        &self,
+        session: &mut PostgresImport,
+        file: Option<String>,
+        account: Option<Uuid>,
+        extraction_config: Option<&Config>,
+        profiles: &[Config],
+        policy: Policy,
+        mut input: mpsc::Receiver<Vec<u8>>,
+        events: &mut (dyn FnMut(Event) + Send),
+    ) -> Result<Report, ParseError> {
+        let mut head = Vec::new();
+        let mut finished = false;
+        while head.len() < SNIFF_BYTES {
+            match input.recv().await {
+                Some(chunk) => head.extend(chunk),
+                None => {
+                    finished = true;
+                    break;
+                }
+            }
+        }
+
+        // a partly received last line would look like a bad row
+        let sample = match finished {
+            true => String::from_utf8_lossy(&head),
+            false => {
+                let end = head.iter().rposition(|b| *b == b'\n').unwrap_or(head.len());
+                String::from_utf8_lossy(&head[..end])
+            }
+        };
+
+        let (format, config) = match extraction_config {
+            Some(c) => (detect::format(file.as_deref(), &sample), Some(c.clone())),
+            None => {
+                let detected = detect::statement(file.as_deref(), &sample, profiles)?;
+                (detected.format, detected.profile)
+            }
+        };
+
+        if format != Format::Csv {
+            while let Some(chunk) = input.recv().await {
+                head.extend(chunk);
+            }
+
+            let data = String::from_utf8(head)
+                .map_err(|e| ParseError::RecordError(format!("File is not valid UTF-8: {}", e)))?;
+
+            let mut import = self.parse_data(format, config.as_ref(), data, policy, account)?;
+            import.report.file = file;
+
+            let report = write(session, import).await?;
+            if !report.errors.is_empty() {
+                events(Event::Errors {
+                    file: report.file.clone(),
+                    errors: report.errors.clone(),
+                });
+            }
+            if !report.duplicate_transactions.is_empty() {
+                events(Event::Duplicates {
+                    file: report.file.clone(),
+                    transactions: report.duplicate_transactions.clone(),
+                });
+            }
+            events(Event::Progress {
+                file: report.file.clone(),
+                counts: Counts::from(&report),
+            });
+
+            return Ok(report);
+        }
 
-            let empty_time = match chrono::NaiveTime::from_hms_opt(0, 0, 0) {
-                Some(time) => time,
-                None => return Err(ParseError::DateConversionError("Invalid time".to_string())),
-            };
+        let config = config.ok_or_else(|| {
+            ParseError::ConfigError("A profile is required to parse csv data".to_string())
+        })?;
 
-            let new_transaction = CreateTransaction {
-                account_type: extraction_config.name.clone(),
-                amount,
-                payment_date: NaiveDateTime::new(pd, empty_time),
-                description: description.to_string(),
-            };
+        let mut report = Report {
+            file,
+            format: format.to_string(),
+            profile: Some(config.name.clone()),
+            ..Report::default()
+        };
+        let batch_id = session
+            .create_batch(&batch(&report))
+            .await
+            .map_err(save_error)?;
 
-            transaction_service
-                .create_transaction(new_transaction)
+        let (output, mut chunks) = mpsc::channel(PARSED_CHUNKS);
+        let parser = tokio::task::spawn_blocking(move || {
+            stream::csv(&config, head, input, output, CHUNK_ROWS)
+        });
+
+        let mut fingerprints = Fingerprints::default();
+        let mut warnings = Warnings::default();
+        let mut rejected = false;
+
+        while let Some(rows) = chunks.recv().await {
+            let mut transactions = Vec::new();
+            let mut errors = Vec::new();
+            for row in rows {
+                match row {
+                    Ok(transaction) => transactions.push(transaction.into()),
+                    Err(e) => errors.push(e),
+                }
+            }
+
+            if !errors.is_empty() {
+                events(Event::Errors {
+                    file: report.file.clone(),
+                    errors: errors.clone(),
+                });
+                errors.into_iter().for_each(|e| report.add_error(e));
+            }
+
+            // the rows saved so far are taken back out of the session
+            if policy == Policy::RejectFile && report.failed > 0 && !rejected {
+                session
+                    .delete_transactions(batch_id)
+                    .await
+                    .map_err(save_error)?;
+
+                report.skipped += report.imported + report.duplicates;
+                report.imported = 0;
+                report.duplicates = 0;
+                report.duplicate_transactions.clear();
+                warnings = Warnings::default();
+                rejected = true;
+            }
+
+            if rejected {
+                report.skipped += transactions.len();
+            } else {
+                fingerprints.assign(&mut transactions);
+                if let Some(account) = account {
+                    transactions.iter_mut().for_each(|t| link(t, account));
+                }
+                warnings.add(&transactions);
+                let duplicates = write_chunk(session, batch_id, &mut report, transactions).await?;
+
+                if !duplicates.is_empty() {
+                    events(Event::Duplicates {
+                        file: report.file.clone(),
+                        transactions: duplicates.clone(),
+                    });
+                    report.add_duplicates(duplicates);
+                }
+            }
+
+            events(Event::Progress {
+                file: report.file.clone(),
+                counts: Counts::from(&report),
+            });
+        }
+
+        report.checksum = parser
+            .await
+            .map_err(|e| ParseError::RecordError(e.to_string()))??;
+        report.warnings = warnings.messages();
+
+        session
+            .update_batch(batch_id, &batch(&report))
+            .await
+            .map_err(save_error)?;
+        report.batch_id = Some(batch_id.to_string());
+
+        Ok(report)
+    }
+
+    // runs the duplicate checks of save without writing anything, the imports
+    // are kept so the preview can be committed with its token
+    pub async fn preview(&self, mut imports: Vec<Import>) -> Result<Preview, ParseError> {
+        self.remove_duplicates(&mut imports).await?;
+
+        let size: usize = imports.iter().map(|i| i.transactions.len()).sum();
+        if size > MAX_PREVIEW_TRANSACTIONS {
+            return Err(ParseError::LimitError(format!(
+                "A preview can hold up to {} transactions, this upload has {}",
+                MAX_PREVIEW_TRANSACTIONS, size
+            )));
+        }
+
+        let mut summary = Summary::default();
+        for import in &imports {
+            let mut report = import.report.clone();
+            report.imported = import.transactions.len();
+            report.transactions = import.transactions.clone();
+            summary.add(report);
+        }
+
+        let now = Utc::now().naive_utc();
+        let expires_at = now + Duration::minutes(PREVIEW_MINUTES);
+        let token = Uuid::new_v4().to_string();
+
+        let mut previews = self.previews.write().await;
+        make_room(&mut previews, now, size);
+        previews.insert(token.clone(), (expires_at, imports));
+
+        Ok(Preview {
+            token,
+            expires_at,
+            summary,
+        })
+    }
+
+    // saves a preview, rows imported since it was made are reported as
+    // duplicates. returns None when the token is unknown or has expired
+    pub async fn commit_preview(&self, token: &str) -> Result<Option<Summary>, ParseError> {
+        let preview = self.previews.write().await.remove(token);
+
+        match preview {
+            Some((expires_at, imports)) if expires_at > Utc::now().naive_utc() => {
+                Ok(Some(self.save(imports).await?))
+            }
+            _ => Ok(None),
+        }
+    }
+
+    // returns false when the token is unknown
+    pub async fn discard_preview(&self, token: &str) -> bool {
+        self.previews.write().await.remove(token).is_some()
+    }
+
+    // moves transactions imported by an earlier upload, or by an earlier file
+    // of this upload, out of the imports and into their reports. transactions
+    // without an account are linked to the account named after the
+    // statement's when there is one, otherwise it is created when they are
+    // saved and none of them can be duplicates
+    async fn remove_duplicates(&self, imports: &mut [Import]) -> Result<(), ParseError> {
+        let mut names: Vec<String> = imports
+            .iter()
+            .flat_map(|i| i.transactions.iter())
+            .filter(|t| t.account_id.is_none())
+            .map(|t| t.account_type.clone())
+            .collect();
+        names.sort();
+        names.dedup();
+
+        if !names.is_empty() {
+            let accounts: HashMap<String, Uuid> = self
+                .account_service
+                .read()
                 .await
-                .map_err(|e| ParseError::SaveError(e.to_string()))?;
+                .find_accounts_named(&names)
+                .await
+                .map_err(|e| ParseError::SaveError(e.to_string()))?
+                .into_iter()
+                .map(|a| (a.name, a.id))
+                .collect();
+
+            for t in imports.iter_mut().flat_map(|i| i.transactions.iter_mut()) {
+                if t.account_id.is_none() {
+                    if let Some(id) = accounts.get(&t.account_type) {
+                        link(t, *id);
+                    }
+                }
+            }
+        }
+
+        let fingerprints: Vec<String> = imports
+            .iter()
+            .flat_map(|i| i.transactions.iter())
+            .filter_map(|t| t.fingerprint.clone())
+            .collect();
+
+        let mut seen: HashSet<String> = self
+            .transaction_service
+            .read()
+            .await
+            .find_known_fingerprints(&fingerprints)
+            .await
+            .map_err(|e| ParseError::SaveError(e.to_string()))?
+            .into_iter()
+            .collect();
+
+        for import in imports.iter_mut() {
+            let (duplicates, transactions) = std::mem::take(&mut import.transactions)
+                .into_iter()
+                .partition(|t| match &t.fingerprint {
+                    Some(f) => !seen.insert(f.clone()),
+                    None => false,
+                });
+
+            import.transactions = transactions;
+            import.report.add_duplicates(duplicates);
         }
 
         Ok(())
     }
 }
 
-fn string_or_empty(s: Option<&str>) -> &str {
-    match s {
-        Some(value) => value,
-        None => "",
+// every file of an upload is saved together, so a failure leaves none of
+// their transactions behind
+async fn save<S: ImportSession>(
+    mut session: S,
+    imports: Vec<Import>,
+) -> Result<Summary, ParseError> {
+    let mut summary = Summary::default();
+    for import in imports {
+        summary.add(write(&mut session, import).await?);
+    }
+
+    session.commit().await.map_err(save_error)?;
+
+    Ok(summary)
+}
+
+// saves a parsed file as a batch of the session's upload
+async fn write<S: ImportSession>(session: &mut S, import: Import) -> Result<Report, ParseError> {
+    let mut report = import.report;
+    let batch_id = session
+        .create_batch(&batch(&report))
+        .await
+        .map_err(save_error)?;
+
+    let duplicates = write_chunk(session, batch_id, &mut report, import.transactions).await?;
+    report.add_duplicates(duplicates);
+
+    session
+        .update_batch(batch_id, &batch(&report))
+        .await
+        .map_err(save_error)?;
+    report.batch_id = Some(batch_id.to_string());
+
+    Ok(report)
+}
+
+// saves the rows of a file that were not imported before, by this or an
+// earlier upload, and returns the others
+async fn write_chunk<S: ImportSession>(
+    session: &mut S,
+    batch_id: Uuid,
+    report: &mut Report,
+    mut transactions: Vec<CreateTransaction>,
+) -> Result<Vec<CreateTransaction>, ParseError> {
+    link_statement_accounts(session, &mut transactions).await?;
+
+    let fingerprints: Vec<String> = transactions
+        .iter()
+        .filter_map(|t| t.fingerprint.clone())
+        .collect();
+
+    let mut seen: HashSet<String> = session
+        .get_known_fingerprints(&fingerprints)
+        .await
+        .map_err(save_error)?
+        .into_iter()
+        .collect();
+
+    let (duplicates, transactions): (Vec<_>, Vec<_>) =
+        transactions
+            .into_iter()
+            .partition(|t| match &t.fingerprint {
+                Some(f) => !seen.insert(f.clone()),
+                None => false,
+            });
+
+    session
+        .create_transactions(batch_id, &transactions)
+        .await
+        .map_err(save_error)?;
+
+    report.imported += transactions.len();
+
+    Ok(duplicates)
+}
+
+// links transactions imported without an account to the account named after
+// the statement's, creating it in the currency of their first transaction
+async fn link_statement_accounts<S: ImportSession>(
+    session: &mut S,
+    transactions: &mut [CreateTransaction],
+) -> Result<(), ParseError> {
+    let mut names = Vec::new();
+    let mut currencies = Vec::new();
+    for t in transactions.iter().filter(|t| t.account_id.is_none()) {
+        if !names.contains(&t.account_type) {
+            names.push(t.account_type.clone());
+            currencies.push(t.amount.currency.clone());
+        }
+    }
+
+    if names.is_empty() {
+        return Ok(());
+    }
+
+    let mut accounts = HashMap::new();
+    for account in session
+        .get_statement_accounts(&names, &currencies)
+        .await
+        .map_err(save_error)?
+    {
+        if account.closed {
+            return Err(ParseError::ConfigError(format!(
+                "Account {} is closed",
+                account.name
+            )));
+        }
+        accounts.insert(account.name, account.id);
+    }
+
+    for t in transactions.iter_mut().filter(|t| t.account_id.is_none()) {
+        let id = accounts.get(&t.account_type).ok_or_else(|| {
+            ParseError::SaveError(format!("Unable to find account {}", t.account_type))
+        })?;
+        link(t, *id);
+    }
+
+    Ok(())
+}
+
+// drops the expired previews, then the oldest until a preview of the given
+// number of transactions fits under the limits
+fn make_room(
+    previews: &mut HashMap<String, (NaiveDateTime, Vec<Import>)>,
+    now: NaiveDateTime,
+    size: usize,
+) {
+    previews.retain(|_, (expiry, _)| *expiry > now);
+
+    loop {
+        let held: usize = previews
+            .values()
+            .flat_map(|(_, imports)| imports)
+            .map(|i| i.transactions.len())
+            .sum();
+        if previews.len() < MAX_PREVIEWS && held + size <= MAX_PREVIEW_TRANSACTIONS {
+            return;
+        }
+
+        let oldest = previews
+            .iter()
+            .min_by_key(|(_, (expiry, _))| *expiry)
+            .map(|(token, _)| token.clone());
+        match oldest {
+            Some(token) => previews.remove(&token),
+            None => return,
+        };
+    }
+}
+
+fn batch(report: &Report) -> CreateImportBatch {
+    CreateImportBatch {
+        filename: report.file.clone(),
+        profile: report.profile.clone(),
+        format: report.format.clone(),
+        checksum: report.checksum.clone(),
+        imported_rows: report.imported as i32,
+        skipped_rows: report.skipped as i32,
+        failed_rows: report.failed as i32,
+        duplicate_rows: report.duplicates as i32,
+    }
+}
+
+fn save_error(e: DatabaseError) -> ParseError {
+    ParseError::SaveError(e.to_string())
+}
+
+// rows that were read but are worth a second look before they are imported,
+// counted a chunk at a time
+#[derive(Default)]
+struct Warnings {
+    future: usize,
+    zero: usize,
+}
+
+impl Warnings {
+    fn add(&mut self, transactions: &[CreateTransaction]) {
+        let now = Utc::now().naive_utc();
+
+        self.future += transactions.iter().filter(|t| t.payment_date > now).count();
+        self.zero += transactions.iter().filter(|t| t.amount.is_zero()).count();
+    }
+
+    fn messages(&self) -> Vec<String> {
+        let mut warnings = Vec::new();
+
+        if self.future > 0 {
+            warnings.push(format!(
+                "{} transactions are dated in the future",
+                self.future
+            ));
+        }
+
+        if self.zero > 0 {
+            warnings.push(format!("{} transactions have an amount of zero", self.zero));
+        }
+
+        warnings
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::NaiveDate;
+    use import::service::parse_service::Money;
+    use rust_decimal::Decimal;
+
+    use super::*;
+    use crate::{database::memory::Memory, service::batch::BatchWrite};
+
+    // a file of the named statement account's transactions, each paid on the
+    // given day of february
+    fn import(account: &str, days: &[u32]) -> Import {
+        let mut transactions: Vec<CreateTransaction> = days
+            .iter()
+            .map(|day| CreateTransaction {
+                account_id: None,
+                account_type: account.to_string(),
+                payment_date: NaiveDate::from_ymd_opt(2024, 2, *day)
+                    .and_then(|d| d.and_hms_opt(0, 0, 0))
+                    .unwrap(),
+                amount: Money::new(Decimal::ONE, "GBP"),
+                description: "Coffee".to_string(),
+                external_id: None,
+                category: None,
+                value_date: None,
+                fingerprint: None,
+            })
+            .collect();
+        Fingerprints::default().assign(&mut transactions);
+
+        Import {
+            report: Report {
+                format: Format::Csv.to_string(),
+                profile: Some(account.to_string()),
+                ..Report::default()
+            },
+            transactions,
+        }
+    }
+
+    // previews expiring the given number of minutes from now, each holding
+    // one file of the given number of transactions
+    fn previews(held: &[(i64, usize)]) -> HashMap<String, (NaiveDateTime, Vec<Import>)> {
+        let now = Utc::now().naive_utc();
+        let row = import("Monzo", &[1]).transactions.remove(0);
+
+        held.iter()
+            .map(|(minutes, size)| {
+                let mut preview = import("Monzo", &[]);
+                preview.transactions = vec![row.clone(); *size];
+                let expires_at = now + Duration::minutes(*minutes);
+
+                (minutes.to_string(), (expires_at, vec![preview]))
+            })
+            .collect()
+    }
+
+    fn tokens(previews: &HashMap<String, (NaiveDateTime, Vec<Import>)>) -> Vec<&str> {
+        let mut tokens: Vec<&str> = previews.keys().map(|t| t.as_str()).collect();
+        tokens.sort();
+        tokens
+    }
+
+    #[test]
+    fn expired_previews_are_dropped() {
+        let mut held = previews(&[(-1, 1), (5, 1)]);
+
+        make_room(&mut held, Utc::now().naive_utc(), 1);
+
+        assert_eq!(tokens(&held), ["5"]);
+    }
+
+    #[test]
+    fn the_oldest_preview_makes_room_once_all_are_held() {
+        let minutes: Vec<(i64, usize)> = (1..=MAX_PREVIEWS as i64).map(|m| (m, 1)).collect();
+        let mut held = previews(&minutes);
+
+        make_room(&mut held, Utc::now().naive_utc(), 1);
+
+        assert_eq!(held.len(), MAX_PREVIEWS - 1);
+        assert!(!held.contains_key("1"));
+        assert!(held.contains_key("2"));
+    }
+
+    #[test]
+    fn the_oldest_previews_make_room_for_their_transactions() {
+        let half = MAX_PREVIEW_TRANSACTIONS / 2;
+        let mut held = previews(&[(2, half - 1), (1, half)]);
+
+        make_room(&mut held, Utc::now().naive_utc(), 1);
+        assert_eq!(tokens(&held), ["1", "2"]);
+
+        make_room(&mut held, Utc::now().naive_utc(), 2);
+        assert_eq!(tokens(&held), ["2"]);
+    }
+
+    #[tokio::test]
+    async fn the_files_of_an_upload_are_saved_together() {
+        let db = Memory::default();
+        let session = db.begin_import().await.unwrap_or_else(|e| panic!("{}", e));
+
+        let summary = save(
+            session,
+            vec![import("Monzo", &[1, 2]), import("Amex", &[3])],
+        )
+        .await
+        .unwrap_or_else(|e| panic!("{}", e));
+
+        assert_eq!(summary.imported, 3);
+        let tables = db.tables();
+        assert_eq!(tables.batches.len(), 2);
+        assert_eq!(tables.batches[0].imported_rows, 2);
+        assert_eq!(tables.batches[1].imported_rows, 1);
+        assert_eq!(tables.transactions.len(), 3);
+        // the statement accounts are created as the files are saved
+        let mut names: Vec<&str> = tables.accounts.iter().map(|a| a.name.as_str()).collect();
+        names.sort();
+        assert_eq!(names, ["Amex", "Monzo"]);
+    }
+
+    #[tokio::test]
+    async fn a_failed_file_leaves_nothing_of_the_upload_behind() {
+        let db = Memory::default();
+        db.add_account("Amex", "GBP", Decimal::ZERO);
+        db.tables().accounts[0].closed = true;
+        let session = db.begin_import().await.unwrap_or_else(|e| panic!("{}", e));
+
+        let saved = save(
+            session,
+            vec![import("Monzo", &[1, 2]), import("Amex", &[3])],
+        )
+        .await;
+
+        assert!(matches!(saved, Err(ParseError::ConfigError(_))));
+        let tables = db.tables();
+        assert!(tables.batches.is_empty());
+        assert!(tables.transactions.is_empty());
+        assert_eq!(tables.accounts.len(), 1);
     }
 }
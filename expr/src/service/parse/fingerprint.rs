@@ -0,0 +1,215 @@
+use std::{
+    collections::{hash_map::DefaultHasher, HashMap},
+    hash::{Hash, Hasher},
+};
+
+use sha2::{Digest, Sha256};
+use uuid::Uuid;
+
+use crate::models::transaction::CreateTransaction;
+
+// fingerprints the transactions of one statement file. the bank's own id is
+// used when there is one, otherwise the account, date, amount and description.
+// identical rows within the file are told apart by their occurrence index, so
+// two coffees bought on the same day both import while a statement overlapping
+// an earlier one maps its rows onto the same fingerprints
+#[derive(Default)]
+pub struct Fingerprints {
+    // occurrences seen so far of each row key, by the key's hash so a large
+    // file's descriptions are not kept in memory. the hash only lives as long
+    // as the file is read and is never saved, so it does not matter that
+    // DefaultHasher can change between rust releases
+    occurrences: HashMap<u64, usize>,
+}
+
+impl Fingerprints {
+    // called with each chunk of a file in order
+    pub fn assign(&mut self, transactions: &mut [CreateTransaction]) {
+        for transaction in transactions.iter_mut() {
+            let key = match &transaction.external_id {
+                Some(id) if !id.trim().is_empty() => {
+                    format!("{}|id|{}", transaction.account_type, id.trim())
+                }
+                // the amount is written without trailing zeros, the way amounts
+                // were when they were floats, so earlier fingerprints still match
+                _ => {
+                    let key = format!(
+                        "{}|{}|{}|{}",
+                        transaction.account_type,
+                        transaction.payment_date.format("%Y-%m-%d"),
+                        transaction.amount.amount.normalize(),
+                        normalise(&transaction.description)
+                    );
+
+                    let mut hasher = DefaultHasher::new();
+                    key.hash(&mut hasher);
+                    let occurrence = self.occurrences.entry(hasher.finish()).or_default();
+                    *occurrence += 1;
+
+                    format!("{}|{}", key, occurrence)
+                }
+            };
+
+            transaction.fingerprint = Some(format!("{:x}", Sha256::digest(key.as_bytes())));
+        }
+    }
+}
+
+// puts a transaction into an account, its fingerprint is scoped to the account
+// so the same rows on two accounts are not taken for duplicates. called once,
+// on transactions without an account
+pub fn link(transaction: &mut CreateTransaction, account_id: Uuid) {
+    transaction.account_id = Some(account_id);
+
+    if let Some(fingerprint) = &transaction.fingerprint {
+        let key = format!("{}|{}", account_id, fingerprint);
+        transaction.fingerprint = Some(format!("{:x}", Sha256::digest(key.as_bytes())));
+    }
+}
+
+// banks change the case and spacing of descriptions between exports
+fn normalise(description: &str) -> String {
+    description
+        .split_whitespace()
+        .collect::<Vec<&str>>()
+        .join(" ")
+        .to_lowercase()
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::NaiveDate;
+    use import::service::parse_service::Money;
+
+    use super::*;
+
+    fn row(day: u32, amount: &str, description: &str) -> CreateTransaction {
+        CreateTransaction {
+            account_id: None,
+            account_type: "Monzo".to_string(),
+            payment_date: NaiveDate::from_ymd_opt(2024, 2, day)
+                .and_then(|d| d.and_hms_opt(0, 0, 0))
+                .unwrap(),
+            amount: Money::parse(amount, "GBP").unwrap(),
+            description: description.to_string(),
+            external_id: None,
+            category: None,
+            value_date: None,
+            fingerprint: None,
+        }
+    }
+
+    // two coffees on the 3rd and the rent on the 6th
+    fn statement() -> Vec<CreateTransaction> {
+        vec![
+            row(3, "-2.80", "Pret A Manger"),
+            row(3, "-2.80", "Pret A Manger"),
+            row(6, "-1250.00", "Rent"),
+        ]
+    }
+
+    fn fingerprints(transactions: &[CreateTransaction]) -> Vec<String> {
+        transactions
+            .iter()
+            .map(|t| t.fingerprint.clone().expect("fingerprinted"))
+            .collect()
+    }
+
+    #[test]
+    fn identical_rows_in_a_file_get_their_own_fingerprints() {
+        let mut transactions = statement();
+        Fingerprints::default().assign(&mut transactions);
+
+        let assigned = fingerprints(&transactions);
+        assert_ne!(assigned[0], assigned[1]);
+        assert_ne!(assigned[1], assigned[2]);
+    }
+
+    #[test]
+    fn a_reimport_gets_the_same_fingerprints() {
+        let mut first = statement();
+        Fingerprints::default().assign(&mut first);
+
+        // counting starts again for each file, so both coffees are found
+        // again rather than only the first
+        let mut again = statement();
+        Fingerprints::default().assign(&mut again);
+
+        assert_eq!(fingerprints(&first), fingerprints(&again));
+    }
+
+    #[test]
+    fn occurrences_are_counted_across_chunks() {
+        let mut whole = statement();
+        Fingerprints::default().assign(&mut whole);
+
+        let mut chunked = statement();
+        let mut file = Fingerprints::default();
+        let (first, rest) = chunked.split_at_mut(1);
+        file.assign(first);
+        file.assign(rest);
+
+        assert_eq!(fingerprints(&whole), fingerprints(&chunked));
+    }
+
+    #[test]
+    fn an_overlapping_statement_maps_onto_the_same_rows() {
+        let mut first = statement();
+        Fingerprints::default().assign(&mut first);
+
+        // a later export with the description respaced and recased, and a
+        // new row
+        let mut later = vec![
+            row(3, "-2.80", "PRET  A MANGER"),
+            row(3, "-2.8", "pret a manger"),
+            row(6, "-1250", "Rent"),
+            row(7, "-2.80", "Pret A Manger"),
+        ];
+        Fingerprints::default().assign(&mut later);
+
+        assert_eq!(fingerprints(&later)[..3], fingerprints(&first));
+        assert!(!fingerprints(&first).contains(&fingerprints(&later)[3]));
+    }
+
+    #[test]
+    fn the_bank_id_is_used_when_there_is_one() {
+        let mut transactions = statement();
+        transactions[0].external_id = Some("tx_0001".to_string());
+        transactions[1].external_id = Some("tx_0002".to_string());
+        Fingerprints::default().assign(&mut transactions);
+
+        let mut renamed = statement();
+        renamed[0].external_id = Some(" tx_0001 ".to_string());
+        renamed[0].description = "Pret".to_string();
+        Fingerprints::default().assign(&mut renamed);
+
+        assert_ne!(
+            fingerprints(&transactions)[0],
+            fingerprints(&transactions)[1]
+        );
+        assert_eq!(fingerprints(&transactions)[0], fingerprints(&renamed)[0]);
+    }
+
+    #[test]
+    fn fingerprints_are_scoped_to_the_account() {
+        let mut transactions = statement();
+        Fingerprints::default().assign(&mut transactions);
+        let (current, savings) = (Uuid::new_v4(), Uuid::new_v4());
+
+        let mut first = statement();
+        Fingerprints::default().assign(&mut first);
+        let mut again = statement();
+        Fingerprints::default().assign(&mut again);
+        for t in &mut first {
+            link(t, current);
+        }
+        for (t, account) in again.iter_mut().zip([current, savings, savings]) {
+            link(t, account);
+        }
+
+        assert_eq!(first[0].account_id, Some(current));
+        assert_eq!(fingerprints(&first)[0], fingerprints(&again)[0]);
+        assert_ne!(fingerprints(&first)[1], fingerprints(&again)[1]);
+        assert_ne!(fingerprints(&transactions)[0], fingerprints(&first)[0]);
+    }
+}
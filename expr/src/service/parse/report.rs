@@ -0,0 +1,188 @@
+use std::{
+    fmt::{self, Display},
+    ops::Add,
+    str::FromStr,
+};
+
+use chrono::NaiveDateTime;
+use serde::Serialize;
+
+use import::service::parse_service::RowError;
+
+use crate::models::transaction::CreateTransaction;
+
+use super::ParseError;
+
+// most row errors and duplicates listed in a file's report, the counts include
+// the rest so a large export with a bad column does not fill the response
+pub const LISTED_ROWS: usize = 1000;
+
+// what happens to the valid rows of a file that also has invalid ones
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum Policy {
+    // import the valid rows and report the invalid ones
+    #[default]
+    SkipInvalid,
+    // import nothing from the file
+    RejectFile,
+}
+
+impl FromStr for Policy {
+    type Err = ParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim() {
+            "skip_invalid" => Ok(Policy::SkipInvalid),
+            "reject_file" => Ok(Policy::RejectFile),
+            other => Err(ParseError::ConfigError(format!(
+                "on_error must be skip_invalid or reject_file, got: {}",
+                other
+            ))),
+        }
+    }
+}
+
+impl Display for Policy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Policy::SkipInvalid => write!(f, "skip_invalid"),
+            Policy::RejectFile => write!(f, "reject_file"),
+        }
+    }
+}
+
+// outcome of importing one statement file. failed rows could not be read,
+// skipped rows were valid but not imported because of the policy and
+// duplicates had already been imported
+#[derive(Debug, Default, Clone, Serialize)]
+pub struct Report {
+    pub batch_id: Option<String>,
+    pub file: Option<String>,
+    pub format: String,
+    pub profile: Option<String>,
+    // sha256 of the file's contents
+    pub checksum: String,
+    pub imported: usize,
+    pub skipped: usize,
+    pub failed: usize,
+    pub duplicates: usize,
+    pub errors: Vec<RowError>,
+    // rows that were read but look suspicious, e.g. dated in the future
+    pub warnings: Vec<String>,
+    pub duplicate_transactions: Vec<CreateTransaction>,
+    // the transactions that would be imported, only listed in previews
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub transactions: Vec<CreateTransaction>,
+}
+
+impl Report {
+    pub fn add_error(&mut self, error: RowError) {
+        self.failed += 1;
+        if self.errors.len() < LISTED_ROWS {
+            self.errors.push(error);
+        }
+    }
+
+    pub fn add_duplicates(&mut self, duplicates: Vec<CreateTransaction>) {
+        self.duplicates += duplicates.len();
+        let listed = LISTED_ROWS.saturating_sub(self.duplicate_transactions.len());
+        self.duplicate_transactions
+            .extend(duplicates.into_iter().take(listed));
+    }
+}
+
+// totals across all the files of an upload
+#[derive(Debug, Default, Serialize)]
+pub struct Summary {
+    pub imported: usize,
+    pub skipped: usize,
+    pub failed: usize,
+    pub duplicates: usize,
+    pub files: Vec<Report>,
+}
+
+impl Summary {
+    pub fn add(&mut self, report: Report) {
+        self.imported += report.imported;
+        self.skipped += report.skipped;
+        self.failed += report.failed;
+        self.duplicates += report.duplicates;
+        self.files.push(report);
+    }
+}
+
+// row counts of an import so far
+#[derive(Debug, Default, Clone, Copy, PartialEq, Serialize)]
+pub struct Counts {
+    pub imported: usize,
+    pub skipped: usize,
+    pub failed: usize,
+    pub duplicates: usize,
+}
+
+impl From<&Report> for Counts {
+    fn from(report: &Report) -> Self {
+        Self {
+            imported: report.imported,
+            skipped: report.skipped,
+            failed: report.failed,
+            duplicates: report.duplicates,
+        }
+    }
+}
+
+impl From<&Summary> for Counts {
+    fn from(summary: &Summary) -> Self {
+        Self {
+            imported: summary.imported,
+            skipped: summary.skipped,
+            failed: summary.failed,
+            duplicates: summary.duplicates,
+        }
+    }
+}
+
+impl Add for Counts {
+    type Output = Counts;
+
+    fn add(self, other: Counts) -> Counts {
+        Counts {
+            imported: self.imported + other.imported,
+            skipped: self.skipped + other.skipped,
+            failed: self.failed + other.failed,
+            duplicates: self.duplicates + other.duplicates,
+        }
+    }
+}
+
+// what happened to a chunk of a file's rows as it was imported
+#[derive(Debug, Clone, Serialize)]
+#[serde(untagged)]
+pub enum Event {
+    // rows that could not be read
+    Errors {
+        file: Option<String>,
+        errors: Vec<RowError>,
+    },
+    // rows that had already been imported
+    Duplicates {
+        file: Option<String>,
+        transactions: Vec<CreateTransaction>,
+    },
+    // the file's counts once the chunk was saved
+    Progress {
+        file: Option<String>,
+        #[serde(flatten)]
+        counts: Counts,
+    },
+}
+
+// the outcome an upload would have, kept under the token until it is committed
+// or expires
+#[derive(Debug, Serialize)]
+pub struct Preview {
+    pub token: String,
+    pub expires_at: NaiveDateTime,
+    #[serde(flatten)]
+    pub summary: Summary,
+}
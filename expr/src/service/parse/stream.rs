@@ -0,0 +1,150 @@
+use std::io::{self, Read};
+
+use sha2::{Digest, Sha256};
+use tokio::sync::mpsc;
+
+use import::service::parse_service::{delimited, Row};
+
+use super::{Config, ParseError};
+
+// parses a streamed csv upload on a blocking thread, sending its rows on in
+// chunks as they are read. the rest of the upload is still read after the
+// receiver hangs up so the checksum, which is returned, covers the whole file
+pub fn csv(
+    extraction_config: &Config,
+    head: Vec<u8>,
+    input: mpsc::Receiver<Vec<u8>>,
+    output: mpsc::Sender<Vec<Row>>,
+    chunk_rows: usize,
+) -> Result<String, ParseError> {
+    let mut reader = Reader {
+        buffered: io::Cursor::new(head),
+        input,
+        hasher: Sha256::new(),
+    };
+
+    let mut chunk = Vec::with_capacity(chunk_rows);
+    for row in delimited::rows(extraction_config, &mut reader)? {
+        chunk.push(row);
+
+        if chunk.len() == chunk_rows && output.blocking_send(std::mem::take(&mut chunk)).is_err() {
+            break;
+        }
+    }
+
+    if !chunk.is_empty() {
+        let _ = output.blocking_send(chunk);
+    }
+
+    reader.finish()
+}
+
+// reads the bytes already received followed by the rest of the upload,
+// hashing them on the way
+struct Reader {
+    buffered: io::Cursor<Vec<u8>>,
+    input: mpsc::Receiver<Vec<u8>>,
+    hasher: Sha256,
+}
+
+impl Reader {
+    fn finish(mut self) -> Result<String, ParseError> {
+        io::copy(&mut self, &mut io::sink()).map_err(|e| ParseError::RecordError(e.to_string()))?;
+
+        Ok(format!("{:x}", self.hasher.finalize()))
+    }
+}
+
+impl Read for Reader {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+
+        loop {
+            let read = self.buffered.read(buf)?;
+            if read > 0 {
+                self.hasher.update(&buf[..read]);
+                return Ok(read);
+            }
+
+            match self.input.blocking_recv() {
+                Some(chunk) => self.buffered = io::Cursor::new(chunk),
+                None => return Ok(0),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FILE: &str = "Date,Description,Amount\n\
+        01/02/2024,Coffee,-3.50\n\
+        02/02/2024,Salary,2000.00\n\
+        03/02/2024,Bad row,twelve\n\
+        04/02/2024,Rent,-950.00\n\
+        05/02/2024,Coffee,-3.50\n";
+
+    fn config() -> Config {
+        Config {
+            name: "Test".to_string(),
+            date_headers: vec!["Date".to_string()],
+            description_headers: vec!["Description".to_string()],
+            amount_headers: vec!["Amount".to_string()],
+            ..Config::default()
+        }
+    }
+
+    // the first bytes of the file as already received, and a receiver with the
+    // rest of it sent a few bytes at a time
+    fn upload(head: usize) -> (Vec<u8>, mpsc::Receiver<Vec<u8>>) {
+        let (sender, receiver) = mpsc::channel(FILE.len());
+        for chunk in FILE.as_bytes()[head..].chunks(7) {
+            sender.try_send(chunk.to_vec()).unwrap();
+        }
+
+        (FILE.as_bytes()[..head].to_vec(), receiver)
+    }
+
+    fn checksum() -> String {
+        format!("{:x}", Sha256::digest(FILE.as_bytes()))
+    }
+
+    #[test]
+    fn rows_are_sent_in_chunks_in_order() {
+        let (head, input) = upload(30);
+        let (output, mut chunks) = mpsc::channel(8);
+
+        let sum = csv(&config(), head, input, output, 2).unwrap_or_else(|e| panic!("{}", e));
+
+        let mut sizes = Vec::new();
+        let mut descriptions = Vec::new();
+        while let Ok(rows) = chunks.try_recv() {
+            sizes.push(rows.len());
+            descriptions.extend(rows.into_iter().map(|r| match r {
+                Ok(t) => t.description,
+                Err(_) => "error".to_string(),
+            }));
+        }
+
+        assert_eq!(sizes, [2, 2, 1]);
+        assert_eq!(
+            descriptions,
+            ["Coffee", "Salary", "error", "Rent", "Coffee"]
+        );
+        assert_eq!(sum, checksum());
+    }
+
+    #[test]
+    fn the_checksum_covers_the_file_after_the_receiver_hangs_up() {
+        let (head, input) = upload(10);
+        let (output, chunks) = mpsc::channel(8);
+        drop(chunks);
+
+        let sum = csv(&config(), head, input, output, 2).unwrap_or_else(|e| panic!("{}", e));
+
+        assert_eq!(sum, checksum());
+    }
+}
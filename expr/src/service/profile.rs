@@ -0,0 +1,140 @@
+use core::fmt;
+use std::{fmt::Display, sync::Arc};
+use tokio::sync::RwLock;
+
+use import::service::parse_service::AccountType;
+
+use crate::database::base::{DatabaseError, DatabaseInit};
+
+use super::parse::Config;
+
+#[allow(clippy::enum_variant_names)]
+pub enum ProfileError {
+    InvalidError(String),
+    SaveError(String),
+    FindError(String),
+    DeleteError(String),
+}
+
+impl Display for ProfileError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ProfileError::InvalidError(e) => write!(f, "ProfileError -> InvalidError, {}", e),
+            ProfileError::SaveError(e) => write!(f, "ProfileError -> SaveError, {}", e),
+            ProfileError::FindError(e) => write!(f, "ProfileError -> FindError, {}", e),
+            ProfileError::DeleteError(e) => write!(f, "ProfileError -> DeleteError, {}", e),
+        }
+    }
+}
+
+pub trait ProfileWrite {
+    async fn create_profile(&self, profile: &Config) -> Result<(), DatabaseError>;
+
+    // returns false when no profile with the given name exists
+    async fn update_profile(&self, name: &str, profile: &Config) -> Result<bool, DatabaseError>;
+
+    // returns false when no profile with the given name exists
+    async fn delete_profile(&self, name: &str) -> Result<bool, DatabaseError>;
+}
+
+pub trait ProfileRead {
+    async fn get_profile(&self, name: &str) -> Result<Option<Config>, DatabaseError>;
+    async fn get_profiles(&self) -> Result<Vec<Config>, DatabaseError>;
+}
+
+pub struct ProfileService<T>
+where
+    T: DatabaseInit + ProfileWrite + ProfileRead,
+{
+    db: Arc<RwLock<T>>,
+}
+
+impl<T> ProfileService<T>
+where
+    T: DatabaseInit + ProfileWrite + ProfileRead,
+{
+    pub fn new(db: T) -> ProfileService<T> {
+        let db = Arc::new(RwLock::new(db));
+        Self { db }
+    }
+
+    pub async fn create_profile(&self, profile: Config) -> Result<Config, ProfileError> {
+        profile
+            .validate()
+            .map_err(|e| ProfileError::InvalidError(e.to_string()))?;
+
+        let db_connection = self.db.write().await;
+
+        db_connection
+            .create_profile(&profile)
+            .await
+            .map_err(|e| ProfileError::SaveError(e.to_string()))?;
+
+        Ok(profile)
+    }
+
+    pub async fn update_profile(
+        &self,
+        name: &str,
+        profile: Config,
+    ) -> Result<Option<Config>, ProfileError> {
+        profile
+            .validate()
+            .map_err(|e| ProfileError::InvalidError(e.to_string()))?;
+
+        let db_connection = self.db.write().await;
+
+        let updated = db_connection
+            .update_profile(name, &profile)
+            .await
+            .map_err(|e| ProfileError::SaveError(e.to_string()))?;
+
+        if !updated {
+            return Ok(None);
+        }
+
+        Ok(Some(profile))
+    }
+
+    // a saved profile is used in place of the built in bank profile of the
+    // same name
+    pub async fn find_profile(&self, name: &str) -> Result<Option<Config>, ProfileError> {
+        let db_connection = self.db.read().await;
+
+        let profile = db_connection
+            .get_profile(name)
+            .await
+            .map_err(|e| ProfileError::FindError(e.to_string()))?;
+
+        Ok(profile.or_else(|| AccountType::named(name).map(|a| a.config())))
+    }
+
+    // the saved profiles followed by the built in ones that have not been
+    // replaced by a saved profile
+    pub async fn find_profiles(&self) -> Result<Vec<Config>, ProfileError> {
+        let db_connection = self.db.read().await;
+
+        let mut profiles = db_connection
+            .get_profiles()
+            .await
+            .map_err(|e| ProfileError::FindError(e.to_string()))?;
+
+        for account_type in AccountType::all() {
+            let config = account_type.config();
+            if !profiles.iter().any(|p| p.name == config.name) {
+                profiles.push(config);
+            }
+        }
+
+        Ok(profiles)
+    }
+
+    pub async fn delete_profile(&self, name: &str) -> Result<bool, ProfileError> {
+        let db_connection = self.db.write().await;
+
+        db_connection
+            .delete_profile(name)
+            .await
+            .map_err(|e| ProfileError::DeleteError(e.to_string()))
+    }
+}
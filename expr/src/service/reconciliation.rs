@@ -0,0 +1,547 @@
+use core::fmt;
+use std::{fmt::Display, sync::Arc};
+use tokio::sync::RwLock;
+
+use chrono::NaiveDate;
+use rust_decimal::Decimal;
+use uuid::Uuid;
+
+use crate::{
+    database::base::{DatabaseError, DatabaseInit},
+    models::{
+        account::Account,
+        reconciliation::{
+            ClearTransactions, CreateReconciliation, Reconciliation, ReconciliationState,
+            ReconciliationSummary,
+        },
+        transaction::Transaction,
+    },
+};
+
+use super::account::AccountRead;
+
+#[allow(clippy::enum_variant_names)]
+pub enum ReconciliationError {
+    InvalidError(String),
+    SaveError(String),
+    FindError(String),
+    DeleteError(String),
+}
+
+impl Display for ReconciliationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ReconciliationError::InvalidError(e) => {
+                write!(f, "ReconciliationError -> InvalidError, {}", e)
+            }
+            ReconciliationError::SaveError(e) => {
+                write!(f, "ReconciliationError -> SaveError, {}", e)
+            }
+            ReconciliationError::FindError(e) => {
+                write!(f, "ReconciliationError -> FindError, {}", e)
+            }
+            ReconciliationError::DeleteError(e) => {
+                write!(f, "ReconciliationError -> DeleteError, {}", e)
+            }
+        }
+    }
+}
+
+pub trait ReconciliationWrite {
+    async fn create_reconciliation(
+        &self,
+        account_id: Uuid,
+        reconciliation: &CreateReconciliation,
+    ) -> Result<Reconciliation, DatabaseError>;
+
+    // marks the account's transactions cleared or uncleared, reconciled ones
+    // are left alone
+    async fn set_cleared(
+        &self,
+        account_id: Uuid,
+        transactions: &[Uuid],
+        cleared: bool,
+    ) -> Result<(), DatabaseError>;
+
+    // reconciles the account's transactions cleared up to the statement date,
+    // finishes the reconciliation and saves its closing balance as a balance
+    // anchor, returns the number of transactions reconciled
+    async fn finish_reconciliation(
+        &self,
+        reconciliation: &Reconciliation,
+    ) -> Result<u64, DatabaseError>;
+
+    // returns false when no open reconciliation has the given id
+    async fn delete_reconciliation(&self, id: Uuid) -> Result<bool, DatabaseError>;
+}
+
+pub trait ReconciliationRead {
+    async fn get_reconciliation(&self, id: &str) -> Result<Option<Reconciliation>, DatabaseError>;
+    async fn get_reconciliations(
+        &self,
+        account_id: Uuid,
+    ) -> Result<Vec<Reconciliation>, DatabaseError>;
+
+    // the sum of the account's cleared and reconciled transactions in the
+    // currency paid up to the end of the day
+    async fn get_cleared_total(
+        &self,
+        account_id: Uuid,
+        currency: &str,
+        to: NaiveDate,
+    ) -> Result<Decimal, DatabaseError>;
+
+    // the transactions a reconciliation of the account is made from
+    async fn get_unreconciled_transactions(
+        &self,
+        reconciliation: &Reconciliation,
+    ) -> Result<Vec<Transaction>, DatabaseError>;
+
+    // the given transactions that belong to the account, are not reconciled
+    // and were paid up to the end of the day
+    async fn get_unreconciled_ids(
+        &self,
+        account_id: Uuid,
+        transactions: &[Uuid],
+        to: NaiveDate,
+    ) -> Result<Vec<Uuid>, DatabaseError>;
+}
+
+pub struct ReconciliationService<T>
+where
+    T: DatabaseInit + ReconciliationWrite + ReconciliationRead + AccountRead,
+{
+    db: Arc<RwLock<T>>,
+}
+
+impl<T> ReconciliationService<T>
+where
+    T: DatabaseInit + ReconciliationWrite + ReconciliationRead + AccountRead,
+{
+    pub fn new(db: T) -> ReconciliationService<T> {
+        let db = Arc::new(RwLock::new(db));
+        Self { db }
+    }
+
+    // starts reconciling the account against a statement, returns None when
+    // no account has the id
+    pub async fn create_reconciliation(
+        &self,
+        account_id: &str,
+        reconciliation: CreateReconciliation,
+    ) -> Result<Option<ReconciliationSummary>, ReconciliationError> {
+        let db_connection = self.db.write().await;
+
+        let account = db_connection
+            .get_account(account_id)
+            .await
+            .map_err(|e| ReconciliationError::FindError(e.to_string()))?;
+        let Some(account) = account else {
+            return Ok(None);
+        };
+
+        let earlier = db_connection
+            .get_reconciliations(account.id)
+            .await
+            .map_err(|e| ReconciliationError::FindError(e.to_string()))?;
+        if earlier.iter().any(|r| r.state == ReconciliationState::Open) {
+            return Err(ReconciliationError::InvalidError(format!(
+                "Account {} is already being reconciled, finish or delete that first",
+                account.name
+            )));
+        }
+        if let Some(last) = earlier
+            .iter()
+            .map(|r| r.statement_date)
+            .filter(|d| *d >= reconciliation.statement_date)
+            .max()
+        {
+            return Err(ReconciliationError::InvalidError(format!(
+                "Account {} is already reconciled up to {}",
+                account.name, last
+            )));
+        }
+
+        let created = db_connection
+            .create_reconciliation(account.id, &reconciliation)
+            .await
+            .map_err(|e| ReconciliationError::SaveError(e.to_string()))?;
+
+        summary(&*db_connection, created, account).await.map(Some)
+    }
+
+    pub async fn find_reconciliations(
+        &self,
+        account_id: &str,
+    ) -> Result<Option<Vec<Reconciliation>>, ReconciliationError> {
+        let db_connection = self.db.read().await;
+
+        let account = db_connection
+            .get_account(account_id)
+            .await
+            .map_err(|e| ReconciliationError::FindError(e.to_string()))?;
+        let Some(account) = account else {
+            return Ok(None);
+        };
+
+        db_connection
+            .get_reconciliations(account.id)
+            .await
+            .map(Some)
+            .map_err(|e| ReconciliationError::FindError(e.to_string()))
+    }
+
+    // returns None when no reconciliation has the id
+    pub async fn find_reconciliation(
+        &self,
+        id: &str,
+    ) -> Result<Option<ReconciliationSummary>, ReconciliationError> {
+        let db_connection = self.db.read().await;
+
+        let Some((reconciliation, account)) = find(&*db_connection, id).await? else {
+            return Ok(None);
+        };
+
+        summary(&*db_connection, reconciliation, account)
+            .await
+            .map(Some)
+    }
+
+    // transactions can only be ticked off an open reconciliation of their
+    // account when paid by its statement date, reconciled ones are locked
+    pub async fn clear_transactions(
+        &self,
+        id: &str,
+        clear: ClearTransactions,
+    ) -> Result<Option<ReconciliationSummary>, ReconciliationError> {
+        let db_connection = self.db.write().await;
+
+        let Some((reconciliation, account)) = find(&*db_connection, id).await? else {
+            return Ok(None);
+        };
+        open(&reconciliation)?;
+
+        let found = db_connection
+            .get_unreconciled_ids(
+                account.id,
+                &clear.transactions,
+                reconciliation.statement_date,
+            )
+            .await
+            .map_err(|e| ReconciliationError::FindError(e.to_string()))?;
+        if let Some(missing) = clear.transactions.iter().find(|t| !found.contains(t)) {
+            return Err(ReconciliationError::InvalidError(format!(
+                "Transaction {} is not an unreconciled transaction of account {} paid by {}",
+                missing, account.name, reconciliation.statement_date
+            )));
+        }
+
+        db_connection
+            .set_cleared(account.id, &clear.transactions, clear.cleared)
+            .await
+            .map_err(|e| ReconciliationError::SaveError(e.to_string()))?;
+
+        summary(&*db_connection, reconciliation, account)
+            .await
+            .map(Some)
+    }
+
+    // locks the cleared transactions once they add up to the statement's
+    // closing balance
+    pub async fn finish_reconciliation(
+        &self,
+        id: &str,
+    ) -> Result<Option<ReconciliationSummary>, ReconciliationError> {
+        let db_connection = self.db.write().await;
+
+        let Some((reconciliation, account)) = find(&*db_connection, id).await? else {
+            return Ok(None);
+        };
+        open(&reconciliation)?;
+
+        let current = summary(&*db_connection, reconciliation.clone(), account.clone()).await?;
+        if !current.difference.is_zero() {
+            return Err(ReconciliationError::InvalidError(format!(
+                "The cleared balance is {} away from the statement's closing balance",
+                current.difference
+            )));
+        }
+
+        db_connection
+            .finish_reconciliation(&reconciliation)
+            .await
+            .map_err(|e| ReconciliationError::SaveError(e.to_string()))?;
+
+        let Some((finished, account)) = find(&*db_connection, id).await? else {
+            return Ok(None);
+        };
+
+        summary(&*db_connection, finished, account).await.map(Some)
+    }
+
+    // only an open reconciliation can be deleted, its transactions stay cleared
+    pub async fn delete_reconciliation(&self, id: &str) -> Result<bool, ReconciliationError> {
+        let db_connection = self.db.write().await;
+
+        let Some((reconciliation, _)) = find(&*db_connection, id).await? else {
+            return Ok(false);
+        };
+        open(&reconciliation)?;
+
+        db_connection
+            .delete_reconciliation(reconciliation.id)
+            .await
+            .map_err(|e| ReconciliationError::DeleteError(e.to_string()))
+    }
+}
+
+async fn find<T>(db: &T, id: &str) -> Result<Option<(Reconciliation, Account)>, ReconciliationError>
+where
+    T: ReconciliationRead + AccountRead,
+{
+    let reconciliation = db
+        .get_reconciliation(id)
+        .await
+        .map_err(|e| ReconciliationError::FindError(e.to_string()))?;
+    let Some(reconciliation) = reconciliation else {
+        return Ok(None);
+    };
+
+    let account = db
+        .get_account(&reconciliation.account_id.to_string())
+        .await
+        .map_err(|e| ReconciliationError::FindError(e.to_string()))?
+        .ok_or_else(|| {
+            ReconciliationError::FindError(format!(
+                "Unable to find account {}",
+                reconciliation.account_id
+            ))
+        })?;
+
+    Ok(Some((reconciliation, account)))
+}
+
+fn open(reconciliation: &Reconciliation) -> Result<(), ReconciliationError> {
+    if reconciliation.state != ReconciliationState::Open {
+        return Err(ReconciliationError::InvalidError(format!(
+            "Reconciliation {} is finished",
+            reconciliation.id
+        )));
+    }
+
+    Ok(())
+}
+
+async fn summary<T>(
+    db: &T,
+    reconciliation: Reconciliation,
+    account: Account,
+) -> Result<ReconciliationSummary, ReconciliationError>
+where
+    T: ReconciliationRead,
+{
+    // a finished reconciliation matched its statement, transactions cleared
+    // since belong to the next one
+    if reconciliation.state == ReconciliationState::Finished {
+        return Ok(ReconciliationSummary {
+            cleared_balance: reconciliation.closing_balance,
+            difference: Decimal::ZERO,
+            reconciliation,
+            currency: account.currency,
+            transactions: Vec::new(),
+        });
+    }
+
+    let cleared = db
+        .get_cleared_total(account.id, &account.currency, reconciliation.statement_date)
+        .await
+        .map_err(|e| ReconciliationError::FindError(e.to_string()))?;
+    let transactions = db
+        .get_unreconciled_transactions(&reconciliation)
+        .await
+        .map_err(|e| ReconciliationError::FindError(e.to_string()))?;
+
+    let cleared_balance = account.opening_balance + cleared;
+
+    Ok(ReconciliationSummary {
+        difference: reconciliation.closing_balance - cleared_balance,
+        reconciliation,
+        currency: account.currency,
+        cleared_balance,
+        transactions,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use import::service::parse_service::Money;
+
+    use super::*;
+    use crate::{database::memory::Memory, models::transaction::TransactionStatus};
+
+    fn date(day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(2024, 2, day).unwrap()
+    }
+
+    fn decimal(value: &str) -> Decimal {
+        value.parse().unwrap()
+    }
+
+    // the monzo fixture's rows, reconciled against a statement to the 4th
+    async fn start(
+        closing_balance: &str,
+    ) -> (ReconciliationService<Memory>, ReconciliationSummary) {
+        let db = Memory::default();
+        let account = db.add_account("Monzo", "GBP", decimal("100"));
+        for (day, amount) in [(3, "-4.95"), (4, "30.00"), (6, "-1250.00")] {
+            db.add_transaction(account.id, date(day), Money::new(decimal(amount), "GBP"));
+        }
+        let account_id = account.id.to_string();
+        let service = ReconciliationService::new(db);
+
+        let summary = service
+            .create_reconciliation(
+                &account_id,
+                CreateReconciliation {
+                    statement_date: date(4),
+                    closing_balance: decimal(closing_balance),
+                },
+            )
+            .await
+            .unwrap_or_else(|e| panic!("{}", e))
+            .expect("account exists");
+
+        (service, summary)
+    }
+
+    fn ids(summary: &ReconciliationSummary) -> Vec<Uuid> {
+        summary
+            .transactions
+            .iter()
+            .map(|t| Uuid::parse_str(&t.id).unwrap())
+            .collect()
+    }
+
+    async fn clear(
+        service: &ReconciliationService<Memory>,
+        summary: &ReconciliationSummary,
+        transactions: Vec<Uuid>,
+    ) -> Result<ReconciliationSummary, ReconciliationError> {
+        service
+            .clear_transactions(
+                &summary.reconciliation.id.to_string(),
+                ClearTransactions {
+                    transactions,
+                    cleared: true,
+                },
+            )
+            .await
+            .map(|s| s.expect("reconciliation exists"))
+    }
+
+    #[tokio::test]
+    async fn summary_adds_cleared_transactions_to_the_opening_balance() {
+        let (service, summary) = start("125.05").await;
+
+        // only the transactions paid by the statement date can be ticked off
+        assert_eq!(summary.transactions.len(), 2);
+        assert_eq!(summary.cleared_balance, decimal("100"));
+        assert_eq!(summary.difference, decimal("25.05"));
+
+        let first = ids(&summary)[..1].to_vec();
+        let summary = clear(&service, &summary, first)
+            .await
+            .unwrap_or_else(|e| panic!("{}", e));
+        assert_eq!(summary.cleared_balance, decimal("95.05"));
+        assert_eq!(summary.difference, decimal("30.00"));
+
+        let all = ids(&summary);
+        let summary = clear(&service, &summary, all)
+            .await
+            .unwrap_or_else(|e| panic!("{}", e));
+        assert_eq!(summary.cleared_balance, decimal("125.05"));
+        assert!(summary.difference.is_zero());
+    }
+
+    #[tokio::test]
+    async fn transactions_after_the_statement_date_cannot_be_cleared() {
+        let (service, summary) = start("125.05").await;
+        let later = service.db.read().await.tables().transactions[2].id.clone();
+
+        let cleared = clear(&service, &summary, vec![Uuid::parse_str(&later).unwrap()]).await;
+
+        assert!(matches!(cleared, Err(ReconciliationError::InvalidError(_))));
+    }
+
+    #[tokio::test]
+    async fn finish_is_refused_until_there_is_no_difference() {
+        let (service, summary) = start("125.05").await;
+        let id = summary.reconciliation.id.to_string();
+
+        let finished = service.finish_reconciliation(&id).await;
+        assert!(matches!(
+            finished,
+            Err(ReconciliationError::InvalidError(_))
+        ));
+
+        clear(&service, &summary, ids(&summary))
+            .await
+            .unwrap_or_else(|e| panic!("{}", e));
+        let finished = service
+            .finish_reconciliation(&id)
+            .await
+            .unwrap_or_else(|e| panic!("{}", e))
+            .expect("reconciliation exists");
+
+        assert_eq!(finished.reconciliation.state, ReconciliationState::Finished);
+        assert_eq!(finished.cleared_balance, decimal("125.05"));
+        let db = service.db.read().await;
+        let statuses: Vec<TransactionStatus> =
+            db.tables().transactions.iter().map(|t| t.status).collect();
+        assert_eq!(
+            statuses,
+            [
+                TransactionStatus::Reconciled,
+                TransactionStatus::Reconciled,
+                TransactionStatus::Uncleared
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn finished_reconciliations_are_locked() {
+        let (service, summary) = start("125.05").await;
+        let id = summary.reconciliation.id.to_string();
+        clear(&service, &summary, ids(&summary))
+            .await
+            .unwrap_or_else(|e| panic!("{}", e));
+        service
+            .finish_reconciliation(&id)
+            .await
+            .unwrap_or_else(|e| panic!("{}", e));
+
+        let deleted = service.delete_reconciliation(&id).await;
+        assert!(matches!(deleted, Err(ReconciliationError::InvalidError(_))));
+
+        let cleared = clear(&service, &summary, ids(&summary)).await;
+        assert!(matches!(cleared, Err(ReconciliationError::InvalidError(_))));
+    }
+
+    #[tokio::test]
+    async fn open_reconciliations_can_be_deleted() {
+        let (service, summary) = start("125.05").await;
+        let id = summary.reconciliation.id.to_string();
+
+        let deleted = service
+            .delete_reconciliation(&id)
+            .await
+            .unwrap_or_else(|e| panic!("{}", e));
+
+        assert!(deleted);
+        assert!(service
+            .find_reconciliation(&id)
+            .await
+            .unwrap_or_else(|e| panic!("{}", e))
+            .is_none());
+    }
+}
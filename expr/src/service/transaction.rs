@@ -1,43 +1,43 @@
 use core::fmt;
 use std::{fmt::Display, sync::Arc};
 use tokio::sync::RwLock;
-use uuid::Uuid;
 
 use crate::{
     database::base::{DatabaseError, DatabaseInit},
-    models::transaction::{CreateTransaction, Transaction},
+    models::transaction::{Transaction, TransactionStatus},
 };
 
 pub enum TransactionError {
-    SaveError(String),
+    InvalidError(String),
     FindError(String),
 }
 
 impl Display for TransactionError {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
+            TransactionError::InvalidError(e) => {
+                write!(f, "TransactionError -> InvalidError, {}", e)
+            }
             TransactionError::FindError(e) => {
                 write!(f, "TransactionError -> FindError, {}", e)
             }
-            TransactionError::SaveError(e) => {
-                write!(f, "TransactionError -> SaveError, {}", e)
-            }
         }
     }
 }
 
 pub trait TransactionWrite {
-    async fn create_transaction(
-        &self,
-        create_transaction: CreateTransaction,
-    ) -> Result<Uuid, DatabaseError>;
-
     async fn delete_transaction(&self, id: &str) -> Result<(), DatabaseError>;
 }
 
 pub trait TransactionRead {
     async fn get_transaction(&self, id: &str) -> Result<Option<Transaction>, DatabaseError>;
     async fn get_transactions(&self) -> Result<Vec<Transaction>, DatabaseError>;
+
+    // the given fingerprints that already belong to a saved transaction
+    async fn get_known_fingerprints(
+        &self,
+        fingerprints: &[String],
+    ) -> Result<Vec<String>, DatabaseError>;
 }
 
 pub struct TransactionService<T>
@@ -56,20 +56,6 @@ where
         Self { db }
     }
 
-    pub async fn create_transaction(
-        &self,
-        create_transaction: CreateTransaction,
-    ) -> Result<(), TransactionError> {
-        let db_connection = self.db.write().await;
-
-        db_connection
-            .create_transaction(create_transaction)
-            .await
-            .map_err(|e| TransactionError::SaveError(e.to_string()))?;
-
-        Ok(())
-    }
-
     pub async fn find_transaction(
         &self,
         id: &str,
@@ -81,7 +67,7 @@ where
             .await
             .map_err(|e| TransactionError::FindError(e.to_string()))?;
 
-        return Ok(transaction);
+        Ok(transaction)
     }
 
     pub async fn find_transactions(&self) -> Result<Vec<Transaction>, TransactionError> {
@@ -92,18 +78,99 @@ where
             .await
             .map_err(|e| TransactionError::FindError(e.to_string()))?;
 
-        return Ok(transactions);
+        Ok(transactions)
     }
 
+    pub async fn find_known_fingerprints(
+        &self,
+        fingerprints: &[String],
+    ) -> Result<Vec<String>, TransactionError> {
+        let db_connection = self.db.read().await;
+
+        db_connection
+            .get_known_fingerprints(fingerprints)
+            .await
+            .map_err(|e| TransactionError::FindError(e.to_string()))
+    }
+
+    // reconciled transactions are locked, an unknown one is left to the delete
     pub async fn delete_transaction(&self, id: &str) -> Result<(), TransactionError> {
         let db_connection = self.db.read().await;
 
-        let _ = db_connection
+        let transaction = db_connection
+            .get_transaction(id)
+            .await
+            .map_err(|e| TransactionError::FindError(e.to_string()))?;
+        if transaction.is_some_and(|t| t.status == TransactionStatus::Reconciled) {
+            return Err(TransactionError::InvalidError(format!(
+                "Transaction {} is reconciled",
+                id
+            )));
+        }
+
+        db_connection
             .delete_transaction(id)
             .await
             .map_err(|e| TransactionError::FindError(e.to_string()))?;
 
-        return Ok(());
+        Ok(())
     }
     // there will need to be something to do with categorising the transactions
 }
+
+#[cfg(test)]
+mod tests {
+    use chrono::NaiveDate;
+    use import::service::parse_service::Money;
+    use rust_decimal::Decimal;
+
+    use super::*;
+    use crate::database::memory::Memory;
+
+    // a cleared and a reconciled transaction, by their ids
+    fn service() -> (TransactionService<Memory>, String, String) {
+        let db = Memory::default();
+        let account = db.add_account("Monzo", "GBP", Decimal::ZERO);
+        let day = NaiveDate::from_ymd_opt(2024, 2, 1).unwrap();
+        let mut ids = Vec::new();
+        for status in [TransactionStatus::Cleared, TransactionStatus::Reconciled] {
+            let id = db.add_transaction(account.id, day, Money::new(Decimal::ONE, "GBP"));
+            db.tables().transactions.last_mut().unwrap().status = status;
+            ids.push(id.to_string());
+        }
+
+        let reconciled = ids.pop().unwrap();
+        let cleared = ids.pop().unwrap();
+        (TransactionService::new(db), cleared, reconciled)
+    }
+
+    #[tokio::test]
+    async fn reconciled_transactions_cannot_be_deleted() {
+        let (service, _, reconciled) = service();
+
+        let deleted = service.delete_transaction(&reconciled).await;
+
+        assert!(matches!(deleted, Err(TransactionError::InvalidError(_))));
+        assert!(service
+            .find_transaction(&reconciled)
+            .await
+            .unwrap_or_else(|e| panic!("{}", e))
+            .is_some());
+    }
+
+    #[tokio::test]
+    async fn unreconciled_transactions_can_be_deleted() {
+        let (service, cleared, _) = service();
+
+        service
+            .delete_transaction(&cleared)
+            .await
+            .unwrap_or_else(|e| panic!("{}", e));
+
+        assert!(service
+            .find_transaction(&cleared)
+            .await
+            .unwrap_or_else(|e| panic!("{}", e))
+            .is_none());
+    }
+}
@@ -0,0 +1,343 @@
+use std::{
+    collections::HashMap,
+    io,
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::{Duration, SystemTime},
+};
+
+use chrono::Utc;
+use serde_json::json;
+use tokio::{fs, io::AsyncReadExt, sync::mpsc, sync::RwLock};
+use tracing::{error, info};
+
+use crate::{
+    database::postgres::Postgres,
+    service::{
+        account::AccountService,
+        batch::{BatchService, ImportSession},
+        parse::{
+            report::{Policy, Report},
+            Service,
+        },
+        profile::ProfileService,
+        transaction::TransactionService,
+    },
+};
+
+// how often the directory is checked for new files
+const POLL_SECONDS: u64 = 5;
+
+// how much of a file is read at a time
+const READ_BYTES: usize = 64 * 1024;
+
+// chunks of a file read ahead of the parser
+const READ_CHUNKS: usize = 4;
+
+const PROCESSED_DIR: &str = "processed";
+const FAILED_DIR: &str = "failed";
+
+// imports the statement files dropped into a directory. the format and profile
+// of each file are detected, it is then moved into processed/ or, when it could
+// not be imported, failed/ next to a <file>.report.json describing the import.
+// a file is only picked up once its size has stopped changing between checks,
+// so files still being copied in are left alone. hidden files are ignored.
+// transactions go into the account named after the one their statement names
+pub struct Watcher {
+    parse_service: Arc<RwLock<Service>>,
+    profile_service: Arc<RwLock<ProfileService<Postgres>>>,
+    directory: PathBuf,
+}
+
+impl Watcher {
+    pub async fn new(directory: PathBuf) -> Result<Self, String> {
+        let new_pg_service = Postgres::connect_default()
+            .await
+            .map_err(|e| format!("Failed to connect to database: {}", e))?;
+        let a_service = Arc::new(RwLock::new(AccountService::new(new_pg_service.clone())));
+        let t_service = Arc::new(RwLock::new(TransactionService::new(new_pg_service.clone())));
+        let p_service = Arc::new(RwLock::new(ProfileService::new(new_pg_service.clone())));
+        let b_service = Arc::new(RwLock::new(BatchService::new(new_pg_service)));
+
+        Ok(Self {
+            parse_service: Arc::new(RwLock::new(Service::new(a_service, b_service, t_service))),
+            profile_service: p_service,
+            directory,
+        })
+    }
+
+    pub async fn start(&self) -> Result<(), String> {
+        for dir in [PROCESSED_DIR, FAILED_DIR] {
+            fs::create_dir_all(self.directory.join(dir))
+                .await
+                .map_err(|e| format!("Unable to create {} directory: {}", dir, e))?;
+        }
+
+        info!("Watching {} for statements", self.directory.display());
+
+        // the size and modified time of each file when it was last seen
+        let mut seen: HashMap<PathBuf, (u64, SystemTime)> = HashMap::new();
+
+        loop {
+            let files = files(&self.directory)
+                .await
+                .map_err(|e| format!("Unable to read {}: {}", self.directory.display(), e))?;
+
+            for path in ready(&seen, &files) {
+                self.import_file(path).await;
+            }
+
+            seen = files;
+            tokio::time::sleep(Duration::from_secs(POLL_SECONDS)).await;
+        }
+    }
+
+    async fn import_file(&self, path: &Path) {
+        let filename = path
+            .file_name()
+            .map(|f| f.to_string_lossy().to_string())
+            .unwrap_or_default();
+        info!("Importing {}", filename);
+
+        let (dir, report) = match self.import(path, &filename).await {
+            Ok(report) => {
+                info!(
+                    "Imported {}: {} imported, {} failed, {} duplicates",
+                    filename, report.imported, report.failed, report.duplicates
+                );
+                (PROCESSED_DIR, json!(report))
+            }
+            Err(e) => {
+                error!("Unable to import {}: {}", filename, e);
+                (FAILED_DIR, json!({ "file": filename, "error": e }))
+            }
+        };
+
+        if let Err(e) = move_file(&self.directory, path, &filename, dir, report).await {
+            error!("Unable to move {} to {}: {}", filename, dir, e);
+        }
+    }
+
+    async fn import(&self, path: &Path, filename: &str) -> Result<Report, String> {
+        let profiles = self
+            .profile_service
+            .read()
+            .await
+            .find_profiles()
+            .await
+            .map_err(|e| e.to_string())?;
+
+        let ps = self.parse_service.read().await;
+        let mut session = ps.begin_import().await.map_err(|e| e.to_string())?;
+
+        let (sender, receiver) = mpsc::channel(READ_CHUNKS);
+        let read = async move {
+            let mut file = fs::File::open(path).await?;
+            loop {
+                let mut buffer = vec![0; READ_BYTES];
+                let read = file.read(&mut buffer).await?;
+                if read == 0 {
+                    break;
+                }
+
+                buffer.truncate(read);
+                // the import has stopped reading, its error is returned instead
+                if sender.send(buffer).await.is_err() {
+                    break;
+                }
+            }
+
+            Ok::<(), io::Error>(())
+        };
+
+        // per chunk events are only used by import jobs
+        let mut events = |_| ();
+        let (read, report) = tokio::join!(
+            read,
+            ps.import_stream(
+                &mut session,
+                Some(filename.to_string()),
+                None,
+                None,
+                &profiles,
+                Policy::default(),
+                receiver,
+                &mut events,
+            )
+        );
+        read.map_err(|e| e.to_string())?;
+        let report = report.map_err(|e| e.to_string())?;
+
+        session.commit().await.map_err(|e| e.to_string())?;
+
+        Ok(report)
+    }
+}
+
+// the files that have not changed since they were last seen
+fn ready<'a>(
+    seen: &HashMap<PathBuf, (u64, SystemTime)>,
+    files: &'a HashMap<PathBuf, (u64, SystemTime)>,
+) -> Vec<&'a Path> {
+    files
+        .iter()
+        .filter(|(path, state)| seen.get(*path) == Some(state))
+        .map(|(path, _)| path.as_path())
+        .collect()
+}
+
+// the files in the directory with their size and modified time
+async fn files(directory: &Path) -> io::Result<HashMap<PathBuf, (u64, SystemTime)>> {
+    let mut files = HashMap::new();
+    let mut entries = fs::read_dir(directory).await?;
+
+    while let Some(entry) = entries.next_entry().await? {
+        let hidden = entry.file_name().to_string_lossy().starts_with('.');
+        let metadata = entry.metadata().await?;
+        if hidden || !metadata.is_file() {
+            continue;
+        }
+
+        files.insert(entry.path(), (metadata.len(), metadata.modified()?));
+    }
+
+    Ok(files)
+}
+
+// a file of the same name imported before is kept, the new one gets the
+// time it was moved added to its name
+async fn move_file(
+    directory: &Path,
+    path: &Path,
+    filename: &str,
+    dir: &str,
+    report: serde_json::Value,
+) -> io::Result<()> {
+    let mut target = directory.join(dir).join(filename);
+    if fs::try_exists(&target).await? {
+        let stamp = Utc::now().format("%Y%m%d%H%M%S");
+        target = directory.join(dir).join(format!("{}-{}", stamp, filename));
+    }
+
+    fs::rename(path, &target).await?;
+
+    let mut sidecar = target.into_os_string();
+    sidecar.push(".report.json");
+    let contents = serde_json::to_vec_pretty(&report).map_err(io::Error::other)?;
+    fs::write(sidecar, contents).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // an empty directory removed again when the test is done
+    struct TempDir(PathBuf);
+
+    impl TempDir {
+        async fn new() -> Self {
+            let path = std::env::temp_dir().join(format!("watch-{}", uuid::Uuid::new_v4()));
+            fs::create_dir_all(path.join(PROCESSED_DIR))
+                .await
+                .unwrap_or_else(|e| panic!("{}", e));
+            Self(path)
+        }
+    }
+
+    impl Drop for TempDir {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_dir_all(&self.0);
+        }
+    }
+
+    async fn write(path: &Path, contents: &str) {
+        fs::write(path, contents)
+            .await
+            .unwrap_or_else(|e| panic!("{}", e));
+    }
+
+    async fn read(path: &Path) -> String {
+        fs::read_to_string(path)
+            .await
+            .unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    #[tokio::test]
+    async fn hidden_files_and_directories_are_not_listed() {
+        let dir = TempDir::new().await;
+        write(&dir.0.join("monzo.csv"), "Date,Amount").await;
+        write(&dir.0.join(".monzo.csv.part"), "Date").await;
+
+        let files = files(&dir.0).await.unwrap_or_else(|e| panic!("{}", e));
+
+        assert_eq!(files.len(), 1);
+        assert_eq!(files[&dir.0.join("monzo.csv")].0, 11);
+    }
+
+    #[test]
+    fn a_file_is_ready_once_it_stops_changing() {
+        let now = SystemTime::now();
+        let later = now + Duration::from_secs(POLL_SECONDS);
+        let done = PathBuf::from("done.csv");
+        let copying = PathBuf::from("copying.csv");
+        let new = PathBuf::from("new.csv");
+        let seen = HashMap::from([(done.clone(), (10, now)), (copying.clone(), (10, now))]);
+        let files = HashMap::from([
+            (done.clone(), (10, now)),
+            (copying, (20, later)),
+            (new, (10, now)),
+        ]);
+
+        assert_eq!(ready(&seen, &files), [done.as_path()]);
+    }
+
+    #[tokio::test]
+    async fn a_moved_file_gets_its_report_next_to_it() {
+        let dir = TempDir::new().await;
+        let path = dir.0.join("monzo.csv");
+        write(&path, "Date,Amount").await;
+
+        move_file(
+            &dir.0,
+            &path,
+            "monzo.csv",
+            PROCESSED_DIR,
+            json!({ "imported": 1 }),
+        )
+        .await
+        .unwrap_or_else(|e| panic!("{}", e));
+
+        let processed = dir.0.join(PROCESSED_DIR);
+        assert!(!fs::try_exists(&path).await.unwrap_or(true));
+        assert_eq!(read(&processed.join("monzo.csv")).await, "Date,Amount");
+        let report = read(&processed.join("monzo.csv.report.json")).await;
+        assert_eq!(
+            serde_json::from_str::<serde_json::Value>(&report).ok(),
+            Some(json!({ "imported": 1 }))
+        );
+    }
+
+    #[tokio::test]
+    async fn a_file_imported_before_is_kept() {
+        let dir = TempDir::new().await;
+        let processed = dir.0.join(PROCESSED_DIR);
+        write(&processed.join("monzo.csv"), "first").await;
+        let path = dir.0.join("monzo.csv");
+        write(&path, "second").await;
+
+        move_file(&dir.0, &path, "monzo.csv", PROCESSED_DIR, json!({}))
+            .await
+            .unwrap_or_else(|e| panic!("{}", e));
+
+        let moved = files(&processed).await.unwrap_or_else(|e| panic!("{}", e));
+        let renamed: Vec<_> = moved
+            .keys()
+            .filter_map(|p| p.file_name())
+            .map(|f| f.to_string_lossy().to_string())
+            .filter(|f| f.ends_with("-monzo.csv"))
+            .collect();
+        assert_eq!(read(&processed.join("monzo.csv")).await, "first");
+        assert_eq!(renamed.len(), 1);
+        assert_eq!(read(&processed.join(&renamed[0])).await, "second");
+    }
+}
@@ -1,66 +1,172 @@
 use std::sync::Arc;
 
 use axum::{
-    extract::{MatchedPath, Multipart, Path},
+    extract::{multipart::Field, DefaultBodyLimit, MatchedPath, Multipart, Path, Query},
     http::{Request, StatusCode},
-    response::{IntoResponse, Response},
-    routing::{delete, get, post},
+    response::{
+        sse::{self, KeepAlive, Sse},
+        IntoResponse, Response,
+    },
+    routing::{delete, get, post, put},
     Extension, Json, Router,
 };
+use chrono::NaiveDate;
+use serde::Deserialize;
 use serde_json::{json, Value};
-use tokio::{signal, sync::RwLock};
+use tokio::{
+    signal,
+    sync::{broadcast, Notify, RwLock},
+};
+use tokio_stream::{wrappers::ReceiverStream, Stream, StreamExt};
 use tower_http::trace::TraceLayer;
 use tracing::info_span;
-use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt, EnvFilter};
 
 use crate::{
-    database::{base::DatabaseInit, postgres::Postgres},
+    database::postgres::Postgres,
+    models::{
+        account::{Account, CreateAccount},
+        balance::{CreateBalanceAnchor, Interval},
+        job::ImportJobFile,
+        reconciliation::{ClearTransactions, CreateReconciliation},
+        transaction::Transaction,
+    },
     service::{
-        parse::{Config, Service},
-        transaction::TransactionService,
+        account::{AccountError, AccountService},
+        balance::{BalanceError, BalanceService},
+        batch::{BatchError, BatchService},
+        fx::{FxError, FxService},
+        job::{JobError, JobService, JobUpload},
+        parse::{
+            detect,
+            report::{Policy, Summary},
+            Config, ParseError, Service,
+        },
+        profile::{ProfileError, ProfileService},
+        reconciliation::{ReconciliationError, ReconciliationService},
+        transaction::{TransactionError, TransactionService},
     },
+    worker::{follow, JobEvents, Worker, JOB_EVENTS},
 };
 
+// uploaded files are saved for their import job in chunks of this size
+const JOB_CHUNK_BYTES: usize = 1024 * 1024;
+
+// the upload body limit is lifted so files can be streamed into import jobs,
+// anything read into memory is capped instead. previews hold every file sent
+const PREVIEW_BYTES: usize = 16 * 1024 * 1024;
+const TEXT_FIELD_BYTES: usize = 1024;
+
 pub struct Server {
     parse_service: Arc<RwLock<Service>>,
     transactions_service: Arc<RwLock<TransactionService<Postgres>>>,
+    profile_service: Arc<RwLock<ProfileService<Postgres>>>,
+    batch_service: Arc<RwLock<BatchService<Postgres>>>,
+    job_service: Arc<RwLock<JobService<Postgres>>>,
+    account_service: Arc<RwLock<AccountService<Postgres>>>,
+    balance_service: Arc<RwLock<BalanceService<Postgres>>>,
+    fx_service: Arc<RwLock<FxService<Postgres>>>,
+    reconciliation_service: Arc<RwLock<ReconciliationService<Postgres>>>,
+    // notified when an upload queues an import job
+    queued: Arc<Notify>,
+    events: JobEvents,
 }
 
 impl Server {
-    pub async fn new() -> Self {
-        let pg_connection_string = env!("DATABASE_URL");
-        println!("Using connection string: {}", pg_connection_string);
-
-        let mut new_pg_service = Postgres::new(pg_connection_string);
-        match new_pg_service.connect().await {
-            Ok(_) => (),
-            Err(e) => {
-                // FIX ME - this should be a proper error
-                panic!("Failed to connect to database: {}", e);
-            }
-        }
-        let t_service = Arc::new(RwLock::new(TransactionService::new(new_pg_service)));
+    pub async fn new() -> Result<Self, String> {
+        let new_pg_service = Postgres::connect_default()
+            .await
+            .map_err(|e| format!("Failed to connect to database: {}", e))?;
+        let a_service = Arc::new(RwLock::new(AccountService::new(new_pg_service.clone())));
+        let bal_service = Arc::new(RwLock::new(BalanceService::new(new_pg_service.clone())));
+        let t_service = Arc::new(RwLock::new(TransactionService::new(new_pg_service.clone())));
+        let p_service = Arc::new(RwLock::new(ProfileService::new(new_pg_service.clone())));
+        let b_service = Arc::new(RwLock::new(BatchService::new(new_pg_service.clone())));
+        let j_service = Arc::new(RwLock::new(JobService::new(new_pg_service.clone())));
+        let f_service = Arc::new(RwLock::new(FxService::new(new_pg_service.clone())));
+        let r_service = Arc::new(RwLock::new(ReconciliationService::new(new_pg_service)));
 
-        Self {
-            parse_service: Arc::new(RwLock::new(Service::new(t_service.clone()))),
+        Ok(Self {
+            parse_service: Arc::new(RwLock::new(Service::new(
+                a_service.clone(),
+                b_service.clone(),
+                t_service.clone(),
+            ))),
             transactions_service: t_service,
-        }
+            profile_service: p_service,
+            batch_service: b_service,
+            job_service: j_service,
+            account_service: a_service,
+            balance_service: bal_service,
+            fx_service: f_service,
+            reconciliation_service: r_service,
+            queued: Arc::new(Notify::new()),
+            events: broadcast::channel(JOB_EVENTS).0,
+        })
     }
 
     pub async fn start(&self) -> Result<(), String> {
-        tracing_subscriber::registry()
-            .with(EnvFilter::new("info"))
-            .with(tracing_subscriber::fmt::layer())
-            .init();
+        let worker = Worker::new(
+            self.parse_service.clone(),
+            self.profile_service.clone(),
+            self.job_service.clone(),
+            self.queued.clone(),
+            self.events.clone(),
+        );
+        tokio::spawn(worker.run());
 
         let app = Router::new()
             .route("/", get("Ok"))
-            .route("/upload", post(upload))
+            // uploads are saved as they are received, so historical exports of any
+            // size are accepted. what upload reads into memory has its own limits
+            .route("/upload", post(upload).layer(DefaultBodyLimit::disable()))
+            .route("/previews/:token", post(commit_preview))
+            .route("/previews/:token", delete(discard_preview))
             .route("/transactions/:id", get(get_transaction))
             .route("/transactions", get(get_transactions))
             .route("/transactions/:id", delete(delete_transaction))
+            .route("/profiles", get(get_profiles))
+            .route("/profiles", post(create_profile))
+            .route("/profiles/:name", get(get_profile))
+            .route("/profiles/:name", put(update_profile))
+            .route("/profiles/:name", delete(delete_profile))
+            .route("/batches", get(get_batches))
+            .route("/batches/:id", get(get_batch))
+            .route("/batches/:id", delete(rollback_batch))
+            .route("/batches/:id/transactions", get(get_batch_transactions))
+            .route("/imports/:id", get(get_import_job))
+            .route("/imports/:id/events", get(get_import_events))
+            .route("/accounts", get(get_accounts))
+            .route("/accounts", post(create_account))
+            .route("/accounts/:id", get(get_account))
+            .route("/accounts/:id", put(update_account))
+            .route("/accounts/:id", delete(delete_account))
+            .route("/accounts/:id/balances", get(get_balances))
+            .route("/accounts/:id/transactions", get(get_account_transactions))
+            .route("/accounts/:id/anchors", get(get_anchors))
+            .route("/accounts/:id/anchors", post(save_anchor))
+            .route("/accounts/:id/anchors/:date", delete(delete_anchor))
+            .route("/accounts/:id/reconciliations", get(get_reconciliations))
+            .route("/accounts/:id/reconciliations", post(create_reconciliation))
+            .route("/reconciliations/:id", get(get_reconciliation))
+            .route("/reconciliations/:id", delete(delete_reconciliation))
+            .route(
+                "/reconciliations/:id/transactions",
+                post(clear_transactions),
+            )
+            .route("/reconciliations/:id/finish", post(finish_reconciliation))
+            .route("/fx_rates", get(get_fx_rates))
+            .route("/fx_rates", post(import_fx_rates))
             .layer(Extension(self.parse_service.clone()))
             .layer(Extension(self.transactions_service.clone()))
+            .layer(Extension(self.profile_service.clone()))
+            .layer(Extension(self.batch_service.clone()))
+            .layer(Extension(self.job_service.clone()))
+            .layer(Extension(self.account_service.clone()))
+            .layer(Extension(self.balance_service.clone()))
+            .layer(Extension(self.fx_service.clone()))
+            .layer(Extension(self.reconciliation_service.clone()))
+            .layer(Extension(self.queued.clone()))
+            .layer(Extension(self.events.clone()))
             .layer(
                 TraceLayer::new_for_http().make_span_with(|request: &Request<_>| {
                     let matched_path = request.extensions().get().map(MatchedPath::as_str);
@@ -113,6 +219,8 @@ enum ServerError {
     ParseError(String),
     ServiceError(String),
     NoValue(String),
+    InvalidValue(String),
+    TooLarge(String),
 }
 
 impl IntoResponse for ServerError {
@@ -122,43 +230,392 @@ impl IntoResponse for ServerError {
             ServerError::ParseError(c) => (StatusCode::INTERNAL_SERVER_ERROR, c).into_response(),
             ServerError::ServiceError(c) => (StatusCode::INTERNAL_SERVER_ERROR, c).into_response(),
             ServerError::NoValue(c) => (StatusCode::BAD_REQUEST, c).into_response(),
+            ServerError::InvalidValue(c) => (StatusCode::UNPROCESSABLE_ENTITY, c).into_response(),
+            ServerError::TooLarge(c) => (StatusCode::PAYLOAD_TOO_LARGE, c).into_response(),
+        }
+    }
+}
+
+impl From<ParseError> for ServerError {
+    fn from(e: ParseError) -> Self {
+        match e {
+            ParseError::DetectionError(_) => ServerError::InvalidValue(e.to_string()),
+            ParseError::ConfigError(_) => ServerError::NoValue(e.to_string()),
+            ParseError::LimitError(_) => ServerError::TooLarge(e.to_string()),
+            _ => ServerError::ParseError(e.to_string()),
+        }
+    }
+}
+
+impl From<BatchError> for ServerError {
+    fn from(e: BatchError) -> Self {
+        match e {
+            BatchError::InvalidError(_) => ServerError::InvalidValue(e.to_string()),
+            _ => ServerError::ServiceError(e.to_string()),
+        }
+    }
+}
+
+impl From<TransactionError> for ServerError {
+    fn from(e: TransactionError) -> Self {
+        match e {
+            TransactionError::InvalidError(_) => ServerError::InvalidValue(e.to_string()),
+            _ => ServerError::ServiceError(e.to_string()),
+        }
+    }
+}
+
+impl From<JobError> for ServerError {
+    fn from(e: JobError) -> Self {
+        ServerError::ServiceError(e.to_string())
+    }
+}
+
+impl From<AccountError> for ServerError {
+    fn from(e: AccountError) -> Self {
+        match e {
+            AccountError::InvalidError(_) => ServerError::InvalidValue(e.to_string()),
+            _ => ServerError::ServiceError(e.to_string()),
+        }
+    }
+}
+
+impl From<BalanceError> for ServerError {
+    fn from(e: BalanceError) -> Self {
+        match e {
+            BalanceError::InvalidError(_) => ServerError::InvalidValue(e.to_string()),
+            _ => ServerError::ServiceError(e.to_string()),
+        }
+    }
+}
+
+impl From<ReconciliationError> for ServerError {
+    fn from(e: ReconciliationError) -> Self {
+        match e {
+            ReconciliationError::InvalidError(_) => ServerError::InvalidValue(e.to_string()),
+            _ => ServerError::ServiceError(e.to_string()),
+        }
+    }
+}
+
+impl From<FxError> for ServerError {
+    fn from(e: FxError) -> Self {
+        match e {
+            FxError::InvalidError(_) => ServerError::InvalidValue(e.to_string()),
+            _ => ServerError::ServiceError(e.to_string()),
+        }
+    }
+}
+
+impl From<ProfileError> for ServerError {
+    fn from(e: ProfileError) -> Self {
+        match e {
+            ProfileError::InvalidError(_) => ServerError::InvalidValue(e.to_string()),
+            _ => ServerError::ServiceError(e.to_string()),
         }
     }
 }
 
+// expects one or more statement files and an optional `profile` text field
+// naming a saved or built in bank import profile, e.g. Monzo. without a profile
+// the format of each file, and for csv files the profile, is detected from its
+// name and content. an `account` field with the id or name of an account
+// imports the files into it, without one the transactions go into the account
+// named after the statement's, which is created if there is none. an `on_error`
+// field of skip_invalid (the default) or reject_file decides what happens to
+// files with rows that cannot be read. the text fields only apply to the files
+// sent after them. the files are saved as an import job and imported in the
+// background, the 202 response holds the job to follow at /imports/:id. with
+// `preview` set to true nothing is saved, the response lists the transactions
+// and a token to commit them with at /previews/:token
 async fn upload(
     Extension(parse_service): Extension<Arc<RwLock<Service>>>,
+    Extension(profile_service): Extension<Arc<RwLock<ProfileService<Postgres>>>>,
+    Extension(account_service): Extension<Arc<RwLock<AccountService<Postgres>>>>,
+    Extension(job_service): Extension<Arc<RwLock<JobService<Postgres>>>>,
+    Extension(queued): Extension<Arc<Notify>>,
     mut multipart: Multipart,
-) -> Result<StatusCode, ServerError> {
-    let ps = parse_service.write().await;
-    while let Some(field) = multipart
+) -> Result<(StatusCode, Json<Value>), ServerError> {
+    let mut profile_name: Option<String> = None;
+    let mut account: Option<Account> = None;
+    let mut policy = Policy::default();
+    let mut preview = false;
+
+    let mut config: Option<Config> = None;
+    let mut profiles: Option<Vec<Config>> = None;
+    let mut imports = Vec::new();
+    let mut upload = None;
+    let mut position = 0;
+    let mut previewed = 0;
+
+    let js = job_service.read().await;
+
+    while let Some(mut field) = multipart
         .next_field()
         .await
         .map_err(|e| ServerError::MultipartError(e.to_string()))?
     {
-        let data = field
-            .text()
-            .await
-            .map_err(|e| ServerError::MultipartError(e.to_string()))?;
+        let name = field.name().map(|n| n.to_string());
+        let filename = field.file_name().map(|f| f.to_string());
+
+        match name.as_deref() {
+            Some("profile") | Some("account") | Some("on_error") | Some("preview") => {
+                let data = read_text(&mut field, TEXT_FIELD_BYTES).await?;
+
+                match name.as_deref() {
+                    Some("profile") => {
+                        profile_name = Some(data.trim().to_string());
+                        config = None;
+                    }
+                    Some("account") => {
+                        account = Some(find_account(&account_service, data.trim()).await?)
+                    }
+                    Some("on_error") => policy = data.parse()?,
+                    // files before it have already been saved
+                    _ if upload.is_some() => {
+                        return Err(ServerError::MultipartError(
+                            "preview must be sent before the files".to_string(),
+                        ))
+                    }
+                    _ => preview = data.trim() == "true",
+                }
+                continue;
+            }
+            _ => (),
+        }
+
+        // an unknown profile is reported now rather than when the job runs
+        if config.is_none() {
+            if let Some(name) = &profile_name {
+                config = Some(find_config(&profile_service, name).await?);
+            }
+        }
+
+        // previews list every transaction so the files are read whole
+        if preview {
+            if config.is_none() && profiles.is_none() {
+                profiles = Some(profile_service.read().await.find_profiles().await?);
+            }
 
-        let config = Config {
-            name: "Amex".to_string(),
-            date_position: 0,
-            amount_position: 4,
-            description_position: 1,
+            let data = read_text(&mut field, PREVIEW_BYTES - previewed)
+                .await
+                .map_err(|e| match e {
+                    ServerError::TooLarge(_) => ServerError::TooLarge(format!(
+                        "Previews are limited to {} bytes, upload without preview to import larger files",
+                        PREVIEW_BYTES
+                    )),
+                    e => e,
+                })?;
+            previewed += data.len();
+
+            let (format, file_config) = match &config {
+                Some(c) => (detect::format(filename.as_deref(), &data), Some(c.clone())),
+                None => {
+                    let profiles = profiles.as_deref().unwrap_or_default();
+                    let detected = detect::statement(filename.as_deref(), &data, profiles)
+                        .map_err(ParseError::from)?;
+                    (detected.format, detected.profile)
+                }
+            };
+
+            let ps = parse_service.read().await;
+            let mut import = ps.parse_data(
+                format,
+                file_config.as_ref(),
+                data,
+                policy,
+                account.as_ref().map(|a| a.id),
+            )?;
+            import.report.file = filename;
+            imports.push(import);
+            continue;
+        }
+
+        let upload = match &mut upload {
+            Some(upload) => upload,
+            None => upload.insert(js.begin_job().await?),
         };
 
-        ps.parse_data(config, data)
+        upload
+            .add_file(&ImportJobFile {
+                position,
+                filename,
+                profile: profile_name.clone(),
+                on_error: policy.to_string(),
+                account_id: account.as_ref().map(|a| a.id),
+            })
+            .await
+            .map_err(|e| ServerError::ServiceError(e.to_string()))?;
+
+        let mut sequence = 0;
+        let mut buffer = Vec::new();
+        while let Some(chunk) = field
+            .chunk()
             .await
-            .map_err(|e| ServerError::ParseError(e.to_string()))?;
+            .map_err(|e| ServerError::MultipartError(e.to_string()))?
+        {
+            buffer.extend_from_slice(&chunk);
+
+            if buffer.len() >= JOB_CHUNK_BYTES {
+                upload
+                    .add_chunk(position, sequence, &buffer)
+                    .await
+                    .map_err(|e| ServerError::ServiceError(e.to_string()))?;
+                buffer.clear();
+                sequence += 1;
+            }
+        }
+
+        if !buffer.is_empty() {
+            upload
+                .add_chunk(position, sequence, &buffer)
+                .await
+                .map_err(|e| ServerError::ServiceError(e.to_string()))?;
+        }
+
+        position += 1;
+    }
+
+    if preview {
+        let ps = parse_service.read().await;
+        return Ok((StatusCode::OK, Json(json!(ps.preview(imports).await?))));
+    }
+
+    let Some(upload) = upload else {
+        return Err(ServerError::MultipartError(
+            "No statement files were uploaded".to_string(),
+        ));
+    };
+
+    let job = upload
+        .commit()
+        .await
+        .map_err(|e| ServerError::ServiceError(e.to_string()))?;
+    queued.notify_one();
+
+    Ok((StatusCode::ACCEPTED, Json(json!(job))))
+}
+
+async fn commit_preview(
+    Path(token): Path<String>,
+    Extension(parse_service): Extension<Arc<RwLock<Service>>>,
+) -> Result<Json<Summary>, ServerError> {
+    let ps = parse_service.read().await;
+
+    match ps.commit_preview(&token).await? {
+        Some(summary) => Ok(Json(summary)),
+        None => Err(ServerError::NoValue(format!(
+            "Unable to find preview, it may have expired: {}",
+            token
+        ))),
+    }
+}
+
+async fn discard_preview(
+    Path(token): Path<String>,
+    Extension(parse_service): Extension<Arc<RwLock<Service>>>,
+) -> Result<StatusCode, ServerError> {
+    let ps = parse_service.read().await;
+
+    if !ps.discard_preview(&token).await {
+        return Err(ServerError::NoValue(format!(
+            "Unable to find preview: {}",
+            token
+        )));
     }
 
     Ok(StatusCode::OK)
 }
 
+// reads a field into memory, refusing any longer than the limit
+async fn read_text(field: &mut Field<'_>, limit: usize) -> Result<String, ServerError> {
+    let name = field.name().unwrap_or_default().to_string();
+
+    let mut data = Vec::new();
+    while let Some(chunk) = field
+        .chunk()
+        .await
+        .map_err(|e| ServerError::MultipartError(e.to_string()))?
+    {
+        if data.len() + chunk.len() > limit {
+            return Err(ServerError::TooLarge(format!(
+                "The {} field is longer than {} bytes",
+                name, limit
+            )));
+        }
+        data.extend_from_slice(&chunk);
+    }
+
+    String::from_utf8(data)
+        .map_err(|e| ServerError::MultipartError(format!("The {} field: {}", name, e)))
+}
+
+async fn find_config(
+    profile_service: &RwLock<ProfileService<Postgres>>,
+    name: &str,
+) -> Result<Config, ServerError> {
+    let ps = profile_service.read().await;
+
+    ps.find_profile(name)
+        .await?
+        .ok_or_else(|| ServerError::NoValue(format!("Unable to find profile: {}", name)))
+}
+
+// the read apis take a `currency` query, e.g. ?currency=EUR, to present each
+// amount in that currency as well, along with the rate used
+#[derive(Deserialize)]
+struct CurrencyQuery {
+    currency: Option<String>,
+}
+
+// the transactions as json, converted when a currency was asked for
+async fn present(
+    fx_service: &RwLock<FxService<Postgres>>,
+    transactions: Vec<Transaction>,
+    query: &CurrencyQuery,
+) -> Result<Vec<Value>, ServerError> {
+    match &query.currency {
+        Some(currency) => {
+            let fs = fx_service.read().await;
+            let converted = fs.convert(transactions, currency).await?;
+
+            Ok(converted.iter().map(|t| json!(t)).collect())
+        }
+        None => Ok(transactions.iter().map(|t| json!(t)).collect()),
+    }
+}
+
+// an account can be chosen by its id or its name, closed accounts cannot be
+// imported into
+async fn find_account(
+    account_service: &RwLock<AccountService<Postgres>>,
+    key: &str,
+) -> Result<Account, ServerError> {
+    let accs = account_service.read().await;
+
+    let account = match accs.find_account(key).await? {
+        Some(a) => Some(a),
+        None => accs.find_accounts_named(&[key.to_string()]).await?.pop(),
+    };
+
+    match account {
+        Some(a) if a.closed => Err(ServerError::InvalidValue(format!(
+            "Account {} is closed",
+            a.name
+        ))),
+        Some(a) => Ok(a),
+        None => Err(ServerError::NoValue(format!(
+            "Unable to find account: {}",
+            key
+        ))),
+    }
+}
+
 async fn get_transaction(
     Path(id): Path<String>,
+    Query(query): Query<CurrencyQuery>,
     Extension(transaction_service): Extension<Arc<RwLock<TransactionService<Postgres>>>>,
+    Extension(fx_service): Extension<Arc<RwLock<FxService<Postgres>>>>,
 ) -> Result<Json<Value>, ServerError> {
     let ts = transaction_service.read().await;
 
@@ -169,20 +626,23 @@ async fn get_transaction(
 
     match possible_transaction {
         Some(t) => {
-            return Ok(Json(json!(t)));
+            let mut presented = present(&fx_service, vec![t], &query).await?;
+            Ok(Json(presented.remove(0)))
         }
         None => {
             println!("Unable to find transaction for ID: {}", id);
-            return Err(ServerError::NoValue(format!(
+            Err(ServerError::NoValue(format!(
                 "Unable to find transaction for ID: {}",
                 id
-            )));
+            )))
         }
-    };
+    }
 }
 
 async fn get_transactions(
+    Query(query): Query<CurrencyQuery>,
     Extension(transaction_service): Extension<Arc<RwLock<TransactionService<Postgres>>>>,
+    Extension(fx_service): Extension<Arc<RwLock<FxService<Postgres>>>>,
 ) -> Result<Json<Value>, ServerError> {
     let ts = transaction_service.read().await;
 
@@ -191,7 +651,9 @@ async fn get_transactions(
         .await
         .map_err(|e| ServerError::ServiceError(e.to_string()))?;
 
-    return Ok(Json(json!(transactions)));
+    Ok(Json(json!(
+        present(&fx_service, transactions, &query).await?
+    )))
 }
 
 async fn delete_transaction(
@@ -200,17 +662,490 @@ async fn delete_transaction(
 ) -> Result<StatusCode, ServerError> {
     let ts = transaction_service.read().await;
 
-    let has_deleted = ts
-        .delete_transaction(&id)
-        .await
-        .map_err(|e| ServerError::ServiceError(e.to_string()))?;
+    ts.delete_transaction(&id).await?;
+
+    Ok(StatusCode::OK)
+}
+
+async fn get_profiles(
+    Extension(profile_service): Extension<Arc<RwLock<ProfileService<Postgres>>>>,
+) -> Result<Json<Value>, ServerError> {
+    let ps = profile_service.read().await;
+
+    let profiles = ps.find_profiles().await?;
+
+    Ok(Json(json!(profiles)))
+}
+
+async fn get_profile(
+    Path(name): Path<String>,
+    Extension(profile_service): Extension<Arc<RwLock<ProfileService<Postgres>>>>,
+) -> Result<Json<Value>, ServerError> {
+    let config = find_config(&profile_service, &name).await?;
+
+    Ok(Json(json!(config)))
+}
+
+async fn create_profile(
+    Extension(profile_service): Extension<Arc<RwLock<ProfileService<Postgres>>>>,
+    Json(profile): Json<Config>,
+) -> Result<(StatusCode, Json<Value>), ServerError> {
+    let ps = profile_service.read().await;
+
+    let profile = ps.create_profile(profile).await?;
+
+    Ok((StatusCode::CREATED, Json(json!(profile))))
+}
+
+async fn update_profile(
+    Path(name): Path<String>,
+    Extension(profile_service): Extension<Arc<RwLock<ProfileService<Postgres>>>>,
+    Json(profile): Json<Config>,
+) -> Result<Json<Value>, ServerError> {
+    let ps = profile_service.read().await;
+
+    match ps.update_profile(&name, profile).await? {
+        Some(p) => Ok(Json(json!(p))),
+        None => Err(ServerError::NoValue(format!(
+            "Unable to find profile: {}",
+            name
+        ))),
+    }
+}
+
+async fn delete_profile(
+    Path(name): Path<String>,
+    Extension(profile_service): Extension<Arc<RwLock<ProfileService<Postgres>>>>,
+) -> Result<StatusCode, ServerError> {
+    let ps = profile_service.read().await;
+
+    if !ps.delete_profile(&name).await? {
+        return Err(ServerError::NoValue(format!(
+            "Unable to find profile: {}",
+            name
+        )));
+    }
+
+    Ok(StatusCode::OK)
+}
+
+async fn get_batches(
+    Extension(batch_service): Extension<Arc<RwLock<BatchService<Postgres>>>>,
+) -> Result<Json<Value>, ServerError> {
+    let bs = batch_service.read().await;
+
+    let batches = bs.find_batches().await?;
+
+    Ok(Json(json!(batches)))
+}
+
+async fn get_batch(
+    Path(id): Path<String>,
+    Extension(batch_service): Extension<Arc<RwLock<BatchService<Postgres>>>>,
+) -> Result<Json<Value>, ServerError> {
+    let bs = batch_service.read().await;
+
+    match bs.find_batch(&id).await? {
+        Some(b) => Ok(Json(json!(b))),
+        None => Err(ServerError::NoValue(format!(
+            "Unable to find import batch: {}",
+            id
+        ))),
+    }
+}
+
+async fn get_import_job(
+    Path(id): Path<String>,
+    Extension(job_service): Extension<Arc<RwLock<JobService<Postgres>>>>,
+) -> Result<Json<Value>, ServerError> {
+    let js = job_service.read().await;
+
+    match js.find_job(&id).await? {
+        Some(j) => Ok(Json(json!(j))),
+        None => Err(ServerError::NoValue(format!(
+            "Unable to find import job: {}",
+            id
+        ))),
+    }
+}
+
+// streams the events of an import job as server-sent events until it is done
+// or failed: "progress" with a file's counts after each chunk of rows is saved,
+// "errors" and "duplicates" with the chunk's rows that were not imported, then
+// "done" with the job's counts or "failed" with its error. a finished job only
+// sends its final event
+async fn get_import_events(
+    Path(id): Path<String>,
+    Extension(job_service): Extension<Arc<RwLock<JobService<Postgres>>>>,
+    Extension(events): Extension<JobEvents>,
+) -> Result<Sse<impl Stream<Item = Result<sse::Event, axum::Error>>>, ServerError> {
+    // subscribed first so the job cannot finish unseen
+    let receiver = events.subscribe();
+
+    let js = job_service.read().await;
+    let job = js
+        .find_job(&id)
+        .await?
+        .ok_or_else(|| ServerError::NoValue(format!("Unable to find import job: {}", id)))?;
+
+    let job_events = follow(job, receiver);
+    let stream = ReceiverStream::new(job_events)
+        .map(|event| sse::Event::default().event(event.name()).json_data(event));
+
+    Ok(Sse::new(stream).keep_alive(KeepAlive::default()))
+}
+
+async fn get_batch_transactions(
+    Path(id): Path<String>,
+    Query(query): Query<CurrencyQuery>,
+    Extension(batch_service): Extension<Arc<RwLock<BatchService<Postgres>>>>,
+    Extension(fx_service): Extension<Arc<RwLock<FxService<Postgres>>>>,
+) -> Result<Json<Value>, ServerError> {
+    let bs = batch_service.read().await;
+
+    let transactions = bs.find_batch_transactions(&id).await?;
+
+    Ok(Json(json!(
+        present(&fx_service, transactions, &query).await?
+    )))
+}
+
+// deletes every transaction the import created, the batch is kept as history
+async fn rollback_batch(
+    Path(id): Path<String>,
+    Extension(batch_service): Extension<Arc<RwLock<BatchService<Postgres>>>>,
+) -> Result<Json<Value>, ServerError> {
+    let bs = batch_service.read().await;
+
+    match bs.rollback_batch(&id).await? {
+        Some(deleted) => Ok(Json(json!({ "deleted": deleted }))),
+        None => Err(ServerError::NoValue(format!(
+            "Unable to find import batch: {}",
+            id
+        ))),
+    }
+}
+
+async fn get_accounts(
+    Extension(account_service): Extension<Arc<RwLock<AccountService<Postgres>>>>,
+) -> Result<Json<Value>, ServerError> {
+    let accs = account_service.read().await;
+
+    let accounts = accs.find_accounts().await?;
+
+    Ok(Json(json!(accounts)))
+}
+
+async fn get_account(
+    Path(id): Path<String>,
+    Extension(account_service): Extension<Arc<RwLock<AccountService<Postgres>>>>,
+) -> Result<Json<Value>, ServerError> {
+    let accs = account_service.read().await;
+
+    match accs.find_account(&id).await? {
+        Some(a) => Ok(Json(json!(a))),
+        None => Err(ServerError::NoValue(format!(
+            "Unable to find account: {}",
+            id
+        ))),
+    }
+}
 
-    if !has_deleted {
-        return Err(ServerError::ServiceError(format!(
-            "Failed to delete record with ID: {}",
+async fn create_account(
+    Extension(account_service): Extension<Arc<RwLock<AccountService<Postgres>>>>,
+    Json(account): Json<CreateAccount>,
+) -> Result<(StatusCode, Json<Value>), ServerError> {
+    let accs = account_service.read().await;
+
+    let account = accs.create_account(account).await?;
+
+    Ok((StatusCode::CREATED, Json(json!(account))))
+}
+
+async fn update_account(
+    Path(id): Path<String>,
+    Extension(account_service): Extension<Arc<RwLock<AccountService<Postgres>>>>,
+    Json(account): Json<CreateAccount>,
+) -> Result<Json<Value>, ServerError> {
+    let accs = account_service.read().await;
+
+    match accs.update_account(&id, account).await? {
+        Some(a) => Ok(Json(json!(a))),
+        None => Err(ServerError::NoValue(format!(
+            "Unable to find account: {}",
+            id
+        ))),
+    }
+}
+
+// accounts with transactions are closed instead, by updating them
+async fn delete_account(
+    Path(id): Path<String>,
+    Extension(account_service): Extension<Arc<RwLock<AccountService<Postgres>>>>,
+) -> Result<StatusCode, ServerError> {
+    let accs = account_service.read().await;
+
+    if !accs.delete_account(&id).await? {
+        return Err(ServerError::NoValue(format!(
+            "Unable to find account: {}",
             id
         )));
     }
 
-    return Ok(StatusCode::OK);
+    Ok(StatusCode::OK)
+}
+
+// the days of a balance series or of an account's transactions, both included
+#[derive(Deserialize)]
+struct DateRange {
+    from: Option<NaiveDate>,
+    to: Option<NaiveDate>,
+    #[serde(default)]
+    interval: Interval,
+}
+
+// the balance at the end of each day, week or month given by `interval`,
+// starting on `from`. anchors saved at /accounts/:id/anchors are counted on from
+async fn get_balances(
+    Path(id): Path<String>,
+    Query(range): Query<DateRange>,
+    Query(query): Query<CurrencyQuery>,
+    Extension(balance_service): Extension<Arc<RwLock<BalanceService<Postgres>>>>,
+    Extension(fx_service): Extension<Arc<RwLock<FxService<Postgres>>>>,
+) -> Result<Json<Value>, ServerError> {
+    let bs = balance_service.read().await;
+
+    let series = bs
+        .find_balances(&id, range.from, range.to, range.interval)
+        .await?
+        .ok_or_else(|| ServerError::NoValue(format!("Unable to find account: {}", id)))?;
+
+    match &query.currency {
+        Some(currency) => {
+            let fs = fx_service.read().await;
+            Ok(Json(json!(fs.convert_balances(series, currency).await?)))
+        }
+        None => Ok(Json(json!(series))),
+    }
+}
+
+// the account's transactions in the order they were paid, with the balance
+// after each one
+async fn get_account_transactions(
+    Path(id): Path<String>,
+    Query(range): Query<DateRange>,
+    Query(query): Query<CurrencyQuery>,
+    Extension(balance_service): Extension<Arc<RwLock<BalanceService<Postgres>>>>,
+    Extension(fx_service): Extension<Arc<RwLock<FxService<Postgres>>>>,
+) -> Result<Json<Value>, ServerError> {
+    let bs = balance_service.read().await;
+
+    let transactions = bs
+        .find_transaction_balances(&id, range.from, range.to)
+        .await?
+        .ok_or_else(|| ServerError::NoValue(format!("Unable to find account: {}", id)))?;
+
+    match &query.currency {
+        Some(currency) => {
+            let fs = fx_service.read().await;
+            let converted = fs
+                .convert_transaction_balances(transactions, currency)
+                .await?;
+            Ok(Json(json!(converted)))
+        }
+        None => Ok(Json(json!(transactions))),
+    }
+}
+
+async fn get_anchors(
+    Path(id): Path<String>,
+    Extension(balance_service): Extension<Arc<RwLock<BalanceService<Postgres>>>>,
+) -> Result<Json<Value>, ServerError> {
+    let bs = balance_service.read().await;
+
+    match bs.find_anchors(&id).await? {
+        Some(anchors) => Ok(Json(json!(anchors))),
+        None => Err(ServerError::NoValue(format!(
+            "Unable to find account: {}",
+            id
+        ))),
+    }
+}
+
+// records a balance from a statement as the balance at the end of its day,
+// replacing one already saved for the day
+async fn save_anchor(
+    Path(id): Path<String>,
+    Extension(balance_service): Extension<Arc<RwLock<BalanceService<Postgres>>>>,
+    Json(anchor): Json<CreateBalanceAnchor>,
+) -> Result<(StatusCode, Json<Value>), ServerError> {
+    let bs = balance_service.read().await;
+
+    match bs.save_anchor(&id, anchor).await? {
+        Some(anchor) => Ok((StatusCode::CREATED, Json(json!(anchor)))),
+        None => Err(ServerError::NoValue(format!(
+            "Unable to find account: {}",
+            id
+        ))),
+    }
+}
+
+async fn delete_anchor(
+    Path((id, date)): Path<(String, NaiveDate)>,
+    Extension(balance_service): Extension<Arc<RwLock<BalanceService<Postgres>>>>,
+) -> Result<StatusCode, ServerError> {
+    let bs = balance_service.read().await;
+
+    if !bs.delete_anchor(&id, date).await? {
+        return Err(ServerError::NoValue(format!(
+            "Unable to find balance anchor: {} {}",
+            id, date
+        )));
+    }
+
+    Ok(StatusCode::OK)
+}
+
+async fn get_reconciliations(
+    Path(id): Path<String>,
+    Extension(reconciliation_service): Extension<Arc<RwLock<ReconciliationService<Postgres>>>>,
+) -> Result<Json<Value>, ServerError> {
+    let rs = reconciliation_service.read().await;
+
+    match rs.find_reconciliations(&id).await? {
+        Some(reconciliations) => Ok(Json(json!(reconciliations))),
+        None => Err(ServerError::NoValue(format!(
+            "Unable to find account: {}",
+            id
+        ))),
+    }
+}
+
+// starts reconciling the account against a statement's end date and closing
+// balance, the summary has the difference still to clear and the transactions
+// up to the end date that can be ticked off
+async fn create_reconciliation(
+    Path(id): Path<String>,
+    Extension(reconciliation_service): Extension<Arc<RwLock<ReconciliationService<Postgres>>>>,
+    Json(reconciliation): Json<CreateReconciliation>,
+) -> Result<(StatusCode, Json<Value>), ServerError> {
+    let rs = reconciliation_service.read().await;
+
+    match rs.create_reconciliation(&id, reconciliation).await? {
+        Some(summary) => Ok((StatusCode::CREATED, Json(json!(summary)))),
+        None => Err(ServerError::NoValue(format!(
+            "Unable to find account: {}",
+            id
+        ))),
+    }
+}
+
+async fn get_reconciliation(
+    Path(id): Path<String>,
+    Extension(reconciliation_service): Extension<Arc<RwLock<ReconciliationService<Postgres>>>>,
+) -> Result<Json<Value>, ServerError> {
+    let rs = reconciliation_service.read().await;
+
+    match rs.find_reconciliation(&id).await? {
+        Some(summary) => Ok(Json(json!(summary))),
+        None => Err(ServerError::NoValue(format!(
+            "Unable to find reconciliation: {}",
+            id
+        ))),
+    }
+}
+
+// marks the transactions cleared, or uncleared with `"cleared": false`
+async fn clear_transactions(
+    Path(id): Path<String>,
+    Extension(reconciliation_service): Extension<Arc<RwLock<ReconciliationService<Postgres>>>>,
+    Json(clear): Json<ClearTransactions>,
+) -> Result<Json<Value>, ServerError> {
+    let rs = reconciliation_service.read().await;
+
+    match rs.clear_transactions(&id, clear).await? {
+        Some(summary) => Ok(Json(json!(summary))),
+        None => Err(ServerError::NoValue(format!(
+            "Unable to find reconciliation: {}",
+            id
+        ))),
+    }
+}
+
+// reconciles the cleared transactions, which can no longer be deleted or
+// rolled back, once there is no difference left
+async fn finish_reconciliation(
+    Path(id): Path<String>,
+    Extension(reconciliation_service): Extension<Arc<RwLock<ReconciliationService<Postgres>>>>,
+) -> Result<Json<Value>, ServerError> {
+    let rs = reconciliation_service.read().await;
+
+    match rs.finish_reconciliation(&id).await? {
+        Some(summary) => Ok(Json(json!(summary))),
+        None => Err(ServerError::NoValue(format!(
+            "Unable to find reconciliation: {}",
+            id
+        ))),
+    }
+}
+
+async fn delete_reconciliation(
+    Path(id): Path<String>,
+    Extension(reconciliation_service): Extension<Arc<RwLock<ReconciliationService<Postgres>>>>,
+) -> Result<StatusCode, ServerError> {
+    let rs = reconciliation_service.read().await;
+
+    if !rs.delete_reconciliation(&id).await? {
+        return Err(ServerError::NoValue(format!(
+            "Unable to find reconciliation: {}",
+            id
+        )));
+    }
+
+    Ok(StatusCode::OK)
+}
+
+async fn get_fx_rates(
+    Extension(fx_service): Extension<Arc<RwLock<FxService<Postgres>>>>,
+) -> Result<Json<Value>, ServerError> {
+    let fs = fx_service.read().await;
+
+    let rates = fs.find_rates().await?;
+
+    Ok(Json(json!(rates)))
+}
+
+// expects one or more rate files, csv with date, base, quote and rate columns
+// or a json array of objects with the same fields. a rate already saved for the
+// pair and day is replaced. a file with an invalid rate saves none of its rates
+async fn import_fx_rates(
+    Extension(fx_service): Extension<Arc<RwLock<FxService<Postgres>>>>,
+    mut multipart: Multipart,
+) -> Result<Json<Value>, ServerError> {
+    let fs = fx_service.read().await;
+    let mut imported = 0;
+    let mut files = 0;
+
+    while let Some(field) = multipart
+        .next_field()
+        .await
+        .map_err(|e| ServerError::MultipartError(e.to_string()))?
+    {
+        let filename = field.file_name().map(|f| f.to_string());
+        let data = field
+            .text()
+            .await
+            .map_err(|e| ServerError::MultipartError(e.to_string()))?;
+
+        imported += fs.import_rates(filename.as_deref(), &data).await?;
+        files += 1;
+    }
+
+    if files == 0 {
+        return Err(ServerError::NoValue(
+            "No rate files were uploaded".to_string(),
+        ));
+    }
+
+    Ok(Json(json!({ "imported": imported })))
 }
@@ -0,0 +1,501 @@
+use std::{sync::Arc, time::Duration};
+
+use serde::Serialize;
+use serde_json::json;
+use tokio::sync::{
+    broadcast::{self, error::RecvError},
+    mpsc, watch, Notify, RwLock,
+};
+use tracing::{error, info};
+
+use crate::{
+    database::{base::DatabaseInit, postgres::Postgres},
+    models::job::{ImportJob, JobState},
+    service::{
+        batch::ImportSession,
+        job::{JobError, JobRead, JobService, JobWrite},
+        parse::{
+            report::{Counts, Event, Policy, Summary},
+            ParseError, Service,
+        },
+        profile::ProfileService,
+    },
+};
+
+// how often the queue is checked when no upload has said a job is waiting
+const POLL_SECONDS: u64 = 5;
+
+// chunks of a job's files read ahead of the parser
+const READ_CHUNKS: usize = 4;
+
+// import job events kept for followers that are slow to read them, older ones
+// are skipped
+pub const JOB_EVENTS: usize = 256;
+
+// sent to everyone following an import job, the job id is sent with each
+pub type JobEvents = broadcast::Sender<(String, JobEvent)>;
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(untagged)]
+pub enum JobEvent {
+    Import(Event),
+    // the job's counts once all its files are saved
+    Done(Counts),
+    Failed { error: String },
+}
+
+impl JobEvent {
+    pub fn name(&self) -> &'static str {
+        match self {
+            JobEvent::Import(Event::Errors { .. }) => "errors",
+            JobEvent::Import(Event::Duplicates { .. }) => "duplicates",
+            JobEvent::Import(Event::Progress { .. }) => "progress",
+            JobEvent::Done(_) => "done",
+            JobEvent::Failed { .. } => "failed",
+        }
+    }
+
+    // nothing more is sent for the job after it
+    pub fn is_last(&self) -> bool {
+        matches!(self, JobEvent::Done(_) | JobEvent::Failed { .. })
+    }
+}
+
+// runs the queued import jobs one at a time. an upload notifies the worker
+// when it has queued a job
+pub struct Worker {
+    parse_service: Arc<RwLock<Service>>,
+    profile_service: Arc<RwLock<ProfileService<Postgres>>>,
+    job_service: Arc<RwLock<JobService<Postgres>>>,
+    queued: Arc<Notify>,
+    events: JobEvents,
+}
+
+impl Worker {
+    pub fn new(
+        parse_service: Arc<RwLock<Service>>,
+        profile_service: Arc<RwLock<ProfileService<Postgres>>>,
+        job_service: Arc<RwLock<JobService<Postgres>>>,
+        queued: Arc<Notify>,
+        events: JobEvents,
+    ) -> Self {
+        Self {
+            parse_service,
+            profile_service,
+            job_service,
+            queued,
+            events,
+        }
+    }
+
+    pub async fn run(self) {
+        match self.job_service.read().await.requeue_jobs().await {
+            Ok(0) => (),
+            Ok(n) => info!("Requeued {} import jobs left running", n),
+            Err(e) => error!("Unable to requeue import jobs: {}", e),
+        }
+
+        loop {
+            let claimed = self.job_service.read().await.claim_job().await;
+
+            match claimed {
+                Ok(Some(job)) => self.run_job(job).await,
+                Ok(None) => {
+                    tokio::select! {
+                        _ = self.queued.notified() => (),
+                        _ = tokio::time::sleep(Duration::from_secs(POLL_SECONDS)) => (),
+                    }
+                }
+                Err(e) => {
+                    error!("Unable to claim import job: {}", e);
+                    tokio::time::sleep(Duration::from_secs(POLL_SECONDS)).await;
+                }
+            }
+        }
+    }
+
+    async fn run_job(&self, job: ImportJob) {
+        info!("Running import job {}", job.id);
+
+        // counts are written as they change, skipping any the database is
+        // too slow to keep up with
+        let (progress, mut updates) = watch::channel(Counts::default());
+        let job_service = self.job_service.clone();
+        let id = job.id.clone();
+        let recorder = tokio::spawn(async move {
+            while updates.changed().await.is_ok() {
+                let counts = *updates.borrow_and_update();
+                let js = job_service.read().await;
+                if let Err(e) = js.update_job_progress(&id, &counts).await {
+                    error!("Unable to save progress of import job {}: {}", id, e);
+                }
+            }
+        });
+
+        let result = self.import(&job.id, progress).await;
+        let _ = recorder.await;
+
+        let event = end_job(&*self.job_service.read().await, &job.id, result).await;
+
+        // sending fails when no one is following the job
+        let _ = self.events.send((job.id, event));
+    }
+
+    // imports the job's files together, the same way as an upload
+    async fn import(&self, id: &str, progress: watch::Sender<Counts>) -> Result<Summary, String> {
+        let files = self
+            .job_service
+            .read()
+            .await
+            .find_job_files(id)
+            .await
+            .map_err(|e| e.to_string())?;
+
+        let ps = self.parse_service.read().await;
+        let mut session = ps.begin_import().await.map_err(|e| e.to_string())?;
+        let mut summary = Summary::default();
+        let mut profiles = None;
+
+        for file in files {
+            let policy: Policy = file
+                .on_error
+                .parse()
+                .map_err(|e: ParseError| e.to_string())?;
+
+            let config = match &file.profile {
+                Some(name) => Some(
+                    self.profile_service
+                        .read()
+                        .await
+                        .find_profile(name)
+                        .await
+                        .map_err(|e| e.to_string())?
+                        .ok_or_else(|| format!("Unable to find profile: {}", name))?,
+                ),
+                None => None,
+            };
+
+            if config.is_none() && profiles.is_none() {
+                let ps = self.profile_service.read().await;
+                profiles = Some(ps.find_profiles().await.map_err(|e| e.to_string())?);
+            }
+
+            let (sender, receiver) = mpsc::channel(READ_CHUNKS);
+            let job_service = self.job_service.clone();
+            let job_id = id.to_string();
+            let position = file.position;
+            let read = async move {
+                for sequence in 0.. {
+                    let js = job_service.read().await;
+                    let Some(data) = js.find_job_chunk(&job_id, position, sequence).await? else {
+                        break;
+                    };
+
+                    // the import has stopped reading, its error is returned instead
+                    if sender.send(data).await.is_err() {
+                        break;
+                    }
+                }
+
+                Ok::<(), JobError>(())
+            };
+
+            let done = Counts::from(&summary);
+            let mut file_events = |event: Event| {
+                if let Event::Progress { counts, .. } = &event {
+                    progress.send_replace(done + *counts);
+                }
+
+                let _ = self.events.send((id.to_string(), JobEvent::Import(event)));
+            };
+            let (read, report) = tokio::join!(
+                read,
+                ps.import_stream(
+                    &mut session,
+                    file.filename,
+                    file.account_id,
+                    config.as_ref(),
+                    profiles.as_deref().unwrap_or_default(),
+                    policy,
+                    receiver,
+                    &mut file_events,
+                )
+            );
+            read.map_err(|e| e.to_string())?;
+            summary.add(report.map_err(|e| e.to_string())?);
+        }
+
+        session.commit().await.map_err(|e| e.to_string())?;
+
+        Ok(summary)
+    }
+}
+
+// the job's events until it is done or failed, a finished job only sends its
+// final event. the receiver has to be subscribed before the job was read so it
+// cannot finish unseen
+pub fn follow(
+    job: ImportJob,
+    mut receiver: broadcast::Receiver<(String, JobEvent)>,
+) -> mpsc::Receiver<JobEvent> {
+    let finished = match job.state {
+        JobState::Done => Some(JobEvent::Done(Counts {
+            imported: job.imported_rows as usize,
+            skipped: job.skipped_rows as usize,
+            failed: job.failed_rows as usize,
+            duplicates: job.duplicate_rows as usize,
+        })),
+        JobState::Failed => Some(JobEvent::Failed {
+            error: job.error.unwrap_or_default(),
+        }),
+        JobState::Queued | JobState::Running => None,
+    };
+
+    let (sender, job_events) = mpsc::channel(JOB_EVENTS);
+    tokio::spawn(async move {
+        if let Some(event) = finished {
+            let _ = sender.send(event).await;
+            return;
+        }
+
+        loop {
+            match receiver.recv().await {
+                Ok((job_id, event)) if job_id == job.id => {
+                    let last = event.is_last();
+                    if sender.send(event).await.is_err() || last {
+                        return;
+                    }
+                }
+                Ok(_) | Err(RecvError::Lagged(_)) => (),
+                Err(RecvError::Closed) => return,
+            }
+        }
+    });
+
+    job_events
+}
+
+// saves how the job ended, returns the event telling its followers
+async fn end_job<T>(js: &JobService<T>, id: &str, result: Result<Summary, String>) -> JobEvent
+where
+    T: DatabaseInit + JobWrite + JobRead,
+{
+    let (saved, event) = match result {
+        Ok(summary) => {
+            info!("Finished import job {}", id);
+            let counts = Counts::from(&summary);
+            let saved = js.finish_job(id, &counts, json!(summary)).await;
+            (saved, JobEvent::Done(counts))
+        }
+        Err(e) => {
+            error!("Import job {} failed: {}", id, e);
+            let saved = js.fail_job(id, &e).await;
+            (saved, JobEvent::Failed { error: e })
+        }
+    };
+
+    if let Err(e) = saved {
+        error!("Unable to save state of import job {}: {}", id, e);
+    }
+
+    event
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        database::memory::Memory,
+        models::job::ImportJobFile,
+        service::{job::JobUpload, parse::report::Report},
+    };
+
+    // queues a job of one file, returns its id
+    async fn queue(js: &JobService<Memory>) -> String {
+        let mut upload = js.begin_job().await.unwrap_or_else(|e| panic!("{}", e));
+        upload
+            .add_file(&ImportJobFile {
+                position: 0,
+                filename: Some("monzo.csv".to_string()),
+                profile: None,
+                on_error: Policy::default().to_string(),
+                account_id: None,
+            })
+            .await
+            .unwrap_or_else(|e| panic!("{}", e));
+        upload
+            .add_chunk(0, 0, b"Date,Name,Amount\n")
+            .await
+            .unwrap_or_else(|e| panic!("{}", e));
+
+        upload.commit().await.unwrap_or_else(|e| panic!("{}", e)).id
+    }
+
+    async fn state(js: &JobService<Memory>, id: &str) -> JobState {
+        js.find_job(id)
+            .await
+            .unwrap_or_else(|e| panic!("{}", e))
+            .expect("job")
+            .state
+    }
+
+    async fn claim(js: &JobService<Memory>) -> Option<String> {
+        js.claim_job()
+            .await
+            .unwrap_or_else(|e| panic!("{}", e))
+            .map(|j| j.id)
+    }
+
+    // the events a follower of the job receives
+    async fn received(mut job_events: mpsc::Receiver<JobEvent>) -> Vec<&'static str> {
+        let mut names = Vec::new();
+        while let Some(event) = job_events.recv().await {
+            names.push(event.name());
+        }
+        names
+    }
+
+    #[tokio::test]
+    async fn a_finished_job_only_sends_its_final_event() {
+        let js = JobService::new(Memory::default());
+        let id = queue(&js).await;
+        claim(&js).await;
+        end_job(&js, &id, Ok(Summary::default())).await;
+        let job = js.find_job(&id).await.unwrap_or_else(|e| panic!("{}", e));
+        let (events, receiver) = broadcast::channel(JOB_EVENTS);
+
+        let job_events = follow(job.expect("job"), receiver);
+        let _ = events.send((
+            id,
+            JobEvent::Failed {
+                error: String::new(),
+            },
+        ));
+
+        assert_eq!(received(job_events).await, ["done"]);
+    }
+
+    #[tokio::test]
+    async fn a_running_job_sends_its_own_events_until_the_last() {
+        let js = JobService::new(Memory::default());
+        let id = queue(&js).await;
+        let other = queue(&js).await;
+        claim(&js).await;
+        let job = js.find_job(&id).await.unwrap_or_else(|e| panic!("{}", e));
+        let (events, receiver) = broadcast::channel(JOB_EVENTS);
+
+        let job_events = follow(job.expect("job"), receiver);
+        let progress = |file: &str| {
+            JobEvent::Import(Event::Progress {
+                file: Some(file.to_string()),
+                counts: Counts::default(),
+            })
+        };
+        for (job_id, event) in [
+            (id.clone(), progress("monzo.csv")),
+            (other.clone(), progress("amex.csv")),
+            (other, JobEvent::Done(Counts::default())),
+            (id.clone(), JobEvent::Done(Counts::default())),
+            (id, progress("late.csv")),
+        ] {
+            let _ = events.send((job_id, event));
+        }
+
+        assert_eq!(received(job_events).await, ["progress", "done"]);
+    }
+
+    #[tokio::test]
+    async fn a_job_is_claimed_once_and_done_with_its_counts() {
+        let db = Memory::default();
+        let js = JobService::new(db.clone());
+        let id = queue(&js).await;
+        assert_eq!(state(&js, &id).await, JobState::Queued);
+
+        assert_eq!(claim(&js).await.as_deref(), Some(id.as_str()));
+        assert_eq!(claim(&js).await, None);
+        assert_eq!(state(&js, &id).await, JobState::Running);
+
+        let mut summary = Summary::default();
+        summary.add(Report {
+            imported: 3,
+            duplicates: 1,
+            ..Report::default()
+        });
+        let event = end_job(&js, &id, Ok(summary)).await;
+
+        let counts = Counts {
+            imported: 3,
+            duplicates: 1,
+            ..Counts::default()
+        };
+        assert!(matches!(event, JobEvent::Done(c) if c == counts));
+        assert!(event.is_last());
+        let job = js.find_job(&id).await.unwrap_or_else(|e| panic!("{}", e));
+        let job = job.expect("job");
+        assert_eq!(job.state, JobState::Done);
+        assert_eq!((job.imported_rows, job.duplicate_rows), (3, 1));
+        // the uploaded files are no longer needed
+        assert!(db.tables().job_files.is_empty());
+        assert!(db.tables().job_chunks.is_empty());
+    }
+
+    #[tokio::test]
+    async fn a_failed_job_keeps_its_error_and_none_of_its_counts() {
+        let db = Memory::default();
+        let js = JobService::new(db.clone());
+        let id = queue(&js).await;
+        claim(&js).await;
+        js.update_job_progress(
+            &id,
+            &Counts {
+                imported: 2,
+                ..Counts::default()
+            },
+        )
+        .await
+        .unwrap_or_else(|e| panic!("{}", e));
+
+        let event = end_job(&js, &id, Err("Unable to find profile: Monzo".to_string())).await;
+
+        assert!(matches!(&event, JobEvent::Failed { error } if error.contains("Monzo")));
+        assert_eq!(event.name(), "failed");
+        let job = js.find_job(&id).await.unwrap_or_else(|e| panic!("{}", e));
+        let job = job.expect("job");
+        assert_eq!(job.state, JobState::Failed);
+        assert_eq!(job.imported_rows, 0);
+        assert_eq!(job.error.as_deref(), Some("Unable to find profile: Monzo"));
+        assert!(db.tables().job_files.is_empty());
+    }
+
+    #[tokio::test]
+    async fn jobs_left_running_are_requeued_from_the_start() {
+        let js = JobService::new(Memory::default());
+        let first = queue(&js).await;
+        let second = queue(&js).await;
+        claim(&js).await;
+        js.update_job_progress(
+            &first,
+            &Counts {
+                imported: 5,
+                ..Counts::default()
+            },
+        )
+        .await
+        .unwrap_or_else(|e| panic!("{}", e));
+
+        let requeued = js.requeue_jobs().await.unwrap_or_else(|e| panic!("{}", e));
+
+        assert_eq!(requeued, 1);
+        let job = js
+            .find_job(&first)
+            .await
+            .unwrap_or_else(|e| panic!("{}", e));
+        let job = job.expect("job");
+        assert_eq!(job.state, JobState::Queued);
+        assert_eq!(job.imported_rows, 0);
+        assert!(job.started_at.is_none());
+        // the oldest job is still the first to run
+        assert_eq!(claim(&js).await, Some(first));
+        assert_eq!(claim(&js).await, Some(second));
+    }
+}
@@ -9,7 +9,7 @@ pub mod trans {
 
 use trans::GetRequest;
 
-use crate::trans::{transactor_client::TransactorClient, AddRequest};
+use crate::trans::{transactor_client::TransactorClient, AddRequest, Money};
 
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn Error>> {
@@ -28,10 +28,15 @@ async fn main() -> Result<(), Box<dyn Error>> {
     });
 
     let new_transaction = tonic::Request::new(AddRequest {
-        amount: 100.0,
+        amount: Some(Money {
+            amount: "100.00".to_string(),
+            currency: "GBP".to_string(),
+        }),
         transaction_type: "debit".to_string(),
-        timestamp: 123.to_string(),
+        timestamp: "2024-03-01T12:00:00Z".to_string(),
         user: "josh".to_string(),
+        description: "test".to_string(),
+        account_type: "test".to_string(),
     });
 
     println!("Sending transaction");
@@ -0,0 +1,8 @@
+use std::error::Error;
+
+fn main() -> Result<(), Box<dyn Error>> {
+    #[cfg(feature = "grpc")]
+    tonic_build::compile_protos("../proto/transaction.proto")?;
+
+    Ok(())
+}
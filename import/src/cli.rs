@@ -1,6 +1,6 @@
 use std::fs::File;
 
-use crate::service::parse_service::{AccountType, Config, Service};
+use import::service::parse_service::{AccountType, Service};
 
 pub struct Cli {
     parse_service: Service,
@@ -15,13 +15,20 @@ impl Cli {
 
     pub fn start(self) {
         let file = File::open("test.csv").expect("Could not find / open test file");
-        let _res = self.parse_service.parse(
-            AccountType::Test(Config {
-                name: "Test".to_string(),
-                date: 2,
-                amount: 3,
-            }),
-            file,
-        );
+
+        let rows = match self.parse_service.parse(AccountType::Test, file) {
+            Ok(rows) => rows,
+            Err(e) => {
+                eprintln!("Error trying to parse test file: {}", e);
+                return;
+            }
+        };
+
+        for row in rows {
+            match row {
+                Ok(transaction) => println!("{}", transaction),
+                Err(e) => eprintln!("row {}: {}", e.row, e.reason),
+            }
+        }
     }
 }
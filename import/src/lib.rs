@@ -0,0 +1,2 @@
+// parsing of bank statement exports, shared by the import and expr services
+pub mod service;
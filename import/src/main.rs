@@ -2,7 +2,10 @@ use clap::Parser;
 
 mod cli;
 mod server;
-mod service;
+
+pub mod trans {
+    tonic::include_proto!("transaction");
+}
 
 #[derive(Parser)]
 #[command(author, version, about)]
@@ -10,13 +13,23 @@ struct Args {
     // start a web server
     #[arg(short = 's', long, default_value = "false")]
     enable_server: bool,
+
+    // where the server sends uploaded transactions
+    #[arg(short = 't', long, default_value = "http://127.0.0.1:50051")]
+    transaction_server: String,
 }
 
 #[tokio::main]
 async fn main() {
     let args = Args::parse();
     if args.enable_server {
-        let server = server::Server::new();
+        let server = match server::Server::new(&args.transaction_server) {
+            Ok(s) => s,
+            Err(e) => {
+                eprintln!("Error: {}", e);
+                return;
+            }
+        };
         match server.start().await {
             Ok(_) => (),
             Err(e) => eprintln!("Error: {}", e),
@@ -1,30 +1,65 @@
 use std::sync::{Arc, RwLock};
 
 use axum::{
-    extract::Multipart,
+    extract::{multipart::Field, DefaultBodyLimit, Multipart},
     http::StatusCode,
     routing::{get, post},
-    Extension, Router,
+    Extension, Json, Router,
 };
+use import::service::parse_service::{
+    detect, AccountType, Config, ParsedTransaction, RowError, Service,
+};
+use serde::Serialize;
+use tonic::transport::{Channel, Endpoint};
+
+use crate::trans::{transactor_client::TransactorClient, AddBatchRequest, AddRequest, Money};
+
+// transactions sent to the transaction service in each request
+const BATCH_SIZE: usize = 1000;
 
-use crate::service::parse_service::Service;
+// statements are parsed in memory so the whole upload is capped, text fields
+// are capped on their own
+const UPLOAD_BYTES: usize = 16 * 1024 * 1024;
+const TEXT_FIELD_BYTES: usize = 1024;
 
 pub struct Server {
     parse_server: Arc<RwLock<Service>>,
+    transactor: TransactorClient<Channel>,
+}
+
+// what happened to one uploaded file
+#[derive(Serialize)]
+struct FileResult {
+    file: Option<String>,
+    format: Option<String>,
+    profile: Option<String>,
+    imported: usize,
+    // rows that could not be read, the rest of the file is still imported
+    errors: Vec<RowError>,
+    // set when the file could not be read or its transactions could not all be
+    // saved, the ones counted in imported were saved
+    error: Option<String>,
 }
 
 impl Server {
-    pub fn new() -> Self {
-        Self {
+    // the transaction service is connected to when the first upload is sent on
+    pub fn new(transaction_server: &str) -> Result<Self, String> {
+        let endpoint = Endpoint::from_shared(transaction_server.to_string())
+            .map_err(|e| format!("Invalid transaction server address: {}", e))?;
+
+        Ok(Self {
             parse_server: Arc::new(RwLock::new(Service::new())),
-        }
+            transactor: TransactorClient::new(endpoint.connect_lazy()),
+        })
     }
 
     pub async fn start(&self) -> Result<(), String> {
         let app = Router::new()
             .route("/", get("Ok"))
             .route("/upload", post(upload))
-            .layer(Extension(self.parse_server.clone()));
+            .layer(DefaultBodyLimit::max(UPLOAD_BYTES))
+            .layer(Extension(self.parse_server.clone()))
+            .layer(Extension(self.transactor.clone()));
 
         let listener = tokio::net::TcpListener::bind("0.0.0.0:3000")
             .await
@@ -39,34 +74,191 @@ impl Server {
     }
 }
 
+// parses the uploaded statements and saves their transactions with the
+// transaction service. a `profile` field naming a built in bank profile, e.g.
+// Monzo, is used for the files sent after it, otherwise the profile is detected.
+// a `user` field is recorded as the owner of the transactions of the files
+// sent after it
 async fn upload(
     Extension(parse_server): Extension<Arc<RwLock<Service>>>,
+    Extension(transactor): Extension<TransactorClient<Channel>>,
     mut multipart: Multipart,
-) -> Result<StatusCode, ()> {
-    while let Some(field) = multipart
+) -> Result<Json<Vec<FileResult>>, (StatusCode, String)> {
+    let profiles: Vec<Config> = AccountType::all().iter().map(|a| a.config()).collect();
+    let mut user = String::new();
+    let mut profile: Option<Config> = None;
+    let mut results = Vec::new();
+
+    while let Some(mut field) = multipart
         .next_field()
         .await
-        .expect("failed to read multipart")
+        .map_err(|e| (StatusCode::BAD_REQUEST, e.to_string()))?
     {
-        if let Some(filename) = field.file_name() {
-            println!("filename: {}", filename)
-        }
+        let text_field = match field.file_name() {
+            Some(_) => None,
+            None => field.name().map(|n| n.to_string()),
+        };
+        let filename = field.file_name().map(|f| f.to_string());
+        let limit = match text_field {
+            Some(_) => TEXT_FIELD_BYTES,
+            None => UPLOAD_BYTES,
+        };
+        let data = read_text(&mut field, limit).await?;
 
-        let mut transactions = Vec::new();
-        if let Ok(data) = field.text().await {
-            if let Ok(ps) = parse_server.write() {
-                transactions = match ps.parse_data(data) {
-                    Ok(transactions) => transactions,
-                    Err(e) => {
-                        eprintln!("Error trying to parse data: {}", e);
-                        vec![]
-                    }
-                };
+        match text_field.as_deref() {
+            Some("user") => {
+                user = data;
+                continue;
+            }
+            Some("profile") => {
+                let name = data.trim();
+                let account_type = AccountType::named(name).ok_or_else(|| {
+                    (
+                        StatusCode::NOT_FOUND,
+                        format!("Unable to find profile: {}", name),
+                    )
+                })?;
+                profile = Some(account_type.config());
+                continue;
             }
+            _ => (),
         }
 
-        println!("transactions: {:?}", transactions);
+        let mut result = FileResult {
+            file: filename,
+            format: None,
+            profile: None,
+            imported: 0,
+            errors: Vec::new(),
+            error: None,
+        };
+
+        let imported = import_file(
+            &parse_server,
+            transactor.clone(),
+            profile.as_ref(),
+            &profiles,
+            &user,
+            &data,
+            &mut result,
+        )
+        .await;
+        if let Err(e) = imported {
+            eprintln!(
+                "Unable to import {}: {}",
+                result.file.as_deref().unwrap_or_default(),
+                e
+            );
+            result.error = Some(e);
+        }
+
+        results.push(result);
+    }
+
+    if results.is_empty() {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            "No statement files were uploaded".to_string(),
+        ));
     }
 
-    Ok(StatusCode::OK)
+    Ok(Json(results))
+}
+
+async fn read_text(field: &mut Field<'_>, limit: usize) -> Result<String, (StatusCode, String)> {
+    let name = field.name().unwrap_or_default().to_string();
+
+    let mut data = Vec::new();
+    while let Some(chunk) = field
+        .chunk()
+        .await
+        .map_err(|e| (StatusCode::BAD_REQUEST, e.to_string()))?
+    {
+        if data.len() + chunk.len() > limit {
+            return Err((
+                StatusCode::PAYLOAD_TOO_LARGE,
+                format!("The {} field is longer than {} bytes", name, limit),
+            ));
+        }
+        data.extend_from_slice(&chunk);
+    }
+
+    String::from_utf8(data).map_err(|e| {
+        (
+            StatusCode::BAD_REQUEST,
+            format!("The {} field: {}", name, e),
+        )
+    })
+}
+
+async fn import_file(
+    parse_server: &RwLock<Service>,
+    mut transactor: TransactorClient<Channel>,
+    profile: Option<&Config>,
+    profiles: &[Config],
+    user: &str,
+    data: &str,
+    result: &mut FileResult,
+) -> Result<(), String> {
+    let (format, profile) = match profile {
+        Some(p) => (
+            detect::format(result.file.as_deref(), data),
+            Some(p.clone()),
+        ),
+        None => {
+            let detected = detect::statement(result.file.as_deref(), data, profiles)
+                .map_err(|e| e.to_string())?;
+            (detected.format, detected.profile)
+        }
+    };
+    result.format = Some(format.to_string());
+    result.profile = profile.as_ref().map(|p| p.name.clone());
+
+    let rows = parse_server
+        .read()
+        .map_err(|e| e.to_string())?
+        .parse_data(format, profile.as_ref(), data)
+        .map_err(|e| e.to_string())?;
+
+    let mut transactions = Vec::new();
+    for row in rows {
+        match row {
+            Ok(transaction) => transactions.push(add_request(transaction, user)),
+            Err(e) => result.errors.push(e),
+        }
+    }
+
+    for batch in transactions.chunks(BATCH_SIZE) {
+        let request = AddBatchRequest {
+            transactions: batch.to_vec(),
+        };
+        let response = transactor
+            .add_batch(request)
+            .await
+            .map_err(|e| format!("Unable to save transactions: {}", e.message()))?;
+
+        result.imported += response.into_inner().added as usize;
+    }
+
+    Ok(())
+}
+
+fn add_request(transaction: ParsedTransaction, user: &str) -> AddRequest {
+    let transaction_type = if transaction.amount.is_negative() {
+        "debit"
+    } else {
+        "credit"
+    };
+
+    AddRequest {
+        amount: Some(Money {
+            amount: transaction.amount.amount.to_string(),
+            currency: transaction.amount.currency,
+        }),
+        transaction_type: transaction_type.to_string(),
+        timestamp: transaction.payment_date.and_utc().to_rfc3339(),
+        user: user.to_string(),
+        description: transaction.description,
+        account_type: transaction.account_type,
+    }
 }
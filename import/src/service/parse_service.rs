@@ -1,15 +1,41 @@
 use std::{
     fmt::{self, Display},
-    fs::File,
+    io::Read,
 };
 
+use chrono::NaiveDateTime;
+use serde::{Deserialize, Serialize};
+
+mod camt;
+pub mod delimited;
+pub mod detect;
+mod locale;
+mod money;
+mod mt940;
+mod ofx;
+mod qif;
+
+pub use money::{is_currency, Money, DEFAULT_CURRENCY};
+
+// reads bank statement exports into transactions. csv files are read with an
+// import profile, either one of the built in account types or a custom one,
+// the other formats describe their own layout
 pub struct Service;
 
-#[derive(Debug)]
+// a transaction read from a statement
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct ParsedTransaction {
-    accounttype: String,
-    date: String,
-    amount: f64,
+    // the profile name, or the account named by the statement itself
+    pub account_type: String,
+    pub payment_date: NaiveDateTime,
+    // negative for money going out of the account, positive for money coming in
+    pub amount: Money,
+    pub description: String,
+    // identifier assigned by the bank, e.g. the OFX FITID
+    pub external_id: Option<String>,
+    pub category: Option<String>,
+    // date the money was actually credited or debited, when the bank reports it
+    pub value_date: Option<NaiveDateTime>,
 }
 
 impl Display for ParsedTransaction {
@@ -17,21 +43,186 @@ impl Display for ParsedTransaction {
         write!(
             f,
             "account_type: {}, date: {}, amount: {}",
-            self.accounttype, self.date, self.amount
+            self.account_type, self.payment_date, self.amount
         )
     }
 }
 
-// column position of the given columns
-pub struct Config {
-    pub name: String,
-    pub date: usize,
-    pub amount: usize,
+// one parsed row of a statement, or why it could not be read
+pub type Row = Result<ParsedTransaction, RowError>;
+
+// row is the line number in csv files, the line ending the record in qif files
+// and the entry number, counting from 1, in ofx, camt.053 and mt940 files
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct RowError {
+    pub row: usize,
+    pub column: Option<String>,
+    pub value: Option<String>,
+    pub reason: String,
+}
+
+impl RowError {
+    pub fn new(row: usize, error: ParseError) -> Self {
+        Self {
+            row,
+            column: None,
+            value: None,
+            reason: error.to_string(),
+        }
+    }
+
+    pub fn column(row: usize, column: &str, value: &str, error: ParseError) -> Self {
+        Self {
+            row,
+            column: Some(column.to_string()),
+            value: Some(value.to_string()),
+            reason: error.to_string(),
+        }
+    }
 }
 
+// the banks with a built in profile, Custom takes any other profile
 pub enum AccountType {
-    Test(Config),
-    Lloyds(Config),
+    Test,
+    Lloyds,
+    Amex,
+    Monzo,
+    Starling,
+    Hsbc,
+    Barclays,
+    Nationwide,
+    Santander,
+    Revolut,
+    Custom(Box<Config>),
+}
+
+impl AccountType {
+    pub fn config(&self) -> Config {
+        match self {
+            // headerless test exports: id, description, date, amount
+            AccountType::Test => Config {
+                name: "Test".to_string(),
+                description_position: Some(1),
+                date_position: Some(2),
+                amount_position: Some(3),
+                has_header: false,
+                ..Config::default()
+            },
+            AccountType::Lloyds => Config {
+                name: "Lloyds".to_string(),
+                date_headers: vec!["Transaction Date".to_string()],
+                description_headers: vec!["Transaction Description".to_string()],
+                amount_mode: AmountMode::DebitCredit,
+                debit_headers: vec!["Debit Amount".to_string()],
+                credit_headers: vec!["Credit Amount".to_string()],
+                ..Config::default()
+            },
+            // amex reports purchases as positive amounts
+            AccountType::Amex => Config {
+                name: "Amex".to_string(),
+                date_headers: vec!["Date".to_string()],
+                description_headers: vec!["Description".to_string()],
+                amount_headers: vec!["Amount".to_string()],
+                invert_sign: true,
+                ..Config::default()
+            },
+            // monzo also has a signed Amount column, reading the money out and
+            // money in columns tells its files apart from amex ones
+            AccountType::Monzo => Config {
+                name: "Monzo".to_string(),
+                date_headers: vec!["Date".to_string()],
+                description_headers: vec!["Name".to_string()],
+                amount_mode: AmountMode::DebitCredit,
+                debit_headers: vec!["Money Out".to_string()],
+                credit_headers: vec!["Money In".to_string()],
+                ..Config::default()
+            },
+            AccountType::Starling => Config {
+                name: "Starling".to_string(),
+                date_headers: vec!["Date".to_string()],
+                description_headers: vec!["Counter Party".to_string()],
+                amount_headers: vec!["Amount (GBP)".to_string()],
+                skip_descriptions: vec!["Opening Balance".to_string()],
+                ..Config::default()
+            },
+            // hsbc exports have no header: date, description, amount
+            AccountType::Hsbc => Config {
+                name: "HSBC".to_string(),
+                date_position: Some(0),
+                description_position: Some(1),
+                amount_position: Some(2),
+                has_header: false,
+                ..Config::default()
+            },
+            AccountType::Barclays => Config {
+                name: "Barclays".to_string(),
+                date_headers: vec!["Date".to_string()],
+                description_headers: vec!["Memo".to_string()],
+                amount_headers: vec!["Amount".to_string()],
+                ..Config::default()
+            },
+            // the account name and balances come before the header, which is
+            // found by name
+            AccountType::Nationwide => Config {
+                name: "Nationwide".to_string(),
+                date_headers: vec!["Date".to_string()],
+                description_headers: vec!["Description".to_string()],
+                amount_mode: AmountMode::DebitCredit,
+                debit_headers: vec!["Paid out".to_string()],
+                credit_headers: vec!["Paid in".to_string()],
+                date_formats: vec!["%d %b %Y".to_string()],
+                ..Config::default()
+            },
+            AccountType::Santander => Config {
+                name: "Santander".to_string(),
+                date_headers: vec!["Transaction Date".to_string()],
+                description_headers: vec!["Transaction Description".to_string()],
+                amount_mode: AmountMode::DebitCredit,
+                debit_headers: vec!["Money Out".to_string()],
+                credit_headers: vec!["Money In".to_string()],
+                ..Config::default()
+            },
+            // pending transactions have no completed date, so the started date
+            // is used. fees are in their own column and are not included
+            AccountType::Revolut => Config {
+                name: "Revolut".to_string(),
+                date_headers: vec!["Started Date".to_string()],
+                description_headers: vec!["Description".to_string()],
+                amount_headers: vec!["Amount".to_string()],
+                date_formats: vec!["%Y-%m-%d %H:%M:%S".to_string()],
+                ..Config::default()
+            },
+            AccountType::Custom(config) => config.as_ref().clone(),
+        }
+    }
+
+    // the built in bank profiles, e.g. for detecting which bank a file came from
+    pub fn all() -> Vec<AccountType> {
+        vec![
+            AccountType::Amex,
+            AccountType::Lloyds,
+            AccountType::Monzo,
+            AccountType::Starling,
+            AccountType::Hsbc,
+            AccountType::Barclays,
+            AccountType::Nationwide,
+            AccountType::Santander,
+            AccountType::Revolut,
+        ]
+    }
+
+    // the built in bank profile with the given name
+    pub fn named(name: &str) -> Option<AccountType> {
+        AccountType::all()
+            .into_iter()
+            .find(|a| a.config().name == name)
+    }
+}
+
+impl Default for Service {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl Service {
@@ -39,39 +230,375 @@ impl Service {
         Self {}
     }
 
-    pub fn parse(
+    // reads a csv export with the account type's profile
+    pub fn parse<R: Read>(
         &self,
         account_type: AccountType,
-        file: File,
-    ) -> Result<Vec<ParsedTransaction>, String> {
-        let conf = match account_type {
-            AccountType::Test(conf) => conf,
-            AccountType::Lloyds(conf) => conf,
-        };
+        input: R,
+    ) -> Result<Vec<Row>, ParseError> {
+        Ok(delimited::rows(&account_type.config(), input)?.collect())
+    }
+
+    // csv files need a profile to find their columns, other formats only use
+    // the profile name as the account the transactions belong to and its
+    // currency for statements that do not name their own
+    pub fn parse_data(
+        &self,
+        format: Format,
+        extraction_config: Option<&Config>,
+        data: &str,
+    ) -> Result<Vec<Row>, ParseError> {
+        let currency = extraction_config
+            .map(|c| c.currency.as_str())
+            .unwrap_or(DEFAULT_CURRENCY);
+
+        match format {
+            Format::Csv => {
+                let config = extraction_config.ok_or_else(|| {
+                    ParseError::ConfigError("A profile is required to parse csv data".to_string())
+                })?;
+                delimited::parse(config, data)
+            }
+            Format::Ofx => ofx::parse(data, extraction_config.map(|c| c.name.as_str()), currency),
+            Format::Qif => qif::parse(
+                data,
+                extraction_config.map(|c| c.name.as_str()),
+                extraction_config.map(|c| &c.date_formats[..]),
+                extraction_config,
+                currency,
+            ),
+            Format::Camt053 => {
+                camt::parse(data, extraction_config.map(|c| c.name.as_str()), currency)
+            }
+            Format::Mt940 => {
+                mt940::parse(data, extraction_config.map(|c| c.name.as_str()), currency)
+            }
+        }
+    }
+}
+
+// an import profile describing how a bank's statement export is laid out.
+// csv columns are found by header name when the file has a header and names
+// are given, otherwise by their zero based position
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Config {
+    pub name: String,
+    pub description_position: Option<i32>,
+    pub date_position: Option<i32>,
+    pub amount_position: Option<i32>,
+    // accepted header names for each column, e.g. ["Date", "Transaction Date"]
+    #[serde(default)]
+    pub description_headers: Vec<String>,
+    #[serde(default)]
+    pub date_headers: Vec<String>,
+    #[serde(default)]
+    pub amount_headers: Vec<String>,
+    #[serde(default)]
+    pub amount_mode: AmountMode,
+    pub debit_position: Option<i32>,
+    #[serde(default)]
+    pub debit_headers: Vec<String>,
+    pub credit_position: Option<i32>,
+    #[serde(default)]
+    pub credit_headers: Vec<String>,
+    pub indicator_position: Option<i32>,
+    #[serde(default)]
+    pub indicator_headers: Vec<String>,
+    // indicator values marking money going out, e.g. ["DR", "Debit"], and
+    // coming in, e.g. ["CR", "Credit"]. a row with any other value is an error
+    #[serde(default)]
+    pub debit_indicators: Vec<String>,
+    #[serde(default)]
+    pub credit_indicators: Vec<String>,
+    // tried in order, formats without a time are read as midnight
+    #[serde(default = "default_date_formats")]
+    pub date_formats: Vec<String>,
+    #[serde(default = "default_decimal_separator")]
+    pub decimal_separator: String,
+    // empty when the bank does not group thousands
+    #[serde(default = "default_thousands_separator")]
+    pub thousands_separator: String,
+    // stripped from amounts, e.g. ["£", "GBP"]
+    #[serde(default = "default_currency_symbols")]
+    pub currency_symbols: Vec<String>,
+    // preamble rows before the header, or before the first transaction of a
+    // headerless file. with header names set any further preamble is skipped
+    #[serde(default)]
+    pub skip_rows: i32,
+    #[serde(default = "default_has_header")]
+    pub has_header: bool,
+    // rows with one of these descriptions are balance lines rather than
+    // transactions, e.g. Starling's "Opening Balance"
+    #[serde(default)]
+    pub skip_descriptions: Vec<String>,
+    #[serde(default = "default_delimiter")]
+    pub delimiter: String,
+    // imported amounts are negative for money going out. set this when the
+    // bank reports money going out as a positive number, e.g. Amex
+    #[serde(default)]
+    pub invert_sign: bool,
+    // the ISO 4217 code of the account's currency
+    #[serde(default = "default_currency")]
+    pub currency: String,
+}
+
+// how a csv export records the amount of each transaction
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "sqlx", derive(sqlx::Type))]
+#[cfg_attr(
+    feature = "sqlx",
+    sqlx(type_name = "amount_mode", rename_all = "snake_case")
+)]
+#[serde(rename_all = "snake_case")]
+pub enum AmountMode {
+    // one column holding a signed amount
+    #[default]
+    Signed,
+    // money out and money in are in separate "Debit Amount" / "Credit Amount" columns
+    DebitCredit,
+    // an unsigned amount column and a column saying whether it is a debit or credit
+    Indicator,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            name: String::new(),
+            description_position: None,
+            date_position: None,
+            amount_position: None,
+            description_headers: Vec::new(),
+            date_headers: Vec::new(),
+            amount_headers: Vec::new(),
+            amount_mode: AmountMode::default(),
+            debit_position: None,
+            debit_headers: Vec::new(),
+            credit_position: None,
+            credit_headers: Vec::new(),
+            indicator_position: None,
+            indicator_headers: Vec::new(),
+            debit_indicators: Vec::new(),
+            credit_indicators: Vec::new(),
+            date_formats: default_date_formats(),
+            decimal_separator: default_decimal_separator(),
+            thousands_separator: default_thousands_separator(),
+            currency_symbols: default_currency_symbols(),
+            skip_rows: 0,
+            has_header: default_has_header(),
+            skip_descriptions: Vec::new(),
+            delimiter: default_delimiter(),
+            invert_sign: false,
+            currency: default_currency(),
+        }
+    }
+}
+
+fn default_date_formats() -> Vec<String> {
+    vec!["%d/%m/%Y".to_string()]
+}
+
+fn default_decimal_separator() -> String {
+    ".".to_string()
+}
+
+fn default_thousands_separator() -> String {
+    ",".to_string()
+}
+
+fn default_currency_symbols() -> Vec<String> {
+    vec!["£".to_string(), "$".to_string(), "€".to_string()]
+}
+
+fn default_has_header() -> bool {
+    true
+}
+
+fn default_delimiter() -> String {
+    ",".to_string()
+}
+
+fn default_currency() -> String {
+    DEFAULT_CURRENCY.to_string()
+}
+
+impl Config {
+    pub fn validate(&self) -> Result<(), ParseError> {
+        if self.name.trim().is_empty() {
+            return Err(ParseError::ConfigError(
+                "name must not be empty".to_string(),
+            ));
+        }
+
+        for (column, position, headers) in self.columns() {
+            if position.is_some_and(|p| p < 0) {
+                return Err(ParseError::ConfigError(format!(
+                    "{} position must not be negative",
+                    column
+                )));
+            }
 
-        let mut csv_reader = csv::Reader::from_reader(file);
-        let mut parsed_transactions: Vec<ParsedTransaction> = Vec::new();
-        for result in csv_reader.records() {
-            let r = result.unwrap();
-            let date = r.get(conf.date).expect("Could not find date");
-            let amount = r.get(conf.amount).expect("Could not find amount");
+            let by_name = self.has_header && !headers.is_empty();
+            if position.is_none() && !by_name {
+                return Err(ParseError::ConfigError(format!(
+                    "{} column needs a position, or header names for files with a header",
+                    column
+                )));
+            }
+        }
+
+        if self.amount_mode == AmountMode::Indicator {
+            if self.debit_indicators.is_empty() || self.credit_indicators.is_empty() {
+                return Err(ParseError::ConfigError(
+                    "debit_indicators and credit_indicators must be set when amount_mode is indicator"
+                        .to_string(),
+                ));
+            }
+
+            let both = self.debit_indicators.iter().find(|d| {
+                self.credit_indicators
+                    .iter()
+                    .any(|c| c.trim().eq_ignore_ascii_case(d.trim()))
+            });
+            if let Some(indicator) = both {
+                return Err(ParseError::ConfigError(format!(
+                    "{:?} cannot be both a debit and a credit indicator",
+                    indicator
+                )));
+            }
+        }
+
+        if self.skip_rows < 0 {
+            return Err(ParseError::ConfigError(
+                "skip_rows must not be negative".to_string(),
+            ));
+        }
+
+        if self.date_formats.iter().all(|f| f.trim().is_empty()) {
+            return Err(ParseError::ConfigError(
+                "date_formats must contain at least one format".to_string(),
+            ));
+        }
+
+        if self.decimal_separator.chars().count() != 1 {
+            return Err(ParseError::ConfigError(format!(
+                "decimal_separator must be a single character, got: {:?}",
+                self.decimal_separator
+            )));
+        }
+
+        if self.thousands_separator.chars().count() > 1
+            || self.thousands_separator == self.decimal_separator
+        {
+            return Err(ParseError::ConfigError(format!(
+                "thousands_separator must be empty or a single character other than the decimal separator, got: {:?}",
+                self.thousands_separator
+            )));
+        }
 
-            parsed_transactions.push(ParsedTransaction {
-                accounttype: conf.name.clone(),
-                date: date.to_string(),
-                amount: amount.parse::<f64>().unwrap(),
-            })
+        if !is_currency(&self.currency) {
+            return Err(ParseError::ConfigError(format!(
+                "currency must be a three letter ISO 4217 code, got: {:?}",
+                self.currency
+            )));
         }
 
-        Ok(parsed_transactions)
+        self.delimiter_byte()?;
+
+        Ok(())
+    }
+
+    // the (name, position, header names) of each column the amount mode reads
+    fn columns(&self) -> Vec<(&'static str, Option<i32>, &Vec<String>)> {
+        let mut columns = vec![
+            ("date", self.date_position, &self.date_headers),
+            (
+                "description",
+                self.description_position,
+                &self.description_headers,
+            ),
+        ];
+
+        match self.amount_mode {
+            AmountMode::Signed => {
+                columns.push(("amount", self.amount_position, &self.amount_headers));
+            }
+            AmountMode::DebitCredit => {
+                columns.push(("debit", self.debit_position, &self.debit_headers));
+                columns.push(("credit", self.credit_position, &self.credit_headers));
+            }
+            AmountMode::Indicator => {
+                columns.push(("amount", self.amount_position, &self.amount_headers));
+                columns.push((
+                    "indicator",
+                    self.indicator_position,
+                    &self.indicator_headers,
+                ));
+            }
+        }
+
+        columns
     }
 
-    pub fn parse_data(&self, data: String) -> Result<Vec<ParsedTransaction>, String> {
-        let mut csv_reader = csv::Reader::from_reader(data.as_bytes());
-        for record in csv_reader.records() {
-            println!("{:?}", record.expect("unable to read record"));
+    fn uses_header_names(&self) -> bool {
+        self.has_header
+            && self
+                .columns()
+                .iter()
+                .any(|(_, _, headers)| !headers.is_empty())
+    }
+
+    fn delimiter_byte(&self) -> Result<u8, ParseError> {
+        match self.delimiter.as_bytes() {
+            [d] => Ok(*d),
+            _ => Err(ParseError::ConfigError(format!(
+                "delimiter must be a single ascii character, got: {:?}",
+                self.delimiter
+            ))),
         }
+    }
+}
 
-        Ok(Vec::new())
+#[derive(Debug)]
+pub enum ParseError {
+    RecordError(String),
+    AmountConversionError(String),
+    DateConversionError(String),
+    ConfigError(String),
+    DetectionError(String),
+}
+
+impl Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ParseError::RecordError(e) => write!(f, "RecordError: {}", e),
+            ParseError::AmountConversionError(e) => write!(f, "AmountConversionError: {}", e),
+            ParseError::DateConversionError(e) => write!(f, "DateConversionError: {}", e),
+            ParseError::ConfigError(e) => write!(f, "ConfigError: {}", e),
+            ParseError::DetectionError(e) => write!(f, "DetectionError: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+// statement file formats understood by the import pipeline
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Format {
+    Csv,
+    Ofx,
+    Qif,
+    Camt053,
+    Mt940,
+}
+
+impl Display for Format {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Format::Csv => write!(f, "csv"),
+            Format::Ofx => write!(f, "ofx"),
+            Format::Qif => write!(f, "qif"),
+            Format::Camt053 => write!(f, "camt.053"),
+            Format::Mt940 => write!(f, "mt940"),
+        }
     }
 }
@@ -0,0 +1,220 @@
+use chrono::{NaiveDate, NaiveDateTime, NaiveTime};
+use quick_xml::{events::Event, Reader};
+
+use super::{Money, ParseError, ParsedTransaction, Row, RowError};
+
+// parses ISO 20022 camt.053 bank to customer statements. each <Ntry> becomes a
+// transaction, elements are matched on their local name so any namespace or
+// schema version (001.02 to 001.08) is accepted. a document can hold several
+// <Stmt>, each names its account. amounts are in the currency of their Ccy
+// attribute, or the given currency without one
+pub fn parse(
+    data: &str,
+    account_type: Option<&str>,
+    currency: &str,
+) -> Result<Vec<Row>, ParseError> {
+    let mut reader = Reader::from_str(data);
+    reader.trim_text(true);
+
+    let mut path: Vec<String> = Vec::new();
+    let mut account_id: Option<String> = None;
+    // each entry with the account of its statement
+    let mut entries: Vec<(Option<String>, Entry)> = Vec::new();
+    let mut current: Option<Entry> = None;
+
+    loop {
+        match reader.read_event() {
+            Ok(Event::Start(e)) => {
+                let name = String::from_utf8_lossy(e.local_name().as_ref()).to_string();
+                match name.as_str() {
+                    "Stmt" => account_id = None,
+                    "Ntry" => current = Some(Entry::default()),
+                    _ => (),
+                }
+
+                if let (Some(entry), "Amt", Some("Ntry")) = (
+                    current.as_mut(),
+                    name.as_str(),
+                    path.last().map(|p| p.as_str()),
+                ) {
+                    entry.currency = e
+                        .attributes()
+                        .flatten()
+                        .find(|a| a.key.local_name().as_ref() == b"Ccy")
+                        .map(|a| String::from_utf8_lossy(&a.value).to_string());
+                }
+                path.push(name);
+            }
+            Ok(Event::End(_)) => {
+                if path.pop().as_deref() == Some("Ntry") {
+                    if let Some(entry) = current.take() {
+                        entries.push((account_id.clone(), entry));
+                    }
+                }
+            }
+            Ok(Event::Text(t)) => {
+                let value = t
+                    .unescape()
+                    .map_err(|e| ParseError::RecordError(e.to_string()))?
+                    .trim()
+                    .to_string();
+
+                match current.as_mut() {
+                    Some(entry) => entry.set(&path, value),
+                    None => {
+                        if account_id.is_none() && is_account_id(&path) {
+                            account_id = Some(value);
+                        }
+                    }
+                }
+            }
+            Ok(Event::Eof) => break,
+            Ok(_) => (),
+            Err(e) => {
+                return Err(ParseError::RecordError(format!(
+                    "Invalid camt.053 xml at position {}: {}",
+                    reader.buffer_position(),
+                    e
+                )))
+            }
+        }
+    }
+
+    Ok(entries
+        .into_iter()
+        .enumerate()
+        .map(|(index, (account_id, e))| {
+            let account_type = match (account_type, account_id) {
+                (Some(name), _) => name.to_string(),
+                (None, Some(id)) => id,
+                (None, None) => "camt.053".to_string(),
+            };
+
+            e.into_transaction(&account_type, currency)
+                .map_err(|e| RowError::new(index + 1, e))
+        })
+        .collect())
+}
+
+fn is_account_id(path: &[String]) -> bool {
+    let path: Vec<&str> = path.iter().map(|p| p.as_str()).collect();
+
+    matches!(
+        path.as_slice(),
+        [.., "Stmt", "Acct", "Id", "IBAN"] | [.., "Stmt", "Acct", "Id", "Othr", "Id"]
+    )
+}
+
+#[derive(Default)]
+struct Entry {
+    amount: Option<String>,
+    currency: Option<String>,
+    indicator: Option<String>,
+    booking_date: Option<String>,
+    value_date: Option<String>,
+    reference: Option<String>,
+    debtor: Option<String>,
+    creditor: Option<String>,
+    remittance: Vec<String>,
+    additional_info: Option<String>,
+}
+
+impl Entry {
+    // path is the full element path, only the part below <Ntry> is inspected
+    fn set(&mut self, path: &[String], value: String) {
+        let Some(start) = path.iter().rposition(|p| p == "Ntry") else {
+            return;
+        };
+        let relative: Vec<&str> = path[start + 1..].iter().map(|p| p.as_str()).collect();
+
+        match relative.as_slice() {
+            ["Amt"] => self.amount = Some(value),
+            ["CdtDbtInd"] => self.indicator = Some(value),
+            ["BookgDt", _] => self.booking_date = Some(value),
+            ["ValDt", _] => self.value_date = Some(value),
+            ["AcctSvcrRef"] => self.reference = Some(value),
+            ["NtryRef"] if self.reference.is_none() => self.reference = Some(value),
+            ["AddtlNtryInf"] => self.additional_info = Some(value),
+            [.., "RltdPties", "Dbtr", "Nm"] | [.., "RltdPties", "Dbtr", "Pty", "Nm"] => {
+                self.debtor.get_or_insert(value);
+            }
+            [.., "RltdPties", "Cdtr", "Nm"] | [.., "RltdPties", "Cdtr", "Pty", "Nm"] => {
+                self.creditor.get_or_insert(value);
+            }
+            [.., "RmtInf", "Ustrd"] => self.remittance.push(value),
+            [.., "RmtInf", "Strd", "CdtrRefInf", "Ref"] => self.remittance.push(value),
+            _ => (),
+        }
+    }
+
+    fn into_transaction(
+        self,
+        account_type: &str,
+        currency: &str,
+    ) -> Result<ParsedTransaction, ParseError> {
+        let amount = self
+            .amount
+            .ok_or_else(|| ParseError::RecordError("Ntry is missing Amt".to_string()))?;
+        let mut amount = Money::parse(&amount, self.currency.as_deref().unwrap_or(currency))?;
+
+        amount.amount = match self.indicator.as_deref() {
+            Some("DBIT") => -amount.amount.abs(),
+            Some("CRDT") => amount.amount.abs(),
+            other => {
+                return Err(ParseError::RecordError(format!(
+                    "Ntry has an invalid CdtDbtInd: {:?}",
+                    other
+                )))
+            }
+        };
+
+        let booking_date = self
+            .booking_date
+            .as_deref()
+            .ok_or_else(|| ParseError::RecordError("Ntry is missing BookgDt".to_string()))?;
+
+        let value_date = match self.value_date.as_deref() {
+            Some(d) => Some(parse_date(d)?),
+            None => None,
+        };
+
+        // money going out is paid to the creditor, money coming in is from the debtor
+        let counterparty = if amount.is_negative() {
+            self.creditor
+        } else {
+            self.debtor
+        };
+
+        let remittance = match self.remittance.is_empty() {
+            true => self.additional_info,
+            false => Some(self.remittance.join(" ")),
+        };
+
+        let description = [counterparty, remittance]
+            .into_iter()
+            .flatten()
+            .collect::<Vec<String>>()
+            .join(" ");
+
+        Ok(ParsedTransaction {
+            account_type: account_type.to_string(),
+            payment_date: parse_date(booking_date)?,
+            amount,
+            description,
+            external_id: self.reference,
+            category: None,
+            value_date,
+        })
+    }
+}
+
+// dates are either <Dt>2024-02-03</Dt> or <DtTm>2024-02-03T10:15:00+01:00</DtTm>
+fn parse_date(value: &str) -> Result<NaiveDateTime, ParseError> {
+    if let Ok(date) = NaiveDate::parse_from_str(value, "%Y-%m-%d") {
+        return Ok(NaiveDateTime::new(date, NaiveTime::MIN));
+    }
+
+    let local = value.get(0..19).unwrap_or(value);
+    NaiveDateTime::parse_from_str(local, "%Y-%m-%dT%H:%M:%S")
+        .map_err(|e| ParseError::DateConversionError(format!("{}: {}", value, e)))
+}
@@ -0,0 +1,317 @@
+use std::io;
+
+use csv::StringRecord;
+use rust_decimal::Decimal;
+
+use super::{locale, AmountMode, Config, Money, ParseError, ParsedTransaction, Row, RowError};
+
+// how many rows after skip_rows are searched for a header matching the profile's names
+pub const HEADER_SEARCH_ROWS: usize = 20;
+
+// parses csv style exports, finding the date, amount and description columns
+// from the header names or positions of the given profile
+pub fn parse(extraction_config: &Config, data: &str) -> Result<Vec<Row>, ParseError> {
+    Ok(rows(extraction_config, data.as_bytes())?.collect())
+}
+
+// reads the rows one at a time so large exports are never held in memory,
+// the header is found before the first row is returned
+pub fn rows<R: io::Read>(extraction_config: &Config, input: R) -> Result<Rows<R>, ParseError> {
+    let mut records = reader(extraction_config, input)?.into_records();
+    for _ in 0..extraction_config.skip_rows {
+        if records.next().is_none() {
+            break;
+        }
+    }
+
+    let columns = match extraction_config.has_header {
+        true => {
+            let mut found: Option<Columns> = None;
+            for record in records.by_ref().take(header_search_rows(extraction_config)) {
+                let r = record.map_err(|e| ParseError::RecordError(e.to_string()))?;
+                if let Some(columns) = Columns::from_header(extraction_config, &r) {
+                    found = Some(columns);
+                    break;
+                }
+            }
+
+            found.ok_or_else(|| {
+                ParseError::RecordError(format!(
+                    "Unable to find a header row matching profile {}",
+                    extraction_config.name
+                ))
+            })?
+        }
+        false => Columns::from_positions(extraction_config)?,
+    };
+
+    Ok(Rows {
+        extraction_config: extraction_config.clone(),
+        columns,
+        records,
+    })
+}
+
+pub struct Rows<R: io::Read> {
+    extraction_config: Config,
+    columns: Columns,
+    records: csv::StringRecordsIntoIter<R>,
+}
+
+impl<R: io::Read> Iterator for Rows<R> {
+    type Item = Row;
+
+    fn next(&mut self) -> Option<Row> {
+        let (extraction_config, columns) = (&self.extraction_config, &self.columns);
+        let row = match self
+            .records
+            .find(|r| !is_skipped(extraction_config, columns, r))?
+        {
+            Ok(r) => {
+                let line = r.position().map(|p| p.line()).unwrap_or_default() as usize;
+                row(extraction_config, columns, &r, line)
+            }
+            Err(e) => {
+                let line = e.position().map(|p| p.line()).unwrap_or_default();
+                Err(RowError::new(
+                    line as usize,
+                    ParseError::RecordError(e.to_string()),
+                ))
+            }
+        };
+
+        Some(row)
+    }
+}
+
+// balance lines named in the profile's skip_descriptions are not transactions
+fn is_skipped(
+    extraction_config: &Config,
+    columns: &Columns,
+    record: &csv::Result<StringRecord>,
+) -> bool {
+    let Ok(r) = record else {
+        return false;
+    };
+    let description = string_or_empty(r.get(columns.description)).trim();
+
+    extraction_config
+        .skip_descriptions
+        .iter()
+        .any(|d| d.trim().eq_ignore_ascii_case(description))
+}
+
+fn row(extraction_config: &Config, columns: &Columns, record: &StringRecord, line: usize) -> Row {
+    let date = string_or_empty(record.get(columns.date));
+    let description = string_or_empty(record.get(columns.description));
+
+    let payment_date = locale::date(&extraction_config.date_formats, date)
+        .map_err(|e| RowError::column(line, "date", date, e))?;
+    let amount = columns.amount(extraction_config, record, line)?;
+
+    Ok(ParsedTransaction {
+        account_type: extraction_config.name.clone(),
+        amount: Money::new(amount, &extraction_config.currency),
+        payment_date,
+        description: description.trim().to_string(),
+        external_id: None,
+        category: None,
+        value_date: None,
+    })
+}
+
+// number of the profile's columns found by name in the file's header row, used
+// to rank profiles when detecting which one a file was exported with
+pub fn header_matches(extraction_config: &Config, data: &str) -> usize {
+    if !extraction_config.uses_header_names() {
+        return 0;
+    }
+
+    let Ok(mut csv_reader) = reader(extraction_config, data.as_bytes()) else {
+        return 0;
+    };
+
+    csv_reader
+        .records()
+        .skip(extraction_config.skip_rows as usize)
+        .take(HEADER_SEARCH_ROWS)
+        .filter_map(|r| r.ok())
+        .map(|r| {
+            extraction_config
+                .columns()
+                .iter()
+                .filter(|(_, _, names)| find_column(&r, names).is_some())
+                .count()
+        })
+        .max()
+        .unwrap_or_default()
+}
+
+fn reader<R: io::Read>(extraction_config: &Config, input: R) -> Result<csv::Reader<R>, ParseError> {
+    Ok(csv::ReaderBuilder::new()
+        .has_headers(false)
+        .flexible(true)
+        .delimiter(extraction_config.delimiter_byte()?)
+        .from_reader(input))
+}
+
+// without header names the header is the first row after skip_rows
+fn header_search_rows(extraction_config: &Config) -> usize {
+    match extraction_config.uses_header_names() {
+        true => HEADER_SEARCH_ROWS,
+        false => 1,
+    }
+}
+
+struct Columns {
+    date: usize,
+    description: usize,
+    amount: Option<usize>,
+    debit: Option<usize>,
+    credit: Option<usize>,
+    indicator: Option<usize>,
+}
+
+impl Columns {
+    fn from_positions(extraction_config: &Config) -> Result<Columns, ParseError> {
+        Columns::resolve(extraction_config, |_, position| {
+            position.map(|p| p as usize)
+        })
+        .map_err(|column| {
+            ParseError::ConfigError(format!(
+                "profile {} has no {} position for headerless files",
+                extraction_config.name, column
+            ))
+        })
+    }
+
+    // a row is the header when every column given by name can be found in it,
+    // columns without names fall back to their position
+    fn from_header(extraction_config: &Config, header: &StringRecord) -> Option<Columns> {
+        Columns::resolve(extraction_config, |names, position| {
+            match names.is_empty() {
+                true => position.map(|p| p as usize),
+                false => find_column(header, names),
+            }
+        })
+        .ok()
+    }
+
+    // finds each column the profile's amount mode reads, or the name of the
+    // first one that could not be found
+    fn resolve(
+        extraction_config: &Config,
+        find: impl Fn(&[String], Option<i32>) -> Option<usize>,
+    ) -> Result<Columns, &'static str> {
+        let mut columns = Columns {
+            date: 0,
+            description: 0,
+            amount: None,
+            debit: None,
+            credit: None,
+            indicator: None,
+        };
+
+        for (column, position, names) in extraction_config.columns() {
+            let index = find(names, position).ok_or(column)?;
+            match column {
+                "date" => columns.date = index,
+                "description" => columns.description = index,
+                "amount" => columns.amount = Some(index),
+                "debit" => columns.debit = Some(index),
+                "credit" => columns.credit = Some(index),
+                _ => columns.indicator = Some(index),
+            }
+        }
+
+        Ok(columns)
+    }
+
+    // the signed amount of a row, negative for money going out
+    fn amount(
+        &self,
+        extraction_config: &Config,
+        record: &StringRecord,
+        line: usize,
+    ) -> Result<Decimal, RowError> {
+        let cell =
+            |column: Option<usize>| string_or_empty(column.and_then(|c| record.get(c))).trim();
+        let amount = |column: &str, value: &str| {
+            locale::amount(extraction_config, value)
+                .map_err(|e| RowError::column(line, column, value, e))
+        };
+
+        let amount = match extraction_config.amount_mode {
+            AmountMode::Signed => amount("amount", cell(self.amount))?,
+            AmountMode::DebitCredit => {
+                let debit = cell(self.debit);
+                let credit = cell(self.credit);
+                if debit.is_empty() && credit.is_empty() {
+                    return Err(RowError::new(
+                        line,
+                        ParseError::AmountConversionError(
+                            "row has neither a debit nor a credit amount".to_string(),
+                        ),
+                    ));
+                }
+
+                let debit = match debit.is_empty() {
+                    true => Decimal::ZERO,
+                    false => amount("debit", debit)?.abs(),
+                };
+                let credit = match credit.is_empty() {
+                    true => Decimal::ZERO,
+                    false => amount("credit", credit)?.abs(),
+                };
+
+                credit - debit
+            }
+            AmountMode::Indicator => {
+                let amount = amount("amount", cell(self.amount))?.abs();
+                let indicator = cell(self.indicator);
+                let is = |indicators: &[String]| {
+                    indicators
+                        .iter()
+                        .any(|i| i.trim().eq_ignore_ascii_case(indicator))
+                };
+
+                if is(&extraction_config.debit_indicators) {
+                    -amount
+                } else if is(&extraction_config.credit_indicators) {
+                    amount
+                } else {
+                    return Err(RowError::column(
+                        line,
+                        "indicator",
+                        indicator,
+                        ParseError::AmountConversionError(
+                            "neither a debit nor a credit indicator".to_string(),
+                        ),
+                    ));
+                }
+            }
+        };
+
+        match extraction_config.invert_sign {
+            true => Ok(-amount),
+            false => Ok(amount),
+        }
+    }
+}
+
+fn find_column(header: &StringRecord, names: &[String]) -> Option<usize> {
+    if names.is_empty() {
+        return None;
+    }
+
+    header.iter().position(|cell| {
+        let cell = cell.trim_start_matches('\u{feff}').trim();
+        names
+            .iter()
+            .any(|name| name.trim().eq_ignore_ascii_case(cell))
+    })
+}
+
+fn string_or_empty(s: Option<&str>) -> &str {
+    s.unwrap_or_default()
+}
@@ -0,0 +1,219 @@
+use super::{delimited, Config, Format, ParseError};
+
+// number of transaction rows sampled when checking whether a profile parses a file
+const SAMPLE_ROWS: usize = 20;
+
+const DELIMITERS: [char; 4] = [',', ';', '\t', '|'];
+
+// words expected in the header names of the amount and description columns
+const AMOUNT_HEADERS: [&str; 6] = ["amount", "value", "debit", "credit", "paid", "money"];
+const INDICATOR_HEADERS: [&str; 4] = ["type", "indicator", "dr/cr", "cr/dr"];
+const DESCRIPTION_HEADERS: [&str; 7] = [
+    "desc",
+    "detail",
+    "narrative",
+    "payee",
+    "name",
+    "memo",
+    "reference",
+];
+
+pub struct Detected {
+    pub format: Format,
+    // only set for csv files, other formats describe their own layout
+    pub profile: Option<Config>,
+}
+
+// works out the format of an uploaded statement and, for csv files, which of
+// the given profiles it was exported with
+pub fn statement(
+    filename: Option<&str>,
+    data: &str,
+    profiles: &[Config],
+) -> Result<Detected, ParseError> {
+    let format = format(filename, data);
+    if format != Format::Csv {
+        return Ok(Detected {
+            format,
+            profile: None,
+        });
+    }
+
+    let profile = profile(data, profiles)?;
+
+    Ok(Detected {
+        format,
+        profile: Some(profile),
+    })
+}
+
+// picks the format from the file extension, falling back to sniffing the content
+pub fn format(filename: Option<&str>, data: &str) -> Format {
+    let extension = filename
+        .and_then(|f| f.rsplit_once('.'))
+        .map(|(_, ext)| ext.to_ascii_lowercase());
+
+    match extension.as_deref() {
+        Some("ofx") | Some("qfx") => return Format::Ofx,
+        Some("qif") => return Format::Qif,
+        Some("sta") | Some("mt940") => return Format::Mt940,
+        Some("csv") => return Format::Csv,
+        _ => (),
+    }
+
+    let head: String = data
+        .chars()
+        .take(1024)
+        .collect::<String>()
+        .to_ascii_uppercase();
+    if head.contains("OFXHEADER") || head.contains("<OFX>") {
+        return Format::Ofx;
+    }
+
+    if head.contains("BKTOCSTMRSTMT") || head.contains("CAMT.053") {
+        return Format::Camt053;
+    }
+
+    if head.contains(":20:") && (head.contains(":25:") || head.contains(":60F:")) {
+        return Format::Mt940;
+    }
+
+    let first_line = head.trim_start().lines().next().unwrap_or_default();
+    if first_line.starts_with("!TYPE:")
+        || first_line.starts_with("!ACCOUNT")
+        || first_line.starts_with("!OPTION")
+    {
+        return Format::Qif;
+    }
+
+    Format::Csv
+}
+
+// a profile is a candidate when it uses the delimiter found in the file and can
+// parse the first rows. ties are broken on how well the header row names fit
+// the profile's columns
+fn profile(data: &str, profiles: &[Config]) -> Result<Config, ParseError> {
+    let delimiter = sniff_delimiter(data);
+
+    let candidates: Vec<&Config> = profiles
+        .iter()
+        .filter(|p| delimiter.is_none_or(|d| p.delimiter == d.to_string()))
+        .filter(|p| parses_sample(data, p))
+        .collect();
+
+    if candidates.is_empty() {
+        return Err(ParseError::DetectionError(format!(
+            "No import profile matches this file, checked: {}",
+            names(profiles.iter())
+        )));
+    }
+
+    if candidates.len() == 1 {
+        return Ok(candidates[0].clone());
+    }
+
+    let scores: Vec<usize> = candidates.iter().map(|p| header_score(data, p)).collect();
+    let best = scores.iter().copied().max().unwrap_or_default();
+    let best_candidates: Vec<&Config> = candidates
+        .iter()
+        .zip(scores.iter())
+        .filter(|(_, score)| **score == best)
+        .map(|(p, _)| *p)
+        .collect();
+
+    if best_candidates.len() == 1 {
+        return Ok(best_candidates[0].clone());
+    }
+
+    Err(ParseError::DetectionError(format!(
+        "More than one import profile matches this file, specify one of: {}",
+        names(best_candidates.into_iter())
+    )))
+}
+
+fn names<'a>(profiles: impl Iterator<Item = &'a Config>) -> String {
+    profiles
+        .map(|p| p.name.as_str())
+        .collect::<Vec<&str>>()
+        .join(", ")
+}
+
+// the delimiter that splits the first lines into the same, largest, number of fields
+fn sniff_delimiter(data: &str) -> Option<char> {
+    let lines: Vec<&str> = data
+        .lines()
+        .filter(|l| !l.trim().is_empty())
+        .take(5)
+        .collect();
+
+    DELIMITERS
+        .iter()
+        .filter_map(|d| {
+            let counts: Vec<usize> = lines.iter().map(|l| l.matches(*d).count()).collect();
+            let last = *counts.last()?;
+            match last > 0 && counts.iter().all(|c| *c == last) {
+                true => Some((*d, last)),
+                false => None,
+            }
+        })
+        .max_by_key(|(_, count)| *count)
+        .map(|(d, _)| d)
+}
+
+fn parses_sample(data: &str, profile: &Config) -> bool {
+    let sample: Vec<&str> = data
+        .lines()
+        .take(profile.skip_rows.max(0) as usize + delimited::HEADER_SEARCH_ROWS + SAMPLE_ROWS)
+        .collect();
+
+    // a stray bad row should not rule the profile out, at least half have to parse
+    let Ok(rows) = delimited::parse(profile, &sample.join("\n")) else {
+        return false;
+    };
+    let valid = rows.iter().filter(|r| r.is_ok()).count();
+
+    valid > 0 && valid * 2 >= rows.len()
+}
+
+// profiles with header names score on the columns they found by name, others
+// on whether the header names at their positions look like the right columns
+fn header_score(data: &str, profile: &Config) -> usize {
+    let by_name = delimited::header_matches(profile, data);
+    if by_name > 0 {
+        return by_name * 2;
+    }
+
+    if !profile.has_header {
+        return 0;
+    }
+
+    let Some(header) = data.lines().nth(profile.skip_rows.max(0) as usize) else {
+        return 0;
+    };
+
+    let cells: Vec<String> = header
+        .split(profile.delimiter.as_str())
+        .map(|c| c.trim().trim_matches('"').to_ascii_lowercase())
+        .collect();
+    let cell = |position: Option<i32>| {
+        position
+            .and_then(|p| cells.get(p as usize).cloned())
+            .unwrap_or_default()
+    };
+
+    let mut score = 0;
+    for (column, position, _) in profile.columns() {
+        let keywords: &[&str] = match column {
+            "date" => &["date"],
+            "description" => &DESCRIPTION_HEADERS,
+            "indicator" => &INDICATOR_HEADERS,
+            _ => &AMOUNT_HEADERS,
+        };
+
+        if keywords.iter().any(|k| cell(position).contains(k)) {
+            score += 1;
+        }
+    }
+
+    score
+}
@@ -0,0 +1,232 @@
+use std::str::FromStr;
+
+use chrono::{NaiveDate, NaiveDateTime, NaiveTime};
+use rust_decimal::Decimal;
+
+use super::{Config, ParseError};
+
+// parses an amount written the way the profile's bank formats numbers, e.g.
+// "£1,234.56", "1.234,56", "(12.00)" or "12.00-". parentheses and a trailing
+// minus both mark a negative amount
+pub fn amount(extraction_config: &Config, value: &str) -> Result<Decimal, ParseError> {
+    let invalid =
+        |reason: &str| ParseError::AmountConversionError(format!("{:?}: {}", value, reason));
+
+    let mut number: String = value.chars().filter(|c| !c.is_whitespace()).collect();
+    for symbol in &extraction_config.currency_symbols {
+        if !symbol.is_empty() {
+            number = number.replace(symbol.as_str(), "");
+        }
+    }
+
+    let mut negative = false;
+    if let Some(inner) = number.strip_prefix('(').and_then(|n| n.strip_suffix(')')) {
+        negative = true;
+        number = inner.to_string();
+    }
+
+    if let Some(inner) = number.strip_suffix('-') {
+        negative = true;
+        number = inner.to_string();
+    } else if let Some(inner) = number.strip_prefix('-') {
+        negative = true;
+        number = inner.to_string();
+    } else if let Some(inner) = number.strip_prefix('+') {
+        number = inner.to_string();
+    }
+
+    // a separator in the wrong place means the number is in another format,
+    // e.g. "1.234,56" read with a decimal point, and is rejected rather than
+    // read as a different amount
+    let (whole, fraction) = match number.split_once(extraction_config.decimal_separator.as_str()) {
+        Some((whole, fraction)) => (whole, Some(fraction)),
+        None => (number.as_str(), None),
+    };
+    let thousands = extraction_config.thousands_separator.as_str();
+    if !thousands.is_empty() {
+        if fraction.is_some_and(|f| f.contains(thousands)) {
+            return Err(invalid("thousands separator after the decimal separator"));
+        }
+
+        let mut groups = whole.split(thousands);
+        let first = groups.next().unwrap_or_default();
+        let grouped = groups.all(|g| g.len() == 3);
+        if whole.contains(thousands) && (first.is_empty() || first.len() > 3 || !grouped) {
+            return Err(invalid(
+                "thousands separator not between groups of three digits",
+            ));
+        }
+    }
+
+    // checked before the decimal separator becomes a '.', so a '.' the
+    // profile doesn't use, e.g. "1.234" with a decimal comma, is rejected
+    let whole = whole.replace(thousands, "");
+    let digits = |part: &str| part.chars().all(|c| c.is_ascii_digit());
+    if whole.is_empty() && fraction.is_none_or(str::is_empty) {
+        return Err(invalid("no amount"));
+    }
+
+    if !digits(&whole) || !fraction.is_none_or(digits) {
+        return Err(invalid("not a number in this profile's number format"));
+    }
+
+    let number = match fraction {
+        Some(fraction) => format!("{}.{}", whole, fraction),
+        None => whole,
+    };
+
+    let amount = Decimal::from_str(&number).map_err(|e| invalid(&e.to_string()))?;
+
+    match negative {
+        true => Ok(-amount),
+        false => Ok(amount),
+    }
+}
+
+// tries each of the formats in turn, dates without a time are read as midnight
+pub fn date(date_formats: &[String], value: &str) -> Result<NaiveDateTime, ParseError> {
+    let value = value.trim();
+
+    date_formats
+        .iter()
+        .find_map(|f| {
+            NaiveDateTime::parse_from_str(value, f)
+                .or_else(|_| {
+                    NaiveDate::parse_from_str(value, f).map(|d| d.and_time(NaiveTime::MIN))
+                })
+                .ok()
+        })
+        .ok_or_else(|| {
+            ParseError::DateConversionError(format!(
+                "{:?} does not match any of the formats: {}",
+                value,
+                date_formats.join(", ")
+            ))
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn uk() -> Config {
+        Config::default()
+    }
+
+    // numbers written the german way, e.g. 1.234,56 €
+    fn german() -> Config {
+        Config {
+            decimal_separator: ",".to_string(),
+            thousands_separator: ".".to_string(),
+            ..Config::default()
+        }
+    }
+
+    fn decimal(value: &str) -> Decimal {
+        Decimal::from_str(value).unwrap()
+    }
+
+    fn read(config: &Config, value: &str) -> Decimal {
+        amount(config, value).unwrap_or_else(|e| panic!("{}: {}", value, e))
+    }
+
+    #[test]
+    fn currency_symbols_are_stripped() {
+        assert_eq!(read(&uk(), "£1,234.56"), decimal("1234.56"));
+        assert_eq!(read(&uk(), "-$12.00"), decimal("-12.00"));
+        assert_eq!(read(&german(), "1.234,56 €"), decimal("1234.56"));
+    }
+
+    #[test]
+    fn negative_amounts() {
+        assert_eq!(read(&uk(), "(12.00)"), decimal("-12.00"));
+        assert_eq!(read(&uk(), "(£1,000.00)"), decimal("-1000.00"));
+        assert_eq!(read(&uk(), "12.00-"), decimal("-12.00"));
+        assert_eq!(read(&uk(), "-12.00"), decimal("-12.00"));
+        assert_eq!(read(&uk(), "+12.00"), decimal("12.00"));
+    }
+
+    #[test]
+    fn swapped_separators() {
+        assert_eq!(read(&german(), "1.234,56"), decimal("1234.56"));
+        assert_eq!(read(&german(), "1.234.567,8"), decimal("1234567.8"));
+        assert_eq!(read(&german(), "12,5"), decimal("12.5"));
+        assert_eq!(read(&german(), "1.234,56-"), decimal("-1234.56"));
+    }
+
+    #[test]
+    fn banks_without_thousands_separators() {
+        let config = Config {
+            thousands_separator: String::new(),
+            ..Config::default()
+        };
+
+        assert_eq!(read(&config, "1234.56"), decimal("1234.56"));
+        assert!(amount(&config, "1,234.56").is_err());
+    }
+
+    #[test]
+    fn points_the_profile_does_not_use_are_rejected() {
+        for thousands in ["'", ""] {
+            let config = Config {
+                decimal_separator: ",".to_string(),
+                thousands_separator: thousands.to_string(),
+                ..Config::default()
+            };
+
+            assert_eq!(read(&config, "1234,5"), decimal("1234.5"));
+            for value in ["1.234", "1.234,56", "12,3.4"] {
+                assert!(amount(&config, value).is_err(), "{} was accepted", value);
+            }
+        }
+    }
+
+    #[test]
+    fn ambiguous_amounts_are_rejected() {
+        // each would otherwise be read as a different amount than written
+        for value in ["1.234,56", "12,34", "1,2345.00", ",123.00", "1.23,4"] {
+            assert!(amount(&uk(), value).is_err(), "{} was accepted", value);
+        }
+        for value in ["1,234.56", "12.34", "1.2345,00"] {
+            assert!(amount(&german(), value).is_err(), "{} was accepted", value);
+        }
+
+        for value in ["", "£", "()", "12.00 GBP", "1.2.3", "twelve"] {
+            assert!(amount(&uk(), value).is_err(), "{:?} was accepted", value);
+        }
+    }
+
+    #[test]
+    fn dates_use_the_profile_formats_in_turn() {
+        let formats = vec!["%d/%m/%Y".to_string(), "%Y-%m-%d %H:%M:%S".to_string()];
+        let at = |y, m, d, h, min, s| {
+            NaiveDate::from_ymd_opt(y, m, d)
+                .and_then(|d| d.and_hms_opt(h, min, s))
+                .unwrap()
+        };
+
+        assert_eq!(
+            date(&formats, "03/02/2024").unwrap_or_else(|e| panic!("{}", e)),
+            at(2024, 2, 3, 0, 0, 0)
+        );
+        assert_eq!(
+            date(&formats, " 2024-02-03 13:05:10 ").unwrap_or_else(|e| panic!("{}", e)),
+            at(2024, 2, 3, 13, 5, 10)
+        );
+
+        let us = vec!["%m/%d/%Y".to_string()];
+        assert_eq!(
+            date(&us, "03/02/2024").unwrap_or_else(|e| panic!("{}", e)),
+            at(2024, 3, 2, 0, 0, 0)
+        );
+    }
+
+    #[test]
+    fn dates_in_other_formats_are_rejected() {
+        let formats = vec!["%d/%m/%Y".to_string()];
+
+        for value in ["2024-02-03", "31/02/2024", "13/13/2024", ""] {
+            assert!(date(&formats, value).is_err(), "{:?} was accepted", value);
+        }
+    }
+}
@@ -0,0 +1,141 @@
+use std::{
+    fmt::{self, Display},
+    ops::Neg,
+    str::FromStr,
+};
+
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+
+use super::ParseError;
+
+// the currency of statements that do not name one, and of profiles without one
+pub const DEFAULT_CURRENCY: &str = "GBP";
+
+// an exact amount in an ISO 4217 currency, e.g. -12.50 GBP. amounts are
+// decimals so adding them up never drifts by pennies the way floats do, they
+// are serialized as strings to keep them exact in json
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct Money {
+    pub amount: Decimal,
+    pub currency: String,
+}
+
+impl Money {
+    pub fn new(amount: Decimal, currency: &str) -> Self {
+        Self {
+            amount,
+            currency: currency.trim().to_ascii_uppercase(),
+        }
+    }
+
+    // reads a plain decimal such as "-1234.56", with no grouping or symbols
+    pub fn parse(amount: &str, currency: &str) -> Result<Self, ParseError> {
+        let invalid =
+            |e: &dyn Display| ParseError::AmountConversionError(format!("{:?}: {}", amount, e));
+
+        // one sign at most, a + is dropped
+        let unsigned = match amount.trim().strip_prefix('+') {
+            Some(rest) if rest.starts_with(['+', '-']) => return Err(invalid(&"two signs")),
+            Some(rest) => rest,
+            None => amount.trim(),
+        };
+        let amount = Decimal::from_str(unsigned).map_err(|e| invalid(&e))?;
+
+        Ok(Self::new(amount, currency))
+    }
+
+    // money going out of the account
+    pub fn is_negative(&self) -> bool {
+        self.amount.is_sign_negative() && !self.amount.is_zero()
+    }
+
+    pub fn is_zero(&self) -> bool {
+        self.amount.is_zero()
+    }
+}
+
+impl Neg for Money {
+    type Output = Money;
+
+    fn neg(self) -> Money {
+        Money {
+            amount: -self.amount,
+            currency: self.currency,
+        }
+    }
+}
+
+impl Display for Money {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} {}", self.amount, self.currency)
+    }
+}
+
+// a currency code is three letters, e.g. GBP
+pub fn is_currency(code: &str) -> bool {
+    code.len() == 3 && code.chars().all(|c| c.is_ascii_alphabetic())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn money(amount: &str) -> Money {
+        Money::parse(amount, "gbp").unwrap_or_else(|e| panic!("{}: {}", amount, e))
+    }
+
+    fn decimal(value: &str) -> Decimal {
+        Decimal::from_str(value).unwrap()
+    }
+
+    #[test]
+    fn signs_and_whitespace() {
+        assert_eq!(money("-1234.56").amount, decimal("-1234.56"));
+        assert_eq!(money("+12.50").amount, decimal("12.50"));
+        assert_eq!(money(" 12.50\t").amount, decimal("12.50"));
+        assert_eq!(money("  +0.01 ").amount, decimal("0.01"));
+        assert_eq!(money("12").currency, "GBP");
+    }
+
+    #[test]
+    fn bad_amounts_are_rejected() {
+        for amount in [
+            "", " ", "+", "++12", "+-12", "1,234.56", "£12", "12 GBP", "twelve",
+        ] {
+            assert!(
+                Money::parse(amount, "GBP").is_err(),
+                "{:?} was accepted",
+                amount
+            );
+        }
+    }
+
+    #[test]
+    fn negating_keeps_the_currency() {
+        let refund = -money("-12.50");
+
+        assert_eq!(refund, Money::new(decimal("12.50"), "GBP"));
+        assert_eq!(-money("0").amount, Decimal::ZERO);
+    }
+
+    #[test]
+    fn only_money_going_out_is_negative() {
+        assert!(money("-0.01").is_negative());
+        assert!(!money("0.01").is_negative());
+        assert!(!money("0").is_negative());
+        // negating zero gives a negative zero, which is still no money
+        assert!(!(-money("0")).is_negative());
+        assert!(!money("-0.00").is_negative());
+    }
+
+    #[test]
+    fn currencies_are_three_letters() {
+        for code in ["GBP", "eur", "Usd"] {
+            assert!(is_currency(code), "{} was rejected", code);
+        }
+        for code in ["", "GB", "GBPS", "G1P", "£££", "GB "] {
+            assert!(!is_currency(code), "{:?} was accepted", code);
+        }
+    }
+}
@@ -0,0 +1,216 @@
+use chrono::{Datelike, NaiveDate, NaiveDateTime, NaiveTime};
+
+use super::{is_currency, Money, ParseError, ParsedTransaction, Row, RowError};
+
+// parses SWIFT MT940 customer statements. every :61: statement line becomes a
+// transaction, the :86: field following it provides the counterparty and
+// remittance information. amounts are in the currency of the statement's
+// opening balance, or the given currency when it has none
+pub fn parse(
+    data: &str,
+    account_type: Option<&str>,
+    currency: &str,
+) -> Result<Vec<Row>, ParseError> {
+    let fields = fields(data);
+
+    let mut rows: Vec<Row> = Vec::new();
+    let mut statement_account = account_type.unwrap_or("MT940").to_string();
+    let mut statement_currency = currency;
+    for (index, (tag, value)) in fields.iter().enumerate() {
+        // a file can hold several statements, each names its account in :25:
+        // unless a profile names it
+        if tag == "25" && account_type.is_none() {
+            statement_account = value.trim().to_string();
+        }
+
+        // :60F:/:60M: D/C, YYMMDD, currency, amount
+        if tag == "60F" || tag == "60M" {
+            if let Some(code) = value.get(7..10).filter(|c| is_currency(c)) {
+                statement_currency = code;
+            }
+        }
+
+        if tag != "61" {
+            continue;
+        }
+
+        let information = match fields.get(index + 1) {
+            Some((next_tag, next_value)) if next_tag == "86" => Some(next_value.as_str()),
+            _ => None,
+        };
+
+        let entry = rows.len() + 1;
+        rows.push(
+            statement_line(value, information, &statement_account, statement_currency)
+                .map_err(|e| RowError::new(entry, e)),
+        );
+    }
+
+    Ok(rows)
+}
+
+// splits the message into (tag, value) pairs, joining continuation lines and
+// dropping the SWIFT block wrappers some banks leave in their exports
+fn fields(data: &str) -> Vec<(String, String)> {
+    let mut fields: Vec<(String, String)> = Vec::new();
+
+    for line in data.lines() {
+        let line = line.trim_end();
+        if line.is_empty() || line.starts_with('{') || line == "-" || line.starts_with("-}") {
+            continue;
+        }
+
+        let tag = line
+            .strip_prefix(':')
+            .and_then(|rest| rest.split_once(':'))
+            .filter(|(tag, _)| tag.len() <= 3 && tag.chars().all(|c| c.is_ascii_alphanumeric()));
+
+        match tag {
+            Some((tag, value)) => fields.push((tag.to_string(), value.to_string())),
+            None => {
+                if let Some((_, value)) = fields.last_mut() {
+                    value.push('\n');
+                    value.push_str(line);
+                }
+            }
+        }
+    }
+
+    fields
+}
+
+// :61:YYMMDD[MMDD]{D|C|RD|RC}[funds code]amount{N|F|S}xxx reference[//bank reference]
+fn statement_line(
+    value: &str,
+    information: Option<&str>,
+    account_type: &str,
+    currency: &str,
+) -> Result<ParsedTransaction, ParseError> {
+    let invalid = || ParseError::RecordError(format!("Invalid :61: statement line: {}", value));
+    let first_line = value.lines().next().unwrap_or_default();
+
+    let value_date = first_line
+        .get(0..6)
+        .and_then(|d| NaiveDate::parse_from_str(d, "%y%m%d").ok())
+        .ok_or_else(|| ParseError::DateConversionError(format!("Invalid :61: date: {}", value)))?;
+    let mut rest = &first_line[6..];
+
+    // the entry date has no year so it takes the value date's, moved across a new year
+    let mut booking_date = value_date;
+    let entry_date = rest
+        .get(..4)
+        .filter(|d| d.chars().all(|c| c.is_ascii_digit()));
+    if let Some(entry_date) = entry_date {
+        let month: u32 = entry_date[0..2].parse().map_err(|_| invalid())?;
+        let day: u32 = entry_date[2..4].parse().map_err(|_| invalid())?;
+
+        let year = match (value_date.month(), month) {
+            (12, 1) => value_date.year() + 1,
+            (1, 12) => value_date.year() - 1,
+            _ => value_date.year(),
+        };
+        booking_date = NaiveDate::from_ymd_opt(year, month, day).ok_or_else(invalid)?;
+        rest = &rest[4..];
+    }
+
+    let (negative, after_mark) = if let Some(r) = rest.strip_prefix("RD") {
+        (false, r)
+    } else if let Some(r) = rest.strip_prefix("RC") {
+        (true, r)
+    } else if let Some(r) = rest.strip_prefix('D') {
+        (true, r)
+    } else if let Some(r) = rest.strip_prefix('C') {
+        (false, r)
+    } else {
+        return Err(invalid());
+    };
+
+    // an optional third letter of the currency code, e.g. the R in EUR
+    let after_mark = match after_mark.chars().next() {
+        Some(c) if c.is_ascii_alphabetic() => &after_mark[1..],
+        _ => after_mark,
+    };
+
+    let amount_end = after_mark
+        .find(|c: char| !(c.is_ascii_digit() || c == ','))
+        .unwrap_or(after_mark.len());
+    let amount = Money::parse(&after_mark[..amount_end].replace(',', "."), currency)?;
+
+    // skip the 4 character transaction type, e.g. NTRF, to reach the references
+    let references = after_mark.get(amount_end + 4..).unwrap_or_default();
+    let bank_reference = references
+        .split_once("//")
+        .map(|(_, r)| r.trim().to_string())
+        .filter(|r| !r.is_empty());
+
+    let description = match information {
+        Some(info) => describe(info),
+        None => value.lines().nth(1).unwrap_or_default().trim().to_string(),
+    };
+
+    Ok(ParsedTransaction {
+        account_type: account_type.to_string(),
+        payment_date: NaiveDateTime::new(booking_date, NaiveTime::MIN),
+        amount: match negative {
+            true => -amount,
+            false => amount,
+        },
+        description,
+        external_id: bank_reference,
+        category: None,
+        value_date: Some(NaiveDateTime::new(value_date, NaiveTime::MIN)),
+    })
+}
+
+// :86: is free text for most banks, German banks structure it with ?NN sub
+// fields and others with /KEY/value pairs. the counterparty name is placed
+// ahead of the remittance information when it can be found
+fn describe(information: &str) -> String {
+    let joined: String = information.lines().map(|l| l.trim()).collect();
+
+    if joined.contains("?20") || joined.contains("?32") {
+        let mut counterparty: Vec<String> = Vec::new();
+        let mut remittance: Vec<String> = Vec::new();
+
+        for part in joined.split('?').skip(1) {
+            let code = part.get(..2).unwrap_or_default();
+            let text = part.get(2..).unwrap_or_default();
+            match code {
+                "20" | "21" | "22" | "23" | "24" | "25" | "26" | "27" | "28" | "29" | "60"
+                | "61" | "62" | "63" => remittance.push(text.to_string()),
+                "32" | "33" => counterparty.push(text.to_string()),
+                _ => (),
+            }
+        }
+
+        return [counterparty.join(""), remittance.join("")]
+            .into_iter()
+            .filter(|s| !s.is_empty())
+            .collect::<Vec<String>>()
+            .join(" ");
+    }
+
+    if joined.contains("/NAME/") || joined.contains("/REMI/") {
+        let parts: Vec<&str> = joined.split('/').collect();
+        let value_of = |key: &str| {
+            parts
+                .iter()
+                .position(|p| *p == key)
+                .and_then(|i| parts.get(i + 1))
+                .map(|v| v.trim().to_string())
+        };
+
+        return [value_of("NAME"), value_of("REMI")]
+            .into_iter()
+            .flatten()
+            .filter(|s| !s.is_empty())
+            .collect::<Vec<String>>()
+            .join(" ");
+    }
+
+    information
+        .lines()
+        .map(|l| l.trim())
+        .collect::<Vec<&str>>()
+        .join(" ")
+}
@@ -0,0 +1,208 @@
+use std::collections::HashMap;
+
+use chrono::{NaiveDate, NaiveDateTime, NaiveTime};
+
+use super::{Money, ParseError, ParsedTransaction, Row, RowError};
+
+// parses OFX / QFX statements. v1 files are SGML where leaf elements have no
+// closing tag, v2 files are XML. both are read with the same tag scanner by
+// treating any text directly after an opening tag as that element's value.
+// a file can hold several statements, e.g. a STMTRS for a current account and
+// a CCSTMTRS for a card, each names its own ACCTID. amounts are in their
+// statement's CURDEF, or the given currency without one
+pub fn parse(
+    data: &str,
+    account_type: Option<&str>,
+    currency: &str,
+) -> Result<Vec<Row>, ParseError> {
+    let body = match find_ignore_case(data, "<OFX>") {
+        Some(start) => &data[start..],
+        None => {
+            return Err(ParseError::RecordError(
+                "No <OFX> element found".to_string(),
+            ))
+        }
+    };
+
+    let mut account_id: Option<String> = None;
+    let mut statement_currency: Option<String> = None;
+    let mut current: Option<HashMap<String, String>> = None;
+    // each transaction with the account and currency of its statement
+    let mut statement_transactions = Vec::new();
+
+    for element in Elements::new(body) {
+        match element {
+            Element::Open(tag) if tag == "STMTRS" || tag == "CCSTMTRS" => {
+                account_id = None;
+                statement_currency = None;
+            }
+            Element::Open(tag) if tag == "STMTTRN" => current = Some(HashMap::new()),
+            Element::Close(tag) if tag == "STMTTRN" => {
+                if let Some(t) = current.take() {
+                    statement_transactions.push((
+                        account_id.clone(),
+                        statement_currency.clone(),
+                        t,
+                    ));
+                }
+            }
+            Element::Leaf(tag, value) => match current.as_mut() {
+                Some(t) => {
+                    t.entry(tag).or_insert(value);
+                }
+                None if tag == "ACCTID" && account_id.is_none() => account_id = Some(value),
+                None if tag == "CURDEF" && statement_currency.is_none() => {
+                    statement_currency = Some(value)
+                }
+                None => (),
+            },
+            _ => (),
+        }
+    }
+
+    Ok(statement_transactions
+        .iter()
+        .enumerate()
+        .map(|(index, (account_id, statement_currency, t))| {
+            let account_type = match (account_type, account_id) {
+                (Some(name), _) => name,
+                (None, Some(id)) => id,
+                (None, None) => "OFX",
+            };
+            let currency = statement_currency.as_deref().unwrap_or(currency);
+
+            to_transaction(t, account_type, currency).map_err(|e| RowError::new(index + 1, e))
+        })
+        .collect())
+}
+
+fn to_transaction(
+    fields: &HashMap<String, String>,
+    account_type: &str,
+    currency: &str,
+) -> Result<ParsedTransaction, ParseError> {
+    let date = fields
+        .get("DTPOSTED")
+        .ok_or_else(|| ParseError::RecordError("STMTTRN is missing DTPOSTED".to_string()))?;
+    let amount = fields
+        .get("TRNAMT")
+        .ok_or_else(|| ParseError::RecordError("STMTTRN is missing TRNAMT".to_string()))?;
+
+    let description = match (fields.get("NAME"), fields.get("MEMO")) {
+        (Some(name), Some(memo)) if name != memo => format!("{} {}", name, memo),
+        (Some(name), _) => name.clone(),
+        (None, Some(memo)) => memo.clone(),
+        (None, None) => String::new(),
+    };
+
+    Ok(ParsedTransaction {
+        account_type: account_type.to_string(),
+        payment_date: parse_date(date)?,
+        amount: parse_amount(amount, currency)?,
+        description,
+        external_id: fields.get("FITID").cloned(),
+        category: None,
+        value_date: None,
+    })
+}
+
+// OFX dates look like YYYYMMDD[HHMMSS[.XXX]][[offset:TZ]], the offset is ignored
+fn parse_date(value: &str) -> Result<NaiveDateTime, ParseError> {
+    let digits: String = value.chars().take_while(|c| c.is_ascii_digit()).collect();
+
+    let date = digits
+        .get(0..8)
+        .and_then(|d| NaiveDate::parse_from_str(d, "%Y%m%d").ok())
+        .ok_or_else(|| ParseError::DateConversionError(format!("Invalid OFX date: {}", value)))?;
+
+    let time = match digits.get(8..14) {
+        Some(t) => NaiveTime::parse_from_str(t, "%H%M%S").map_err(|e| {
+            ParseError::DateConversionError(format!("Invalid OFX time {}: {}", value, e))
+        })?,
+        None => NaiveTime::MIN,
+    };
+
+    Ok(NaiveDateTime::new(date, time))
+}
+
+fn parse_amount(value: &str, currency: &str) -> Result<Money, ParseError> {
+    // some banks write the decimal separator as a comma
+    let normalised = if value.contains('.') {
+        value.to_string()
+    } else {
+        value.replace(',', ".")
+    };
+
+    Money::parse(&normalised, currency)
+}
+
+fn find_ignore_case(haystack: &str, needle: &str) -> Option<usize> {
+    haystack
+        .to_ascii_uppercase()
+        .find(&needle.to_ascii_uppercase())
+}
+
+fn decode_entities(value: &str) -> String {
+    value
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&apos;", "'")
+        .replace("&nbsp;", " ")
+        .replace("&amp;", "&")
+}
+
+enum Element {
+    Open(String),
+    Close(String),
+    Leaf(String, String),
+}
+
+struct Elements<'a> {
+    rest: &'a str,
+}
+
+impl<'a> Elements<'a> {
+    fn new(data: &'a str) -> Self {
+        Self { rest: data }
+    }
+}
+
+impl Iterator for Elements<'_> {
+    type Item = Element;
+
+    fn next(&mut self) -> Option<Element> {
+        loop {
+            let start = self.rest.find('<')?;
+            let end = start + self.rest[start..].find('>')?;
+
+            let tag = self.rest[start + 1..end].trim();
+            self.rest = &self.rest[end + 1..];
+
+            // processing instructions, comments and doctypes carry no data
+            if tag.starts_with('?') || tag.starts_with('!') || tag.is_empty() {
+                continue;
+            }
+
+            if let Some(name) = tag.strip_prefix('/') {
+                return Some(Element::Close(name.trim().to_ascii_uppercase()));
+            }
+
+            let name = tag
+                .split_whitespace()
+                .next()
+                .unwrap_or_default()
+                .trim_end_matches('/')
+                .to_ascii_uppercase();
+
+            let value_end = self.rest.find('<').unwrap_or(self.rest.len());
+            let value = self.rest[..value_end].trim();
+
+            if value.is_empty() {
+                return Some(Element::Open(name));
+            }
+
+            return Some(Element::Leaf(name, decode_entities(value)));
+        }
+    }
+}
@@ -0,0 +1,238 @@
+use chrono::{NaiveDate, NaiveDateTime, NaiveTime};
+
+use super::{locale, Config, Money, ParseError, ParsedTransaction, Row, RowError};
+
+// parses QIF exports. only !Type:Bank and !Type:CCard sections are read, other
+// sections such as category lists or investment accounts are skipped. a split
+// transaction becomes one transaction per split so each keeps its own category.
+// qif has no currency, the amounts are taken to be in the given one. amounts
+// are written in the profile's number format, without one in the uk's
+pub fn parse(
+    data: &str,
+    account_type: Option<&str>,
+    date_formats: Option<&[String]>,
+    number_format: Option<&Config>,
+    currency: &str,
+) -> Result<Vec<Row>, ParseError> {
+    let uk = Config::default();
+    let number_format = number_format.unwrap_or(&uk);
+    let mut rows: Vec<Row> = Vec::new();
+    let mut account_name: Option<String> = None;
+    let mut in_account_block = false;
+    let mut in_transactions = false;
+    let mut record = Record::default();
+
+    for (index, line) in data.lines().enumerate() {
+        let line = line.trim_end();
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        if let Some(header) = line.strip_prefix('!') {
+            let header = header.trim().to_ascii_lowercase();
+            in_account_block = header == "account";
+            in_transactions = matches!(header.as_str(), "type:bank" | "type:ccard");
+            record = Record::default();
+            continue;
+        }
+
+        let mut chars = line.chars();
+        let code = chars.next().unwrap_or_default();
+        let value = chars.as_str().trim();
+
+        if in_account_block {
+            if code == 'N' {
+                account_name = Some(value.to_string());
+            }
+            continue;
+        }
+
+        if !in_transactions {
+            continue;
+        }
+
+        match code {
+            'D' => record.date = Some(value.to_string()),
+            'T' | 'U' => record.amount = Some(value.to_string()),
+            'P' => record.payee = Some(value.to_string()),
+            'M' => record.memo = Some(value.to_string()),
+            'L' => record.category = Some(value.to_string()),
+            'S' => record.splits.push(Split {
+                category: Some(value.to_string()),
+                ..Split::default()
+            }),
+            'E' => match record.splits.last_mut() {
+                Some(split) => split.memo = Some(value.to_string()),
+                None => record.splits.push(Split {
+                    memo: Some(value.to_string()),
+                    ..Split::default()
+                }),
+            },
+            '$' => match record.splits.last_mut() {
+                Some(split) if split.amount.is_none() => split.amount = Some(value.to_string()),
+                _ => record.splits.push(Split {
+                    amount: Some(value.to_string()),
+                    ..Split::default()
+                }),
+            },
+            '^' => {
+                let account_type = account_type
+                    .map(|a| a.to_string())
+                    .or_else(|| account_name.clone())
+                    .unwrap_or_else(|| "QIF".to_string());
+
+                let finished = std::mem::take(&mut record);
+                match finished.into_transactions(
+                    &account_type,
+                    date_formats,
+                    number_format,
+                    currency,
+                ) {
+                    Ok(transactions) => rows.extend(transactions.into_iter().map(Ok)),
+                    Err(e) => rows.push(Err(RowError::new(index + 1, e))),
+                }
+            }
+            // check numbers, cleared status and addresses are not stored
+            _ => (),
+        }
+    }
+
+    Ok(rows)
+}
+
+#[derive(Default)]
+struct Split {
+    category: Option<String>,
+    memo: Option<String>,
+    amount: Option<String>,
+}
+
+#[derive(Default)]
+struct Record {
+    date: Option<String>,
+    amount: Option<String>,
+    payee: Option<String>,
+    memo: Option<String>,
+    category: Option<String>,
+    splits: Vec<Split>,
+}
+
+impl Record {
+    fn into_transactions(
+        self,
+        account_type: &str,
+        date_formats: Option<&[String]>,
+        number_format: &Config,
+        currency: &str,
+    ) -> Result<Vec<ParsedTransaction>, ParseError> {
+        let date = self
+            .date
+            .as_deref()
+            .ok_or_else(|| ParseError::RecordError("missing D (date) field".to_string()))?;
+        let payment_date = parse_date(date, date_formats)?;
+
+        let description = match (&self.payee, &self.memo) {
+            (Some(payee), Some(memo)) if payee != memo => format!("{} {}", payee, memo),
+            (Some(payee), _) => payee.clone(),
+            (None, Some(memo)) => memo.clone(),
+            (None, None) => String::new(),
+        };
+
+        if self.splits.is_empty() {
+            let amount = self
+                .amount
+                .as_deref()
+                .ok_or_else(|| ParseError::RecordError("missing T (amount) field".to_string()))?;
+
+            return Ok(vec![ParsedTransaction {
+                account_type: account_type.to_string(),
+                payment_date,
+                amount: parse_amount(amount, number_format, currency)?,
+                description,
+                external_id: None,
+                category: self.category.as_deref().and_then(parse_category),
+                value_date: None,
+            }]);
+        }
+
+        self.splits
+            .iter()
+            .map(|split| {
+                let amount = split.amount.as_deref().ok_or_else(|| {
+                    ParseError::RecordError("split is missing $ (amount) field".to_string())
+                })?;
+
+                let description = match &split.memo {
+                    Some(memo) if !memo.is_empty() => format!("{} {}", description, memo),
+                    _ => description.clone(),
+                };
+
+                Ok(ParsedTransaction {
+                    account_type: account_type.to_string(),
+                    payment_date,
+                    amount: parse_amount(amount, number_format, currency)?,
+                    description: description.trim().to_string(),
+                    external_id: None,
+                    category: split.category.as_deref().and_then(parse_category),
+                    value_date: None,
+                })
+            })
+            .collect()
+    }
+}
+
+// categories may carry a class after a slash, transfers are written as [Account]
+fn parse_category(value: &str) -> Option<String> {
+    let category = value.split('/').next().unwrap_or_default().trim();
+
+    if category.is_empty() {
+        return None;
+    }
+
+    Some(category.to_string())
+}
+
+fn parse_amount(value: &str, number_format: &Config, currency: &str) -> Result<Money, ParseError> {
+    Ok(Money::new(locale::amount(number_format, value)?, currency))
+}
+
+// quicken writes years after 1999 with an apostrophe, e.g. 3/ 2'24
+fn parse_date(value: &str, date_formats: Option<&[String]>) -> Result<NaiveDateTime, ParseError> {
+    let normalised = value.replace('\'', "/").replace(' ', "");
+
+    let two_digit_year = normalised
+        .rsplit(['/', '-', '.'])
+        .next()
+        .map(|year| year.len() <= 2)
+        .unwrap_or(false);
+
+    let date = match date_formats {
+        // a profile's four digit year formats also read the apostrophe form
+        Some(formats) => formats.iter().find_map(|f| {
+            let format = match two_digit_year {
+                true => f.replace("%Y", "%y"),
+                false => f.to_string(),
+            };
+            NaiveDate::parse_from_str(&normalised, &format).ok()
+        }),
+        None => {
+            let formats: &[&str] = if two_digit_year {
+                &["%d/%m/%y", "%d-%m-%y", "%d.%m.%y"]
+            } else {
+                &["%d/%m/%Y", "%Y-%m-%d", "%d-%m-%Y", "%d.%m.%Y"]
+            };
+
+            formats
+                .iter()
+                .find_map(|f| NaiveDate::parse_from_str(&normalised, f).ok())
+        }
+    };
+
+    match date {
+        Some(d) => Ok(NaiveDateTime::new(d, NaiveTime::MIN)),
+        None => Err(ParseError::DateConversionError(format!(
+            "Invalid QIF date: {}",
+            value
+        ))),
+    }
+}
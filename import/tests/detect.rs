@@ -0,0 +1,132 @@
+use std::fs;
+
+use import::service::parse_service::{detect, AccountType, Config, Format, ParseError};
+
+fn fixture(file: &str) -> String {
+    let path = format!("{}/tests/fixtures/{}", env!("CARGO_MANIFEST_DIR"), file);
+    fs::read_to_string(&path).unwrap_or_else(|e| panic!("unable to read {}: {}", path, e))
+}
+
+fn profiles() -> Vec<Config> {
+    AccountType::all().iter().map(|a| a.config()).collect()
+}
+
+#[test]
+fn formats_are_sniffed_without_a_filename() {
+    let files = [
+        ("sgml.ofx", Format::Ofx),
+        ("xml.ofx", Format::Ofx),
+        ("bank.qif", Format::Qif),
+        ("statement.camt.xml", Format::Camt053),
+        ("statements.sta", Format::Mt940),
+        ("monzo.csv", Format::Csv),
+    ];
+
+    for (file, format) in files {
+        assert_eq!(detect::format(None, &fixture(file)), format, "{}", file);
+        assert_eq!(
+            detect::format(Some("upload.dat"), &fixture(file)),
+            format,
+            "{}",
+            file
+        );
+    }
+}
+
+#[test]
+fn qif_files_are_sniffed_by_their_first_header() {
+    for head in [
+        "!Type:Bank\nD3/ 2'24\n^",
+        "!Type:CCard\n",
+        "\n!Account\nNCurrent\n^",
+    ] {
+        assert_eq!(detect::format(None, head), Format::Qif, "{:?}", head);
+    }
+}
+
+#[test]
+fn mt940_needs_a_statement_reference_and_account() {
+    assert_eq!(
+        detect::format(None, ":20:STMT\n:25:GB29NWBK60161331926819\n"),
+        Format::Mt940
+    );
+    assert_eq!(
+        detect::format(None, ":20:STMT\n:60F:C240201EUR500,00\n"),
+        Format::Mt940
+    );
+    assert_eq!(detect::format(None, ":20:,only a reference\n"), Format::Csv);
+}
+
+#[test]
+fn extensions_win_over_the_content() {
+    let data = fixture("monzo.csv");
+
+    assert_eq!(detect::format(Some("export.QFX"), &data), Format::Ofx);
+    assert_eq!(detect::format(Some("export.qif"), &data), Format::Qif);
+    assert_eq!(detect::format(Some("export.mt940"), &data), Format::Mt940);
+    assert_eq!(
+        detect::format(Some("statement.csv"), &fixture("sgml.ofx")),
+        Format::Csv
+    );
+}
+
+#[test]
+fn other_formats_are_detected_without_a_profile() {
+    let detected = detect::statement(Some("sgml.ofx"), &fixture("sgml.ofx"), &profiles())
+        .unwrap_or_else(|e| panic!("{}", e));
+
+    assert_eq!(detected.format, Format::Ofx);
+    assert!(detected.profile.is_none());
+}
+
+#[test]
+fn csv_without_a_matching_profile() {
+    let data = "When,What,How much\n2024-02-03,Coffee,abc\n2024-02-04,Tea,xyz\n";
+
+    let detected = detect::statement(Some("unknown.csv"), data, &profiles());
+
+    match detected {
+        Err(ParseError::DetectionError(e)) => {
+            assert!(e.contains("No import profile matches"), "{}", e);
+            assert!(e.contains("Monzo"), "{}", e);
+        }
+        Err(e) => panic!("unexpected error: {}", e),
+        Ok(d) => panic!("detected {:?}", d.profile.map(|p| p.name)),
+    }
+}
+
+#[test]
+fn profiles_that_tie_are_not_guessed_between() {
+    // the same columns under two names fit the file equally well
+    let starling = AccountType::Starling.config();
+    let copy = Config {
+        name: "Starling Business".to_string(),
+        ..starling.clone()
+    };
+
+    let detected = detect::statement(
+        Some("starling.csv"),
+        &fixture("starling.csv"),
+        &[starling, copy],
+    );
+
+    match detected {
+        Err(ParseError::DetectionError(e)) => {
+            assert!(e.contains("More than one import profile"), "{}", e);
+            assert!(e.contains("Starling, Starling Business"), "{}", e);
+        }
+        Err(e) => panic!("unexpected error: {}", e),
+        Ok(d) => panic!("detected {:?}", d.profile.map(|p| p.name)),
+    }
+}
+
+#[test]
+fn the_profile_naming_more_columns_wins() {
+    // amex and monzo files both parse with either profile's signed amount,
+    // the header names tell them apart
+    let detected = detect::statement(None, &fixture("monzo.csv"), &profiles())
+        .unwrap_or_else(|e| panic!("{}", e));
+
+    assert_eq!(detected.format, Format::Csv);
+    assert_eq!(detected.profile.map(|p| p.name).as_deref(), Some("Monzo"));
+}
@@ -0,0 +1,326 @@
+use std::fs;
+
+use chrono::{NaiveDate, NaiveDateTime};
+use import::service::parse_service::{
+    detect, AccountType, AmountMode, Config, Format, Money, ParsedTransaction, Service,
+};
+
+const BANKS: [(&str, &str); 9] = [
+    ("Amex", "amex.csv"),
+    ("Lloyds", "lloyds.csv"),
+    ("Monzo", "monzo.csv"),
+    ("Starling", "starling.csv"),
+    ("HSBC", "hsbc.csv"),
+    ("Barclays", "barclays.csv"),
+    ("Nationwide", "nationwide.csv"),
+    ("Santander", "santander.csv"),
+    ("Revolut", "revolut.csv"),
+];
+
+fn fixture(file: &str) -> String {
+    let path = format!("{}/tests/fixtures/{}", env!("CARGO_MANIFEST_DIR"), file);
+    fs::read_to_string(&path).unwrap_or_else(|e| panic!("unable to read {}: {}", path, e))
+}
+
+fn parse(name: &str, file: &str) -> Vec<ParsedTransaction> {
+    let account_type = AccountType::named(name).expect("built in profile");
+    let data = fixture(file);
+
+    Service::new()
+        .parse(account_type, data.as_bytes())
+        .expect("fixture parses")
+        .into_iter()
+        .map(|row| row.unwrap_or_else(|e| panic!("row {}: {}", e.row, e.reason)))
+        .collect()
+}
+
+fn date(year: i32, month: u32, day: u32) -> NaiveDateTime {
+    NaiveDate::from_ymd_opt(year, month, day)
+        .and_then(|d| d.and_hms_opt(0, 0, 0))
+        .unwrap()
+}
+
+fn assert_transaction(
+    transaction: &ParsedTransaction,
+    payment_date: NaiveDateTime,
+    amount: &str,
+    description: &str,
+) {
+    assert_eq!(transaction.payment_date, payment_date);
+    assert_eq!(
+        transaction.amount,
+        Money::parse(amount, "GBP").unwrap(),
+        "amount of {}",
+        description
+    );
+    assert_eq!(transaction.description, description);
+}
+
+#[test]
+fn amex() {
+    let transactions = parse("Amex", "amex.csv");
+
+    assert_eq!(transactions.len(), 3);
+    assert_transaction(
+        &transactions[0],
+        date(2024, 2, 3),
+        "-12.5",
+        "TESCO STORES 2041",
+    );
+    assert_transaction(
+        &transactions[1],
+        date(2024, 2, 5),
+        "250.0",
+        "PAYMENT RECEIVED - THANK YOU",
+    );
+    assert_transaction(
+        &transactions[2],
+        date(2024, 2, 7),
+        "-1204.1",
+        "TRAINLINE.COM",
+    );
+    assert!(transactions.iter().all(|t| t.account_type == "Amex"));
+    // amounts keep the digits the bank wrote, in the profile's currency
+    assert_eq!(transactions[2].amount.to_string(), "-1204.10 GBP");
+}
+
+#[test]
+fn lloyds() {
+    let transactions = parse("Lloyds", "lloyds.csv");
+
+    assert_eq!(transactions.len(), 3);
+    assert_transaction(
+        &transactions[0],
+        date(2024, 2, 1),
+        "-23.45",
+        "SAINSBURYS S/MKTS",
+    );
+    assert_transaction(
+        &transactions[1],
+        date(2024, 2, 2),
+        "2100.0",
+        "ACME LTD SALARY",
+    );
+    assert_transaction(&transactions[2], date(2024, 2, 4), "-85.0", "BRITISH GAS");
+}
+
+#[test]
+fn monzo() {
+    let transactions = parse("Monzo", "monzo.csv");
+
+    assert_eq!(transactions.len(), 3);
+    assert_transaction(&transactions[0], date(2024, 2, 3), "-4.95", "Pret A Manger");
+    assert_transaction(&transactions[1], date(2024, 2, 4), "30.0", "Jane Smith");
+    assert_transaction(&transactions[2], date(2024, 2, 6), "-1250.0", "Amazon");
+}
+
+#[test]
+fn starling() {
+    let transactions = parse("Starling", "starling.csv");
+
+    // the opening balance row is not a transaction
+    assert_eq!(transactions.len(), 2);
+    assert_transaction(&transactions[0], date(2024, 2, 2), "-18.2", "Waitrose");
+    assert_transaction(&transactions[1], date(2024, 2, 5), "2500.0", "Acme Ltd");
+}
+
+#[test]
+fn hsbc() {
+    let transactions = parse("HSBC", "hsbc.csv");
+
+    assert_eq!(transactions.len(), 3);
+    assert_transaction(
+        &transactions[0],
+        date(2024, 2, 2),
+        "1500.0",
+        "CR PAYMENT RECEIVED",
+    );
+    assert_transaction(
+        &transactions[1],
+        date(2024, 2, 5),
+        "-45.67",
+        "BP COUNTRY GARAGE )))",
+    );
+    assert_transaction(
+        &transactions[2],
+        date(2024, 2, 7),
+        "-32.0",
+        "THAMES WATER DD",
+    );
+}
+
+#[test]
+fn barclays() {
+    let transactions = parse("Barclays", "barclays.csv");
+
+    assert_eq!(transactions.len(), 3);
+    assert_transaction(&transactions[0], date(2024, 2, 2), "-9.99", "NETFLIX.COM");
+    assert_transaction(
+        &transactions[1],
+        date(2024, 2, 3),
+        "1850.0",
+        "ACME LTD SALARY      BGC",
+    );
+    assert_transaction(
+        &transactions[2],
+        date(2024, 2, 6),
+        "-64.3",
+        "CO-OP GROUP 070113  ON 05 FEB         BCC",
+    );
+}
+
+#[test]
+fn nationwide() {
+    let transactions = parse("Nationwide", "nationwide.csv");
+
+    assert_eq!(transactions.len(), 3);
+    assert_transaction(&transactions[0], date(2024, 2, 1), "-142.0", "COUNCIL TAX");
+    assert_transaction(
+        &transactions[1],
+        date(2024, 2, 2),
+        "2300.0",
+        "ACME LTD SALARY",
+    );
+    assert_transaction(&transactions[2], date(2024, 2, 4), "-27.63", "MORRISONS");
+}
+
+#[test]
+fn santander() {
+    let transactions = parse("Santander", "santander.csv");
+
+    assert_eq!(transactions.len(), 3);
+    assert_transaction(
+        &transactions[0],
+        date(2024, 2, 1),
+        "-7.49",
+        "CARD PAYMENT TO BOOTS",
+    );
+    assert_transaction(
+        &transactions[1],
+        date(2024, 2, 3),
+        "1925.0",
+        "BANK GIRO CREDIT REF ACME LTD",
+    );
+    assert_transaction(
+        &transactions[2],
+        date(2024, 2, 5),
+        "-32.0",
+        "DIRECT DEBIT PAYMENT TO VODAFONE",
+    );
+}
+
+#[test]
+fn revolut() {
+    let transactions = parse("Revolut", "revolut.csv");
+    let at = |day: u32, time: (u32, u32, u32)| {
+        NaiveDate::from_ymd_opt(2024, 2, day)
+            .and_then(|d| d.and_hms_opt(time.0, time.1, time.2))
+            .unwrap()
+    };
+
+    assert_eq!(transactions.len(), 3);
+    assert_transaction(
+        &transactions[0],
+        at(1, (9, 12, 44)),
+        "200.0",
+        "Top-Up by *1234",
+    );
+    assert_transaction(&transactions[1], at(3, (13, 5, 10)), "-12.8", "Uber");
+    // still pending, so it has no completed date
+    assert_transaction(&transactions[2], at(6, (21, 30, 0)), "-23.15", "Deliveroo");
+}
+
+#[test]
+fn profiles_are_valid() {
+    for account_type in AccountType::all() {
+        let config = account_type.config();
+        if let Err(e) = config.validate() {
+            panic!("{} profile is invalid: {}", config.name, e);
+        }
+    }
+}
+
+// a bank writing unsigned amounts with a DR or CR column
+fn indicator_profile() -> Config {
+    Config {
+        name: "Indicator Bank".to_string(),
+        date_headers: vec!["Date".to_string()],
+        description_headers: vec!["Description".to_string()],
+        amount_headers: vec!["Amount".to_string()],
+        amount_mode: AmountMode::Indicator,
+        indicator_headers: vec!["Type".to_string()],
+        debit_indicators: vec!["DR".to_string()],
+        credit_indicators: vec!["CR".to_string()],
+        date_formats: vec!["%d/%m/%Y".to_string()],
+        ..Config::default()
+    }
+}
+
+#[test]
+fn an_unknown_indicator_is_a_row_error() {
+    let data = "Date,Description,Amount,Type\n\
+                01/03/2024,Rent,950.00,dr\n\
+                02/03/2024,Salary,2100.00, CR\n\
+                03/03/2024,Coffee,2.50,\n\
+                04/03/2024,Refund,12.00,DB\n";
+
+    let rows = Service::new()
+        .parse_data(Format::Csv, Some(&indicator_profile()), data)
+        .expect("csv parses");
+
+    assert_eq!(rows.len(), 4);
+    assert_eq!(
+        rows[0].as_ref().map(|t| t.amount.clone()).ok(),
+        Some(Money::parse("-950.00", "GBP").unwrap())
+    );
+    assert_eq!(
+        rows[1].as_ref().map(|t| t.amount.clone()).ok(),
+        Some(Money::parse("2100.00", "GBP").unwrap())
+    );
+    for (row, value) in [(&rows[2], ""), (&rows[3], "DB")] {
+        let error = row.as_ref().expect_err("unknown indicator");
+        assert_eq!(error.column.as_deref(), Some("indicator"));
+        assert_eq!(error.value.as_deref(), Some(value));
+    }
+}
+
+#[test]
+fn indicator_profiles_need_both_lists() {
+    assert!(indicator_profile().validate().is_ok());
+
+    let no_credits = Config {
+        credit_indicators: Vec::new(),
+        ..indicator_profile()
+    };
+    assert!(no_credits.validate().is_err());
+
+    let overlapping = Config {
+        credit_indicators: vec!["CR".to_string(), "dr ".to_string()],
+        ..indicator_profile()
+    };
+    assert!(overlapping.validate().is_err());
+}
+
+#[test]
+fn profiles_are_found_by_name() {
+    for (name, _) in BANKS {
+        let account_type = AccountType::named(name).expect("built in profile");
+        assert_eq!(account_type.config().name, name);
+    }
+
+    assert_eq!(AccountType::all().len(), BANKS.len());
+    assert!(AccountType::named("Unknown Bank").is_none());
+}
+
+#[test]
+fn fixtures_are_detected() {
+    let profiles: Vec<Config> = AccountType::all().iter().map(|a| a.config()).collect();
+
+    for (name, file) in BANKS {
+        let data = fixture(file);
+        let detected = detect::statement(Some(file), &data, &profiles)
+            .unwrap_or_else(|e| panic!("unable to detect {}: {}", file, e));
+
+        assert_eq!(detected.profile.map(|p| p.name).as_deref(), Some(name));
+    }
+}
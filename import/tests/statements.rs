@@ -0,0 +1,405 @@
+use std::fs;
+
+use chrono::{NaiveDate, NaiveDateTime};
+use import::service::parse_service::{Config, Format, Money, ParsedTransaction, Row, Service};
+
+fn fixture(file: &str) -> String {
+    let path = format!("{}/tests/fixtures/{}", env!("CARGO_MANIFEST_DIR"), file);
+    fs::read_to_string(&path).unwrap_or_else(|e| panic!("unable to read {}: {}", path, e))
+}
+
+// reads a statement without a profile, so accounts and currencies come from
+// the file itself
+fn parse(format: Format, file: &str) -> Vec<ParsedTransaction> {
+    Service::new()
+        .parse_data(format, None, &fixture(file))
+        .unwrap_or_else(|e| panic!("unable to parse {}: {}", file, e))
+        .into_iter()
+        .map(|row| row.unwrap_or_else(|e| panic!("row {}: {}", e.row, e.reason)))
+        .collect()
+}
+
+fn at(year: i32, month: u32, day: u32, time: (u32, u32, u32)) -> NaiveDateTime {
+    NaiveDate::from_ymd_opt(year, month, day)
+        .and_then(|d| d.and_hms_opt(time.0, time.1, time.2))
+        .unwrap()
+}
+
+fn date(year: i32, month: u32, day: u32) -> NaiveDateTime {
+    at(year, month, day, (0, 0, 0))
+}
+
+fn money(amount: &str, currency: &str) -> Money {
+    Money::parse(amount, currency).unwrap()
+}
+
+#[test]
+fn ofx_sgml() {
+    let transactions = parse(Format::Ofx, "sgml.ofx");
+
+    assert_eq!(transactions.len(), 3);
+    assert_eq!(transactions[0].payment_date, date(2024, 2, 3));
+    assert_eq!(transactions[0].amount, money("-12.50", "GBP"));
+    assert_eq!(transactions[0].description, "TESCO STORES 2041 CONTACTLESS");
+    assert_eq!(transactions[0].external_id.as_deref(), Some("24034000001"));
+
+    // the timezone is dropped and a comma is read as the decimal separator
+    assert_eq!(transactions[1].payment_date, at(2024, 2, 5, (12, 0, 0)));
+    assert_eq!(transactions[1].amount, money("2100.00", "GBP"));
+    // a memo repeating the name is not added to it
+    assert_eq!(transactions[1].description, "ACME LTD SALARY");
+    assert_eq!(transactions[1].external_id.as_deref(), Some("24036000002"));
+
+    assert_eq!(transactions[2].amount, money("-85.00", "GBP"));
+    assert_eq!(transactions[2].description, "M&S BANK");
+
+    // without a profile the transactions belong to the statement's account
+    assert!(transactions
+        .iter()
+        .all(|t| t.account_type == "30963412345678"));
+}
+
+#[test]
+fn ofx_xml() {
+    let transactions = parse(Format::Ofx, "xml.ofx");
+
+    assert_eq!(transactions.len(), 2);
+    assert_eq!(transactions[0].payment_date, at(2024, 2, 2, (9, 30, 15)));
+    assert_eq!(transactions[0].amount, money("-1204.10", "EUR"));
+    assert_eq!(transactions[0].description, "SNCF VOYAGES");
+    assert_eq!(transactions[0].external_id.as_deref(), Some("FR-0001"));
+
+    assert_eq!(transactions[1].payment_date, date(2024, 2, 6));
+    assert_eq!(transactions[1].amount, money("250.00", "EUR"));
+    assert_eq!(transactions[1].description, "PAYMENT RECEIVED - THANK YOU");
+    assert_eq!(transactions[1].external_id.as_deref(), Some("FR-0002"));
+
+    assert!(transactions
+        .iter()
+        .all(|t| t.account_type == "4111111111111111"));
+}
+
+#[test]
+fn ofx_transactions_belong_to_their_own_statement() {
+    let transaction = "<STMTTRN><DTPOSTED>20240203<TRNAMT>-12.50<FITID>1</STMTTRN>";
+    let data = format!(
+        "<OFX><BANKMSGSRSV1><STMTTRNRS><STMTRS><CURDEF>GBP\
+         <BANKACCTFROM><ACCTID>30963412345678</BANKACCTFROM>\
+         <BANKTRANLIST>{0}</BANKTRANLIST></STMTRS></STMTTRNRS></BANKMSGSRSV1>\
+         <CREDITCARDMSGSRSV1><CCSTMTTRNRS><CCSTMTRS><CURDEF>USD\
+         <CCACCTFROM><ACCTID>4242424242424242</CCACCTFROM>\
+         <BANKTRANLIST>{0}</BANKTRANLIST></CCSTMTRS></CCSTMTTRNRS></CREDITCARDMSGSRSV1></OFX>",
+        transaction
+    );
+
+    let accounts: Vec<(String, Money)> = Service::new()
+        .parse_data(Format::Ofx, None, &data)
+        .unwrap_or_else(|e| panic!("{}", e))
+        .into_iter()
+        .map(|r| r.unwrap_or_else(|e| panic!("{}", e.reason)))
+        .map(|t| (t.account_type, t.amount))
+        .collect();
+
+    assert_eq!(
+        accounts,
+        [
+            ("30963412345678".to_string(), money("-12.50", "GBP")),
+            ("4242424242424242".to_string(), money("-12.50", "USD")),
+        ]
+    );
+}
+
+#[test]
+fn ofx_rows_missing_fields_are_reported() {
+    let data = fixture("sgml.ofx").replace("<TRNAMT>-85.00\n", "");
+
+    let rows = Service::new()
+        .parse_data(Format::Ofx, None, &data)
+        .unwrap_or_else(|e| panic!("unable to parse: {}", e));
+
+    assert_eq!(rows.len(), 3);
+    assert!(rows[0].is_ok() && rows[1].is_ok());
+    let error = rows[2].as_ref().unwrap_err();
+    assert_eq!(error.row, 3);
+    assert!(error.reason.contains("TRNAMT"), "{}", error.reason);
+}
+
+fn parse_qif(profile: Option<&Config>) -> Vec<Row> {
+    Service::new()
+        .parse_data(Format::Qif, profile, &fixture("bank.qif"))
+        .unwrap_or_else(|e| panic!("unable to parse bank.qif: {}", e))
+}
+
+#[test]
+fn qif() {
+    let rows = parse_qif(None);
+
+    // the category list before the bank section is not read as transactions
+    assert_eq!(rows.len(), 5);
+    let transactions: Vec<&ParsedTransaction> = rows[..4]
+        .iter()
+        .map(|r| r.as_ref().unwrap_or_else(|e| panic!("{}", e.reason)))
+        .collect();
+
+    // the apostrophe year form is read day first without a profile
+    assert_eq!(transactions[0].payment_date, date(2024, 2, 3));
+    assert_eq!(transactions[0].amount, money("-1204.10", "GBP"));
+    assert_eq!(transactions[0].description, "TESCO STORES 2041 Weekly shop");
+    assert_eq!(transactions[0].category.as_deref(), Some("Groceries"));
+
+    assert_eq!(transactions[1].payment_date, date(2024, 2, 5));
+    assert_eq!(transactions[1].amount, money("2100.00", "GBP"));
+    // the class after the slash is dropped
+    assert_eq!(transactions[1].category.as_deref(), Some("[Savings]"));
+
+    assert!(transactions
+        .iter()
+        .all(|t| t.account_type == "Joint Current"));
+}
+
+#[test]
+fn qif_splits_become_transactions() {
+    let rows = parse_qif(None);
+    let splits: Vec<&ParsedTransaction> = rows[2..4]
+        .iter()
+        .map(|r| r.as_ref().unwrap_or_else(|e| panic!("{}", e.reason)))
+        .collect();
+
+    assert_eq!(splits[0].payment_date, date(2024, 2, 7));
+    assert_eq!(splits[0].amount, money("-100.00", "GBP"));
+    assert_eq!(splits[0].description, "SAINSBURYS Split shop Food");
+    assert_eq!(splits[0].category.as_deref(), Some("Groceries"));
+
+    assert_eq!(splits[1].payment_date, date(2024, 2, 7));
+    assert_eq!(splits[1].amount, money("-20.00", "GBP"));
+    assert_eq!(splits[1].description, "SAINSBURYS Split shop");
+    assert_eq!(splits[1].category.as_deref(), Some("Household"));
+}
+
+#[test]
+fn qif_records_end_at_a_caret() {
+    let rows = parse_qif(None);
+
+    // the last record has no amount and does not take one from the record
+    // before it, the error is on the line of its ^
+    let error = rows[4].as_ref().unwrap_err();
+    assert_eq!(error.row, 36);
+    assert!(error.reason.contains("missing T"), "{}", error.reason);
+}
+
+#[test]
+fn qif_apostrophe_years_follow_the_profile_date_format() {
+    let profile = Config {
+        name: "US Checking".to_string(),
+        date_formats: vec!["%m/%d/%Y".to_string()],
+        currency: "USD".to_string(),
+        ..Config::default()
+    };
+
+    let rows = parse_qif(Some(&profile));
+    let first = rows[0].as_ref().unwrap_or_else(|e| panic!("{}", e.reason));
+
+    assert_eq!(first.payment_date, date(2024, 3, 2));
+    assert_eq!(first.amount, money("-1204.10", "USD"));
+    assert_eq!(first.account_type, "US Checking");
+}
+
+#[test]
+fn qif_amounts_follow_the_profile_number_format() {
+    let data = "!Type:Bank\nD01/03/2024\nT-1.234,56\nPRENT\n^\nD02/03/2024\nT12,50\nPREFUND\n^\n";
+    let profile = Config {
+        name: "Sparkasse".to_string(),
+        decimal_separator: ",".to_string(),
+        thousands_separator: ".".to_string(),
+        currency: "EUR".to_string(),
+        ..Config::default()
+    };
+
+    let amounts: Vec<Money> = Service::new()
+        .parse_data(Format::Qif, Some(&profile), data)
+        .unwrap_or_else(|e| panic!("{}", e))
+        .into_iter()
+        .map(|r| r.unwrap_or_else(|e| panic!("{}", e.reason)).amount)
+        .collect();
+    assert_eq!(amounts, [money("-1234.56", "EUR"), money("12.50", "EUR")]);
+
+    // read with a decimal point they are errors rather than other amounts
+    let rows = Service::new()
+        .parse_data(Format::Qif, None, data)
+        .unwrap_or_else(|e| panic!("{}", e));
+    assert_eq!(rows.len(), 2);
+    assert!(rows.iter().all(|r| r.is_err()));
+}
+
+#[test]
+fn mt940_statements() {
+    let transactions = parse(Format::Mt940, "statements.sta");
+
+    assert_eq!(transactions.len(), 5);
+
+    // the first statement is in pounds, the second in euros, each in its own
+    // account
+    let accounts: Vec<&str> = transactions
+        .iter()
+        .map(|t| t.account_type.as_str())
+        .collect();
+    assert_eq!(
+        accounts,
+        [
+            "GB29NWBK60161331926819",
+            "GB29NWBK60161331926819",
+            "GB29NWBK60161331926819",
+            "DE89370400440532013000",
+            "DE89370400440532013000",
+        ]
+    );
+    let currencies: Vec<&str> = transactions
+        .iter()
+        .map(|t| t.amount.currency.as_str())
+        .collect();
+    assert_eq!(currencies, ["GBP", "GBP", "GBP", "EUR", "EUR"]);
+}
+
+#[test]
+fn mt940_entry_dates_move_across_the_new_year() {
+    let transactions = parse(Format::Mt940, "statements.sta");
+
+    // valued on 29 december 2023, booked on 2 january 2024
+    assert_eq!(transactions[0].value_date, Some(date(2023, 12, 29)));
+    assert_eq!(transactions[0].payment_date, date(2024, 1, 2));
+
+    // without an entry date it is booked on the value date
+    assert_eq!(transactions[1].value_date, Some(date(2024, 1, 2)));
+    assert_eq!(transactions[1].payment_date, date(2024, 1, 2));
+}
+
+#[test]
+fn mt940_amounts_and_reversals() {
+    let transactions = parse(Format::Mt940, "statements.sta");
+
+    assert_eq!(transactions[0].amount, money("-25.50", "GBP"));
+    assert_eq!(transactions[1].amount, money("1500.00", "GBP"));
+    // a reversed debit is money coming back in, a reversed credit money going
+    // out
+    assert_eq!(transactions[2].amount, money("4.99", "GBP"));
+    assert_eq!(transactions[3].amount, money("-12.00", "EUR"));
+    // the R after the C is the third letter of EUR, not a reversal
+    assert_eq!(transactions[4].amount, money("100.00", "EUR"));
+
+    assert_eq!(transactions[0].external_id.as_deref(), Some("BR24010201"));
+    assert_eq!(transactions[2].external_id, None);
+}
+
+#[test]
+fn mt940_descriptions() {
+    let transactions = parse(Format::Mt940, "statements.sta");
+
+    // free text continues over lines
+    assert_eq!(
+        transactions[0].description,
+        "CARD PAYMENT TO TESCO STORES 2041"
+    );
+    // /NAME/ and /REMI/ fields
+    assert_eq!(transactions[1].description, "ACME LTD SALARY JANUARY");
+    // without an :86: there is no description
+    assert_eq!(transactions[2].description, "");
+    // ?NN sub fields, with the counterparty ahead of the remittance information
+    assert_eq!(
+        transactions[3].description,
+        "STADTWERKEMUENCHEN EREF+ 123RUECKBUCHUNG"
+    );
+    // the supplementary details line of the :61:
+    assert_eq!(transactions[4].description, "MIETE FEBRUAR");
+}
+
+fn parse_camt() -> Vec<Row> {
+    Service::new()
+        .parse_data(Format::Camt053, None, &fixture("statement.camt.xml"))
+        .unwrap_or_else(|e| panic!("unable to parse statement.camt.xml: {}", e))
+}
+
+#[test]
+fn camt053() {
+    let rows = parse_camt();
+
+    assert_eq!(rows.len(), 4);
+    let transactions: Vec<&ParsedTransaction> = rows[..3]
+        .iter()
+        .map(|r| r.as_ref().unwrap_or_else(|e| panic!("{}", e.reason)))
+        .collect();
+
+    // a debit is paid to the creditor, the amount in the transaction details
+    // is not the entry's
+    assert_eq!(transactions[0].amount, money("-45.99", "EUR"));
+    assert_eq!(transactions[0].payment_date, date(2024, 2, 3));
+    assert_eq!(transactions[0].value_date, Some(date(2024, 2, 2)));
+    assert_eq!(
+        transactions[0].description,
+        "REWE MARKT GMBH KARTENZAHLUNG 02.02 FILIALE 4711"
+    );
+    // the servicer's reference is kept over the entry reference
+    assert_eq!(
+        transactions[0].external_id.as_deref(),
+        Some("2024020300001")
+    );
+
+    // a credit is paid by the debtor
+    assert_eq!(transactions[1].amount, money("1500.00", "EUR"));
+    assert_eq!(transactions[1].payment_date, at(2024, 2, 5, (10, 15, 0)));
+    assert_eq!(transactions[1].description, "ACME GMBH RF18539007547034");
+    assert_eq!(transactions[1].external_id.as_deref(), Some("2"));
+
+    // without remittance information the additional entry information is used
+    assert_eq!(transactions[2].amount, money("-4.90", "EUR"));
+    assert_eq!(transactions[2].description, "KONTOFUEHRUNGSGEBUEHR");
+    assert_eq!(transactions[2].external_id, None);
+
+    assert!(transactions
+        .iter()
+        .all(|t| t.account_type == "DE89370400440532013000"));
+}
+
+#[test]
+fn camt053_entries_need_a_credit_or_debit_indicator() {
+    let rows = parse_camt();
+
+    let error = rows[3].as_ref().unwrap_err();
+    assert_eq!(error.row, 4);
+    assert!(error.reason.contains("CdtDbtInd"), "{}", error.reason);
+}
+
+#[test]
+fn camt053_entries_belong_to_their_own_statement() {
+    let statement = |account: &str, amount: &str| {
+        format!(
+            "<Stmt><Acct><Id>{}</Id></Acct><Ntry><Amt Ccy=\"EUR\">{}</Amt>\
+             <CdtDbtInd>DBIT</CdtDbtInd><BookgDt><Dt>2024-02-03</Dt></BookgDt></Ntry></Stmt>",
+            account, amount
+        )
+    };
+    let data = format!(
+        "<Document><BkToCstmrStmt>{}{}{}</BkToCstmrStmt></Document>",
+        statement("<IBAN>DE89370400440532013000</IBAN>", "10.00"),
+        statement("<Othr><Id>CARD-4242</Id></Othr>", "20.00"),
+        // a statement without an account does not take the one before it
+        "<Stmt><Ntry><Amt Ccy=\"EUR\">30.00</Amt><CdtDbtInd>CRDT</CdtDbtInd>\
+         <BookgDt><Dt>2024-02-04</Dt></BookgDt></Ntry></Stmt>",
+    );
+
+    let accounts: Vec<(String, Money)> = Service::new()
+        .parse_data(Format::Camt053, None, &data)
+        .unwrap_or_else(|e| panic!("{}", e))
+        .into_iter()
+        .map(|r| r.unwrap_or_else(|e| panic!("{}", e.reason)))
+        .map(|t| (t.account_type, t.amount))
+        .collect();
+
+    assert_eq!(
+        accounts,
+        [
+            ("DE89370400440532013000".to_string(), money("-10.00", "EUR")),
+            ("CARD-4242".to_string(), money("-20.00", "EUR")),
+            ("camt.053".to_string(), money("30.00", "EUR")),
+        ]
+    );
+}
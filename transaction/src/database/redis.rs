@@ -73,10 +73,32 @@ impl DatabaseWrite for Redis {
     fn save<T: ToString + GetId>(&mut self, record: T) -> Result<(), Box<dyn Error>> {
         match self.connection.as_mut() {
             Some(c) => {
-                redis::cmd("HSET")
+                redis::cmd("SET")
                     .arg(record.get_id())
                     .arg(record.to_string())
-                    .query(c)?;
+                    .query::<()>(c)?;
+            }
+            None => {
+                return Err("Redis connection was None".into());
+            }
+        }
+
+        Ok(())
+    }
+
+    fn save_all<T: ToString + GetId>(&mut self, records: Vec<T>) -> Result<(), Box<dyn Error>> {
+        if records.is_empty() {
+            return Ok(());
+        }
+
+        match self.connection.as_mut() {
+            Some(c) => {
+                // a single MSET sets every key or none of them
+                let mut cmd = redis::cmd("MSET");
+                for record in records {
+                    cmd.arg(record.get_id()).arg(record.to_string());
+                }
+                cmd.query::<()>(c)?;
             }
             None => {
                 return Err("Redis connection was None".into());
@@ -91,7 +113,7 @@ impl DatabaseRead for Redis {
     fn find(&mut self, id: &str) -> Result<(), Box<dyn Error>> {
         match self.connection.as_mut() {
             Some(c) => {
-                redis::cmd("HGET").arg(id).query(c)?;
+                redis::cmd("GET").arg(id).query::<()>(c)?;
             }
             None => return Err("Redis connection was None".into()),
         }
@@ -9,9 +9,9 @@ pub mod t {
 }
 
 use database::redis::Redis;
-use services::transaction_service::TransactionService;
+use services::transaction_service::{InvalidTransactionError, TransactionService};
 use t::transactor_server::Transactor;
-use t::{AddRequest, AddResponse, GetRequest, GetResponse};
+use t::{AddBatchRequest, AddBatchResponse, AddRequest, AddResponse, GetRequest, GetResponse};
 use tonic::transport::Server;
 use tonic::{Request, Response, Status};
 
@@ -47,11 +47,38 @@ impl Transactor for TransactionController {
         println!("Add Request {:?}", request);
         let res = match self.service.add_transaction(request.into_inner()).await {
             Ok(r) => r,
-            Err(e) => return Err(Status::new(tonic::Code::Internal, e.to_string())),
+            Err(e) => return Err(status(e)),
         };
 
         Ok(Response::new(res))
     }
+
+    async fn add_batch(
+        &self,
+        request: Request<AddBatchRequest>,
+    ) -> Result<Response<AddBatchResponse>, Status> {
+        let req = request.into_inner();
+        println!(
+            "Add batch request of {} transactions",
+            req.transactions.len()
+        );
+        let res = match self.service.add_transactions(req).await {
+            Ok(r) => r,
+            Err(e) => return Err(status(e)),
+        };
+
+        Ok(Response::new(res))
+    }
+}
+
+// transactions the client sent wrong are its error, the rest are the server's
+fn status(error: Box<dyn Error>) -> Status {
+    let code = match error.is::<InvalidTransactionError>() {
+        true => tonic::Code::InvalidArgument,
+        false => tonic::Code::Internal,
+    };
+
+    Status::new(code, error.to_string())
 }
 
 #[tokio::main]
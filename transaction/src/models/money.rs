@@ -0,0 +1,66 @@
+use std::{
+    error::Error,
+    fmt::{self, Display},
+    str::FromStr,
+};
+
+use rust_decimal::Decimal;
+
+// an exact amount in an ISO 4217 currency, e.g. -12.50 GBP
+pub struct Money {
+    pub amount: Decimal,
+    pub currency: String,
+}
+
+impl Money {
+    pub fn parse(amount: &str, currency: &str) -> Result<Self, Box<dyn Error>> {
+        let amount = Decimal::from_str(amount.trim())
+            .map_err(|e| format!("Invalid amount {:?}: {}", amount, e))?;
+
+        let currency = currency.trim().to_ascii_uppercase();
+        if currency.len() != 3 || !currency.chars().all(|c| c.is_ascii_alphabetic()) {
+            return Err(format!("Invalid currency code: {:?}", currency).into());
+        }
+
+        Ok(Self { amount, currency })
+    }
+}
+
+impl Display for Money {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} {}", self.amount, self.currency)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(amount: &str, currency: &str) -> Money {
+        Money::parse(amount, currency).unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    #[test]
+    fn amounts_are_read_exactly() {
+        let money = parse(" -12.50 ", "gbp");
+
+        assert_eq!(money.amount, Decimal::new(-1250, 2));
+        assert_eq!(money.currency, "GBP");
+        assert_eq!(money.to_string(), "-12.50 GBP");
+        assert_eq!(parse("0.1", "EUR").amount, Decimal::new(1, 1));
+    }
+
+    #[test]
+    fn invalid_amounts_are_rejected() {
+        for amount in ["", "12,50", "£12.50", "1e3x"] {
+            assert!(Money::parse(amount, "GBP").is_err(), "{:?}", amount);
+        }
+    }
+
+    #[test]
+    fn invalid_currencies_are_rejected() {
+        for currency in ["", "GB", "GBPS", "£££", "G1P"] {
+            assert!(Money::parse("1", currency).is_err(), "{:?}", currency);
+        }
+    }
+}
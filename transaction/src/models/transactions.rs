@@ -1,14 +1,18 @@
+use std::fmt::{self, Display};
+
 use chrono::{DateTime, Utc};
 
-use crate::services::transaction_service::GetId;
+use crate::{models::money::Money, services::transaction_service::GetId};
 
 pub struct Transaction {
     id: String,
-    amount: f32,
+    amount: Money,
     // think of a better name
     transaction_type: String,
     timestamp: DateTime<Utc>,
     user: String,
+    description: String,
+    account_type: String,
     category: Option<Category>,
     created_at: DateTime<Utc>,
     updated_at: DateTime<Utc>,
@@ -17,10 +21,12 @@ pub struct Transaction {
 
 impl Transaction {
     pub fn new(
-        amount: f32,
+        amount: Money,
         transaction_type: String,
         timestamp: DateTime<Utc>,
         user: String,
+        description: String,
+        account_type: String,
     ) -> Self {
         Self {
             id: uuid::Uuid::new_v4().to_string(),
@@ -28,6 +34,8 @@ impl Transaction {
             transaction_type,
             timestamp,
             user,
+            description,
+            account_type,
             category: None,
             created_at: Utc::now(),
             updated_at: Utc::now(),
@@ -42,20 +50,23 @@ impl GetId for Transaction {
     }
 }
 
-impl ToString for Transaction {
-    fn to_string(&self) -> String {
+impl Display for Transaction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         let cat = match &self.category {
             Some(c) => c.to_string(),
             None => "None".to_string(),
         };
 
-        format!(
-            "id: {}, amount: {}, transaction_type: {}, timestamp: {}, user: {}, category: {}, created_at: {}, updated_at: {}, deleted_at: {}",
+        write!(
+            f,
+            "id: {}, amount: {}, transaction_type: {}, timestamp: {}, user: {}, description: {}, account_type: {}, category: {}, created_at: {}, updated_at: {}, deleted_at: {}",
             self.id,
             self.amount,
             self.transaction_type,
             self.timestamp,
             self.user,
+            self.description,
+            self.account_type,
             cat,
             self.created_at,
             self.updated_at,
@@ -68,8 +79,8 @@ impl ToString for Transaction {
 
 struct Category;
 
-impl ToString for Category {
-    fn to_string(&self) -> String {
-        "category".to_string()
+impl Display for Category {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "category")
     }
 }
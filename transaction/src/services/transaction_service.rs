@@ -1,25 +1,41 @@
-use std::{error::Error, sync::Arc};
+use std::{error::Error, fmt::Display, sync::Arc};
 
-use chrono::Utc;
+use chrono::{DateTime, Utc};
 use tokio::sync::Mutex;
 
 use crate::{
-    models::transactions::Transaction,
-    t::{AddRequest, AddResponse, GetResponse},
+    models::{money::Money, transactions::Transaction},
+    t::{self, AddBatchRequest, AddBatchResponse, AddRequest, AddResponse, GetResponse},
 };
 
 // TODO: move the database stuff into the database module
 pub trait DatabaseInit {
     fn connect(&mut self) -> Result<(), Box<dyn Error>>;
+    #[allow(dead_code)]
     fn disconnect(&mut self) -> Result<(), Box<dyn Error>>;
 }
 
+// a transaction sent without an amount, or with an amount or timestamp that
+// cannot be read
+#[derive(Debug)]
+pub struct InvalidTransactionError(String);
+
+impl Error for InvalidTransactionError {}
+
+impl Display for InvalidTransactionError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "Invalid transaction: {}", self.0)
+    }
+}
+
 pub trait GetId {
     fn get_id(&self) -> String;
 }
 
 pub trait DatabaseWrite {
     fn save<T: ToString + GetId>(&mut self, record: T) -> Result<(), Box<dyn Error>>;
+    // saves all of the records or none of them
+    fn save_all<T: ToString + GetId>(&mut self, records: Vec<T>) -> Result<(), Box<dyn Error>>;
 }
 
 pub trait DatabaseRead {
@@ -55,7 +71,10 @@ where
 
         Ok(GetResponse {
             id: "1".to_string(),
-            amount: 1.0,
+            amount: Some(t::Money {
+                amount: "1.00".to_string(),
+                currency: "GBP".to_string(),
+            }),
             transaction_type: "test".to_string(),
             timestamp: 123,
             user: "user".to_string(),
@@ -73,17 +92,189 @@ where
         let mut database = self.db.lock().await;
         database.connect()?;
 
-        let timestamp = Utc::now();
+        database.save(new_transaction(request)?)?;
+
+        Ok(AddResponse { success: true })
+    }
 
-        let new_transaction = Transaction::new(
-            request.amount,
-            request.transaction_type,
-            timestamp,
-            request.user,
-        );
+    // saves all of the transactions or, when any of them is invalid or they
+    // cannot be saved, none of them
+    pub async fn add_transactions(
+        &self,
+        request: AddBatchRequest,
+    ) -> Result<AddBatchResponse, Box<dyn Error>> {
+        let transactions = request
+            .transactions
+            .into_iter()
+            .map(new_transaction)
+            .collect::<Result<Vec<_>, _>>()?;
+        let added = transactions.len() as i32;
 
-        let _ = database.save(new_transaction);
+        let mut database = self.db.lock().await;
+        database.connect()?;
+        database.save_all(transactions)?;
 
-        Ok(AddResponse { success: true })
+        Ok(AddBatchResponse { added })
+    }
+}
+
+fn new_transaction(request: AddRequest) -> Result<Transaction, InvalidTransactionError> {
+    let amount = request
+        .amount
+        .ok_or_else(|| InvalidTransactionError("no amount".to_string()))?;
+    let amount = Money::parse(&amount.amount, &amount.currency)
+        .map_err(|e| InvalidTransactionError(e.to_string()))?;
+
+    let timestamp = DateTime::parse_from_rfc3339(&request.timestamp)
+        .map(|t| t.with_timezone(&Utc))
+        .map_err(|e| {
+            InvalidTransactionError(format!("timestamp {:?}: {}", request.timestamp, e))
+        })?;
+
+    Ok(Transaction::new(
+        amount,
+        request.transaction_type,
+        timestamp,
+        request.user,
+        request.description,
+        request.account_type,
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // keeps the saved records in memory, saves fail once `broken` is set
+    #[derive(Default)]
+    struct Memory {
+        records: Vec<(String, String)>,
+        broken: bool,
+    }
+
+    impl DatabaseInit for Memory {
+        fn connect(&mut self) -> Result<(), Box<dyn Error>> {
+            Ok(())
+        }
+
+        fn disconnect(&mut self) -> Result<(), Box<dyn Error>> {
+            Ok(())
+        }
+    }
+
+    impl DatabaseWrite for Memory {
+        fn save<T: ToString + GetId>(&mut self, record: T) -> Result<(), Box<dyn Error>> {
+            self.save_all(vec![record])
+        }
+
+        fn save_all<T: ToString + GetId>(&mut self, records: Vec<T>) -> Result<(), Box<dyn Error>> {
+            if self.broken {
+                return Err("Database is broken".into());
+            }
+
+            self.records
+                .extend(records.iter().map(|r| (r.get_id(), r.to_string())));
+            Ok(())
+        }
+    }
+
+    impl DatabaseRead for Memory {
+        fn find(&mut self, _id: &str) -> Result<(), Box<dyn Error>> {
+            Ok(())
+        }
+    }
+
+    fn request(amount: &str, timestamp: &str) -> AddRequest {
+        AddRequest {
+            amount: Some(t::Money {
+                amount: amount.to_string(),
+                currency: "GBP".to_string(),
+            }),
+            transaction_type: "debit".to_string(),
+            timestamp: timestamp.to_string(),
+            user: "user".to_string(),
+            description: "Coffee".to_string(),
+            account_type: "Monzo".to_string(),
+        }
+    }
+
+    async fn saved(service: &TransactionService<Memory>) -> usize {
+        service.db.lock().await.records.len()
+    }
+
+    #[tokio::test]
+    async fn a_batch_is_saved_and_counted() {
+        let service = TransactionService::new(Memory::default());
+        let batch = AddBatchRequest {
+            transactions: vec![
+                request("-2.50", "2024-05-01T08:00:00Z"),
+                request("100", "2024-05-02T08:00:00+01:00"),
+            ],
+        };
+
+        let response = service
+            .add_transactions(batch)
+            .await
+            .unwrap_or_else(|e| panic!("{}", e));
+
+        assert_eq!(response.added, 2);
+        assert_eq!(saved(&service).await, 2);
+    }
+
+    #[tokio::test]
+    async fn a_batch_with_an_invalid_transaction_saves_none_of_it() {
+        let service = TransactionService::new(Memory::default());
+        let mut no_amount = request("1", "2024-05-01T08:00:00Z");
+        no_amount.amount = None;
+
+        for invalid in [
+            no_amount,
+            request("one", "2024-05-01T08:00:00Z"),
+            request("1", "01/05/2024"),
+        ] {
+            let batch = AddBatchRequest {
+                transactions: vec![request("-2.50", "2024-05-01T08:00:00Z"), invalid],
+            };
+
+            assert!(service.add_transactions(batch).await.is_err());
+        }
+        assert_eq!(saved(&service).await, 0);
+    }
+
+    #[tokio::test]
+    async fn a_batch_that_cannot_be_saved_is_an_error() {
+        let service = TransactionService::new(Memory {
+            broken: true,
+            ..Default::default()
+        });
+        let batch = AddBatchRequest {
+            transactions: vec![request("-2.50", "2024-05-01T08:00:00Z")],
+        };
+
+        assert!(service.add_transactions(batch).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn a_transaction_that_cannot_be_saved_is_an_error() {
+        let service = TransactionService::new(Memory {
+            broken: true,
+            ..Default::default()
+        });
+
+        let added = service
+            .add_transaction(request("-2.50", "2024-05-01T08:00:00Z"))
+            .await;
+
+        assert!(added.is_err());
+    }
+
+    #[tokio::test]
+    async fn a_transaction_without_a_valid_timestamp_is_rejected() {
+        let service = TransactionService::new(Memory::default());
+
+        let added = service.add_transaction(request("-2.50", "")).await;
+
+        assert!(added.is_err_and(|e| e.is::<InvalidTransactionError>()));
+        assert_eq!(saved(&service).await, 0);
     }
 }